@@ -0,0 +1,492 @@
+use crate::engine::output::{Info, Output, Response, Score};
+use crate::engine::{Engine, ENGINE_NAME};
+use crate::model::util::errors;
+use crate::model::*;
+use crate::uci::options::{self, OptionType};
+use crate::uci::{self, GoCommand, Position};
+use std::io::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+mod tests;
+
+// Scores of mates in xboard thinking output, plus or minus the moves to mate
+const MATE_SCORE: i32 = 100_000;
+// Draw offers are accepted from this move on when the engine's last score is at most this many centipawns
+const DRAW_MIN_MOVE: u32 = 30;
+const DRAW_MARGIN: i32 = 25;
+
+// What xboard sends, times in 'time' and 'otim' are in centiseconds
+pub enum Command {
+    Xboard,
+    Protover(u32),
+    New,
+    Variant(String),
+    Force,
+    Go,
+    PlayOther,
+    UserMove(String),
+    MoveNow,
+    Ping(String),
+    Draw,
+    Result { result: String, comment: String },
+    SetBoard(String),
+    Time(u64),
+    OpponentTime(u64),
+    // moves per time control, 0 for the whole game
+    Level { moves: u32, base: Duration, increment: Duration },
+    SearchTime(Duration),
+    SearchDepth(u8),
+    Undo,
+    Remove,
+    Post,
+    NoPost,
+    Analyze,
+    Exit,
+    Memory(usize),
+    Cores(usize),
+    SetOption { name: String, value: Option<String> },
+    // commands that need no answer, like 'accepted', 'computer' or 'name'
+    Ignored,
+}
+
+// The xboard front end over the UCI engine. Moves of the engine come from the background search, so the game
+// is shared with the output that plays them
+pub struct Xboard {
+    engine: Engine,
+    state: Arc<CecpState>,
+    // None in force mode, otherwise the side the engine plays
+    engine_color: Option<Color>,
+    clock: Clock,
+}
+
+// Time control from 'level', 'st', 'sd', 'time' and 'otim'
+#[derive(Clone, Copy, Default)]
+struct Clock {
+    moves_per_session: u32,
+    increment: Duration,
+    move_time: Option<Duration>,
+    depth: Option<u8>,
+    own_time: Option<Duration>,
+    opponent_time: Option<Duration>,
+}
+
+pub struct CecpState {
+    game: Mutex<Game>,
+    // the next bestmove is played, it is dropped when the search was only analysing or the position changed
+    play: AtomicBool,
+    post: AtomicBool,
+    analyzing: AtomicBool,
+    // of the engine's last search, from its own point of view
+    score: Mutex<Option<Score>>,
+    write: Box<dyn Fn(&str) + Send + Sync>,
+}
+
+impl Command {
+    pub fn parse(raw: &str) -> Result<Command> {
+        let mut split = raw.split_whitespace();
+        let command = split
+            .next()
+            .ok_or(errors::invalid_input(String::from("Unexpected empty xboard input")))?;
+        let rest: Vec<&str> = split.collect();
+        let argument = || rest.first().copied();
+        match command {
+            "xboard" => Ok(Command::Xboard),
+            "protover" => Ok(Command::Protover(parse_number(command, argument())?)),
+            "new" => Ok(Command::New),
+            "variant" => Ok(Command::Variant(rest.join(" "))),
+            "force" => Ok(Command::Force),
+            "go" => Ok(Command::Go),
+            "playother" => Ok(Command::PlayOther),
+            "usermove" => match argument() {
+                Some(mv) => Ok(Command::UserMove(String::from(mv))),
+                None => Err(errors::invalid_input(String::from("Expected a move after 'usermove'"))),
+            },
+            "?" => Ok(Command::MoveNow),
+            "ping" => Ok(Command::Ping(rest.join(" "))),
+            "draw" => Ok(Command::Draw),
+            "result" => Ok(Command::Result {
+                result: argument().map(String::from).unwrap_or_default(),
+                comment: rest.iter().skip(1).copied().collect::<Vec<&str>>().join(" "),
+            }),
+            "setboard" => Ok(Command::SetBoard(rest.join(" "))),
+            "time" => Ok(Command::Time(parse_number(command, argument())?)),
+            "otim" => Ok(Command::OpponentTime(parse_number(command, argument())?)),
+            "level" => parse_level(&rest),
+            "st" => Ok(Command::SearchTime(Duration::from_secs(parse_number(command, argument())?))),
+            "sd" => Ok(Command::SearchDepth(parse_number(command, argument())?)),
+            "undo" => Ok(Command::Undo),
+            "remove" => Ok(Command::Remove),
+            "post" => Ok(Command::Post),
+            "nopost" => Ok(Command::NoPost),
+            "analyze" => Ok(Command::Analyze),
+            "exit" => Ok(Command::Exit),
+            "memory" => Ok(Command::Memory(parse_number(command, argument())?)),
+            "cores" => Ok(Command::Cores(parse_number(command, argument())?)),
+            // option NAME[=VALUE], names may contain spaces
+            "option" => {
+                let option = rest.join(" ");
+                Ok(match option.split_once('=') {
+                    Some((name, value)) => Command::SetOption {
+                        name: String::from(name),
+                        value: Some(String::from(value)),
+                    },
+                    None => Command::SetOption { name: option, value: None },
+                })
+            }
+            "accepted" | "rejected" | "random" | "computer" | "name" | "rating" | "ics" | "hard" | "easy" | "hint" | "bk" | "."
+            | "post?" | "nps" => Ok(Command::Ignored),
+            // moves come without 'usermove' from interfaces that didn't take the feature
+            other if other.parse::<Move>().is_ok() => Ok(Command::UserMove(String::from(other))),
+            other => Err(errors::invalid_input(format!("Unexpected xboard input: '{other}'"))),
+        }
+    }
+}
+
+// 'level MPS BASE INC' with the base in minutes or minutes:seconds and the increment in seconds
+fn parse_level(arguments: &[&str]) -> Result<Command> {
+    let [moves, base, increment] = arguments else {
+        return Err(errors::invalid_input(String::from("Expected 'level moves base increment'")));
+    };
+    let (minutes, seconds) = base.split_once(':').unwrap_or((base, "0"));
+    let seconds = parse_number::<u64>("level", Some(minutes))? * 60 + parse_number::<u64>("level", Some(seconds))?;
+    Ok(Command::Level {
+        moves: parse_number("level", Some(moves))?,
+        base: Duration::from_secs(seconds),
+        increment: Duration::try_from_secs_f64(parse_number("level", Some(increment))?)
+            .map_err(|_| errors::invalid_input(format!("Unexpected increment '{increment}'")))?,
+    })
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: Option<&str>) -> Result<T> {
+    let value = value.ok_or(errors::invalid_input(format!("Expected a value after '{name}'")))?;
+    value
+        .parse()
+        .map_err(|_| errors::invalid_input(format!("{name} was not a number but '{value}'")))
+}
+
+impl Xboard {
+    // `write` gets every line sent to the interface
+    pub fn new(write: impl Fn(&str) + Send + Sync + 'static) -> Xboard {
+        let state = Arc::new(CecpState {
+            game: Mutex::new(Game::new()),
+            play: AtomicBool::new(false),
+            post: AtomicBool::new(false),
+            analyzing: AtomicBool::new(false),
+            score: Mutex::new(None),
+            write: Box::new(write),
+        });
+        Xboard {
+            engine: Engine::with_output(state.clone()),
+            state,
+            engine_color: Some(Color::Black),
+            clock: Clock::default(),
+        }
+    }
+
+    // Parses and runs one line of input, errors are sent as 'Error (...)'. Returns false on 'quit'
+    pub fn execute_line(&mut self, line: &str) -> bool {
+        let input = line.trim();
+        if input == "quit" {
+            self.stop(false);
+            return false;
+        }
+        if !input.is_empty() {
+            if let Err(err) = Command::parse(input).and_then(|command| self.execute(command)) {
+                self.state.write(&format!("Error ({err}): {input}"));
+            }
+        }
+        true
+    }
+
+    pub fn execute(&mut self, command: Command) -> Result<()> {
+        match command {
+            Command::Xboard | Command::Ignored => (),
+            Command::Draw => {
+                if self.accepts_draw() {
+                    self.state.write("offer draw");
+                }
+            }
+            Command::Protover(_) => self.features(),
+            Command::New => {
+                self.stop(false);
+                self.engine.execute_uci(uci::Command::NewGame)?;
+                *self.state.game.lock().unwrap() = Game::new();
+                *self.state.score.lock().unwrap() = None;
+                self.engine_color = Some(Color::Black);
+                self.clock.depth = None;
+                self.after_change()?;
+            }
+            Command::Variant(variant) if variant == "normal" => (),
+            Command::Variant(variant) => return Err(errors::invalid_input(format!("Unsupported variant {variant}"))),
+            Command::Force => {
+                self.stop(false);
+                self.engine_color = None;
+            }
+            Command::Go => {
+                // leaves analyze mode, the infinite search would never end
+                self.stop(false);
+                self.state.analyzing.store(false, Ordering::Relaxed);
+                self.engine_color = Some(self.game().board().turn);
+                self.think()?;
+            }
+            Command::PlayOther => self.engine_color = Some(self.game().board().turn.flip()),
+            Command::UserMove(notation) => {
+                self.stop(false);
+                let mv = notation.parse::<Move>();
+                let played = mv.and_then(|mv| self.state.game.lock().unwrap().make_move(&mv));
+                if played.is_err() {
+                    self.state.write(&format!("Illegal move: {notation}"));
+                    return Ok(());
+                }
+                self.report_result();
+                self.after_change()?;
+            }
+            Command::MoveNow => self.stop(true),
+            Command::Ping(number) => self.state.write(&format!("pong {number}")),
+            Command::Result { .. } => {
+                self.stop(false);
+                self.engine_color = None;
+            }
+            Command::SetBoard(fen) => {
+                self.stop(false);
+                let game = Game::from_fen(&fen).map_err(|_| errors::invalid_input(String::from("illegal position")))?;
+                *self.state.game.lock().unwrap() = game;
+                *self.state.score.lock().unwrap() = None;
+                self.after_change()?;
+            }
+            Command::Time(centiseconds) => self.clock.own_time = Some(Duration::from_millis(centiseconds * 10)),
+            Command::OpponentTime(centiseconds) => self.clock.opponent_time = Some(Duration::from_millis(centiseconds * 10)),
+            Command::Level { moves, base, increment } => {
+                self.clock.moves_per_session = moves;
+                self.clock.increment = increment;
+                self.clock.move_time = None;
+                self.clock.own_time.get_or_insert(base);
+                self.clock.opponent_time.get_or_insert(base);
+            }
+            Command::SearchTime(time) => self.clock.move_time = Some(time),
+            Command::SearchDepth(depth) => self.clock.depth = Some(depth),
+            Command::Undo | Command::Remove => {
+                self.stop(false);
+                let plies = if matches!(command, Command::Undo) { 1 } else { 2 };
+                for _ in 0..plies {
+                    self.state.game.lock().unwrap().takeback();
+                }
+                self.after_change()?;
+            }
+            Command::Post => self.state.post.store(true, Ordering::Relaxed),
+            Command::NoPost => self.state.post.store(false, Ordering::Relaxed),
+            Command::Analyze => {
+                self.stop(false);
+                self.engine_color = None;
+                self.state.analyzing.store(true, Ordering::Relaxed);
+                self.analyze()?;
+            }
+            Command::Exit => {
+                self.state.analyzing.store(false, Ordering::Relaxed);
+                self.stop(false);
+            }
+            Command::Memory(mb) => self.set_option(options::HASH, Some(mb.to_string()))?,
+            Command::Cores(cores) => self.set_option(options::THREADS, Some(cores.to_string()))?,
+            Command::SetOption { name, value } => self.set_option(&name, value)?,
+        }
+        Ok(())
+    }
+
+    pub fn game(&self) -> Game {
+        self.state.game.lock().unwrap().clone()
+    }
+
+    pub fn wait_for_search(&mut self) {
+        self.engine.wait_for_search();
+    }
+
+    fn features(&self) {
+        let mut features = vec![
+            String::from("done=0"),
+            String::from("ping=1"),
+            String::from("setboard=1"),
+            String::from("usermove=1"),
+            String::from("time=1"),
+            String::from("draw=1"),
+            String::from("sigint=0"),
+            String::from("sigterm=0"),
+            String::from("reuse=1"),
+            String::from("analyze=1"),
+            String::from("colors=0"),
+            String::from("san=0"),
+            String::from("memory=1"),
+            String::from("smp=1"),
+            String::from("variants=\"normal\""),
+            format!("myname=\"{ENGINE_NAME}\""),
+        ];
+        let hidden = [options::HASH, options::THREADS];
+        for option in options::OPTIONS.iter().filter(|option| !hidden.contains(&option.name)) {
+            let kind = match &option.kind {
+                OptionType::Spin { default, min, max } => format!("-spin {default} {min} {max}"),
+                OptionType::Check { default } => format!("-check {}", *default as u8),
+                OptionType::Combo { default, vars } => {
+                    let vars: Vec<String> = vars
+                        .iter()
+                        .map(|var| format!("{}{var}", if var == default { "*" } else { "" }))
+                        .collect();
+                    format!("-combo {}", vars.join(" /// "))
+                }
+                OptionType::String { default } => format!("-string {default}"),
+                OptionType::Button => String::from("-button"),
+            };
+            features.push(format!("option=\"{} {kind}\"", option.name));
+        }
+        features.push(String::from("done=1"));
+        for feature in features {
+            self.state.write(&format!("feature {feature}"));
+        }
+    }
+
+    // Only while playing, when the game is long enough and the engine doesn't see itself ahead
+    fn accepts_draw(&self) -> bool {
+        let score = match *self.state.score.lock().unwrap() {
+            Some(Score::Centipawns(score)) => score,
+            Some(Score::Mate(moves)) if moves < 0 => return self.engine_color.is_some(),
+            _ => return false,
+        };
+        self.engine_color.is_some() && self.game().fullmove_number() >= DRAW_MIN_MOVE && score <= DRAW_MARGIN
+    }
+
+    // Checks are sent as 0 and 1
+    fn set_option(&mut self, name: &str, value: Option<String>) -> Result<()> {
+        let value = match options::find(name).map(|option| &option.kind) {
+            Some(OptionType::Check { .. }) => value.map(|value| String::from(if value == "1" { "true" } else { "false" })),
+            _ => value,
+        };
+        self.engine.execute_uci(uci::Command::SetOption {
+            name: String::from(name),
+            value,
+        })
+    }
+
+    // With `play` the move found so far is played, otherwise the search is dropped
+    fn stop(&mut self, play: bool) {
+        if !play {
+            self.state.play.store(false, Ordering::Relaxed);
+        }
+        let _ = self.engine.execute_uci(uci::Command::Stop);
+    }
+
+    // After the position changed the engine analyses it again or answers when it is to move
+    fn after_change(&mut self) -> Result<()> {
+        if self.state.analyzing.load(Ordering::Relaxed) {
+            return self.analyze();
+        }
+        if self.engine_color == Some(self.game().board().turn) {
+            return self.think();
+        }
+        Ok(())
+    }
+
+    fn report_result(&self) {
+        let result = self.game().result();
+        if result.is_over() {
+            self.state.write(&format!("{} {{{result}}}", result.score()));
+        }
+    }
+
+    fn set_position(&mut self) -> Result<Game> {
+        let game = self.game();
+        self.engine.execute_uci(uci::Command::SetPosition {
+            position: Position::Fen(game.start_fen()),
+            moves: game.moves().to_vec(),
+        })?;
+        Ok(game)
+    }
+
+    fn think(&mut self) -> Result<()> {
+        let game = self.set_position()?;
+        if game.result().is_over() {
+            return Ok(());
+        }
+        let clock = self.clock;
+        let mut go = GoCommand {
+            depth: clock.depth,
+            move_time: clock.move_time.map(|time| time.as_millis() as u64),
+            ..GoCommand::default()
+        };
+        if clock.move_time.is_none() {
+            let millis = |time: Option<Duration>| time.map(|time| time.as_millis() as u64);
+            let increment = Some(clock.increment.as_millis() as u64);
+            let (own, opponent) = (millis(clock.own_time), millis(clock.opponent_time));
+            (go.white_time, go.black_time) = match game.board().turn {
+                Color::White => (own, opponent),
+                Color::Black => (opponent, own),
+            };
+            (go.white_increment, go.black_increment) = (increment, increment);
+            if clock.moves_per_session > 0 {
+                let played = game.fullmove_number().saturating_sub(1) % clock.moves_per_session;
+                go.moves_to_go = Some(clock.moves_per_session - played);
+            }
+        }
+        self.state.play.store(true, Ordering::Relaxed);
+        self.engine.execute_uci(uci::Command::Go(go))
+    }
+
+    fn analyze(&mut self) -> Result<()> {
+        self.stop(false);
+        let game = self.set_position()?;
+        if game.result().is_over() {
+            return Ok(());
+        }
+        self.engine.execute_uci(uci::Command::Go(GoCommand {
+            infinite: true,
+            ..GoCommand::default()
+        }))
+    }
+}
+
+impl CecpState {
+    fn write(&self, line: &str) {
+        (self.write)(line)
+    }
+
+    // 'depth score time nodes pv' with the time in centiseconds and the pv in SAN
+    fn thinking(&self, depth: u8, score: Score, time: Duration, nodes: u64, pv: &[Move]) {
+        let score = match score {
+            Score::Centipawns(score) => score,
+            Score::Mate(moves) if moves > 0 => MATE_SCORE + moves,
+            Score::Mate(moves) => -MATE_SCORE + moves,
+        };
+        let line = self.game.lock().unwrap().board().san_line(pv);
+        self.write(&format!("{depth} {score} {} {nodes} {}", time.as_millis() / 10, line.join(" ")));
+    }
+}
+
+// The engine's responses in xboard terms: the best move is played in the shared game
+impl Output for CecpState {
+    fn send(&self, response: Response) {
+        match response {
+            Response::BestMove { mv: Some(mv), .. } if self.play.swap(false, Ordering::Relaxed) => {
+                let played = self.game.lock().unwrap().make_move(&mv);
+                if played.is_ok() {
+                    self.write(&format!("move {}", mv.to_notation()));
+                    let result = self.game.lock().unwrap().result();
+                    if result.is_over() {
+                        self.write(&format!("{} {{{result}}}", result.score()));
+                    }
+                }
+            }
+            Response::Info(Info::Line {
+                stats,
+                multi_pv: 1,
+                score,
+                pv,
+            }) => {
+                *self.score.lock().unwrap() = Some(score);
+                if self.post.load(Ordering::Relaxed) || self.analyzing.load(Ordering::Relaxed) {
+                    self.thinking(stats.depth, score, stats.time, stats.nodes, &pv)
+                }
+            }
+            Response::Info(Info::String(message)) | Response::Error(message) => self.write(&format!("# {message}")),
+            _ => (),
+        }
+    }
+}
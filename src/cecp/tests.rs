@@ -0,0 +1,154 @@
+#[cfg(test)]
+use crate::cecp::*;
+
+#[cfg(test)]
+fn session() -> (Xboard, Arc<Mutex<Vec<String>>>) {
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let written = lines.clone();
+    let xboard = Xboard::new(move |line: &str| written.lock().unwrap().push(String::from(line)));
+    (xboard, lines)
+}
+
+#[cfg(test)]
+fn run(xboard: &mut Xboard, input: &[&str]) {
+    for line in input {
+        assert!(xboard.execute_line(line));
+    }
+    xboard.wait_for_search();
+}
+
+#[cfg(test)]
+fn last(lines: &Mutex<Vec<String>>, count: usize) -> Vec<String> {
+    let lines = lines.lock().unwrap();
+    lines[lines.len().saturating_sub(count)..].to_vec()
+}
+
+#[test]
+fn parse_commands() {
+    let Ok(Command::Level { moves, base, increment }) = Command::parse("level 40 2:30 0.5") else {
+        panic!("Expected level command")
+    };
+    assert_eq!((40, Duration::from_secs(150), Duration::from_millis(500)), (moves, base, increment));
+    let Ok(Command::Level { base, .. }) = Command::parse("level 0 5 0") else {
+        panic!("Expected level command")
+    };
+    assert_eq!(Duration::from_secs(300), base);
+    assert!(Command::parse("level 0 5").is_err());
+    assert!(matches!(Command::parse("time 1234"), Ok(Command::Time(1234))));
+    assert!(matches!(Command::parse("sd 4"), Ok(Command::SearchDepth(4))));
+    assert!(matches!(Command::parse("usermove e7e8q"), Ok(Command::UserMove(mv)) if mv == "e7e8q"));
+    assert!(matches!(Command::parse("e2e4"), Ok(Command::UserMove(mv)) if mv == "e2e4"));
+    assert!(matches!(Command::parse("setboard 8/8/8/8/8/8/8/K6k w - - 0 1"), Ok(Command::SetBoard(fen)) if fen.starts_with("8/8")));
+    let Ok(Command::SetOption { name, value }) = Command::parse("option Skill Level=5") else {
+        panic!("Expected option command")
+    };
+    assert_eq!(("Skill Level", Some("5")), (name.as_str(), value.as_deref()));
+    assert!(Command::parse("fly").is_err());
+}
+
+#[test]
+fn feature_negotiation() {
+    let (mut xboard, lines) = session();
+    run(&mut xboard, &["xboard", "protover 2", "ping 3"]);
+    let lines = lines.lock().unwrap();
+    assert_eq!("feature done=0", lines[0]);
+    assert!(lines.contains(&String::from("feature usermove=1")));
+    assert!(lines.contains(&String::from("feature draw=1")));
+    assert!(lines.contains(&String::from("feature option=\"Skill Level -spin 20 0 20\"")));
+    assert_eq!(["feature done=1", "pong 3"], lines[lines.len() - 2..]);
+}
+
+#[test]
+fn playing_a_game() {
+    let (mut xboard, lines) = session();
+    run(&mut xboard, &["new", "sd 2", "post", "usermove e2e4"]);
+    assert_eq!(2, xboard.game().moves().len());
+    let reply = xboard.game().moves()[1].to_notation();
+    assert_eq!(vec![format!("move {reply}")], last(&lines, 1));
+    // thinking lines are 'depth score centiseconds nodes pv' with the pv in SAN
+    let thinking: Vec<String> = lines
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|line| line.starts_with("2 "))
+        .cloned()
+        .collect();
+    assert_eq!(1, thinking.len());
+    let first = Board::new().make_move(&"e2e4".parse().unwrap());
+    let pv: Vec<&str> = thinking[0].split_whitespace().skip(4).collect();
+    assert_eq!(2, pv.len(), "{thinking:?}");
+    assert!(first.parse_san(pv[0]).is_ok(), "{thinking:?}");
+
+    run(&mut xboard, &["usermove e2e5"]);
+    assert_eq!(Some(&String::from("Illegal move: e2e5")), lines.lock().unwrap().last());
+    run(&mut xboard, &["force", "remove", "usermove d2d4", "usermove d7d5", "undo"]);
+    assert_eq!(1, xboard.game().moves().len());
+    run(&mut xboard, &["nopost", "go"]);
+    assert_eq!(2, xboard.game().moves().len());
+    assert!(lines.lock().unwrap().last().unwrap().starts_with("move "));
+}
+
+#[test]
+fn results_and_positions() {
+    let (mut xboard, lines) = session();
+    run(
+        &mut xboard,
+        &["new", "st 1", "sd 3", "setboard k7/6R1/8/8/8/8/8/6KR w - - 0 1", "go"],
+    );
+    assert_eq!(["move h1h8", "1-0 {white mates}"], last(&lines, 2)[..]);
+    run(&mut xboard, &["setboard not a position"]);
+    assert!(lines.lock().unwrap().last().unwrap().starts_with("Error (illegal position)"));
+    assert_eq!("k6R/6R1/8/8/8/8/8/6K1 b - - 1 1", xboard.game().to_fen());
+
+    // mating moves of either side are followed by the result
+    run(&mut xboard, &["new", "force", "f2f3", "e7e5", "g2g4", "go"]);
+    assert_eq!(["move d8h4", "0-1 {black mates}"], last(&lines, 2)[..]);
+    run(&mut xboard, &["undo", "force", "usermove d8h4"]);
+    assert_eq!(Some(&String::from("0-1 {black mates}")), lines.lock().unwrap().last());
+}
+
+#[test]
+fn analysis_mode() {
+    let (mut xboard, lines) = session();
+    for line in ["new", "analyze", "usermove e2e4"] {
+        assert!(xboard.execute_line(line));
+    }
+    std::thread::sleep(Duration::from_millis(200));
+    run(&mut xboard, &["exit"]);
+    let lines = lines.lock().unwrap();
+    assert!(lines.iter().any(|line| line.starts_with("1 ")));
+    assert!(!lines.iter().any(|line| line.starts_with("move ")));
+    assert_eq!(1, xboard.game().moves().len());
+}
+
+#[test]
+fn go_in_analysis_mode() {
+    let (mut xboard, lines) = session();
+    run(&mut xboard, &["new", "sd 2"]);
+    for line in ["analyze", "go", "ping 7"] {
+        assert!(xboard.execute_line(line));
+    }
+    xboard.wait_for_search();
+    let lines = lines.lock().unwrap();
+    assert!(lines.contains(&String::from("pong 7")));
+    assert!(lines.iter().any(|line| line.starts_with("move ")), "{lines:?}");
+    assert_eq!(1, xboard.game().moves().len());
+}
+
+#[test]
+fn draw_offers() {
+    let (mut xboard, lines) = session();
+    // level late in the game, the offer comes with the opponent's move
+    run(&mut xboard, &["new", "sd 3", "setboard 4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 40", "draw", "e1d1"]);
+    assert!(lines.lock().unwrap().last().unwrap().starts_with("move "));
+    run(&mut xboard, &["draw"]);
+    assert_eq!(Some(&String::from("offer draw")), lines.lock().unwrap().last());
+
+    // declined with a rook more, too early in the game or while not playing
+    run(&mut xboard, &["setboard r3k3/4p3/8/8/8/8/4P3/4K3 w - - 0 40", "e1d1", "draw"]);
+    assert!(lines.lock().unwrap().last().unwrap().starts_with("move "));
+    run(&mut xboard, &["setboard 4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 10", "e1d1", "draw"]);
+    assert!(lines.lock().unwrap().last().unwrap().starts_with("move "));
+    run(&mut xboard, &["setboard 4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 40", "e1d1", "force", "draw"]);
+    assert!(lines.lock().unwrap().last().unwrap().starts_with("move "));
+}
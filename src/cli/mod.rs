@@ -0,0 +1,344 @@
+use crust::console::Console;
+use crust::engine::analysis;
+use crust::engine::book::BookBuilder;
+use crust::engine::output::Response;
+use crust::engine::perft::suite::{self, SuiteRunner};
+use crust::engine::searcher::SearchLimits;
+use crust::engine::skill;
+use crust::engine::solver::{self, SuiteScore};
+use crust::engine::tablebase::{Signature, Tables};
+use crust::engine::tournament::{self, MatchSettings, Sprt, TimeControl};
+use crust::engine::{Engine, Settings};
+use crust::model::epd;
+use crust::model::pgn::{self, PgnGame};
+use crust::model::util::errors;
+use crust::model::*;
+use crust::uci::client::UciClient;
+use crust::uci::Command;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+mod tests;
+
+pub const USAGE: &str = "Usage: crust [bench [depth] | book <games.pgn> <book.bin> [--depth plies] [--min-count games] \
+    | tablebase <directory> <signature>... \
+    | perft-suite <suite.epd> [--depth plies] [--hash MB] [--threads count] [--reference engine] \
+    | solve-suite <suite.epd> [--time ms | --nodes count | --depth plies] [--hash MB] [--threads count] \
+    | match <engine> <engine> [--tc seconds+increment] [--games count] [--openings file] [--pgn file] [--concurrency count] \
+    [--sprt elo0 elo1] \
+    | calibrate [--levels list | --elo list] [--depths list] [--games count] [--time ms] [--openings file] \
+    | play [--color white|black] [--time ms | --depth plies] [--unicode] \
+    | analyze <fen | games.pgn> [--time ms | --depth plies] [--output annotated.pgn] [--hash MB] [--threads count]]";
+
+// Prints the node count of the bench positions, the same for the same code on every machine
+pub fn bench(args: &[String]) -> io::Result<()> {
+    let line = format!("bench {}", args.join(" "));
+    Engine::new().execute_uci(Command::parse(&line)?)
+}
+
+pub fn build_book(args: &[String]) -> io::Result<()> {
+    let [pgn, book, ..] = args else {
+        return Err(usage());
+    };
+    let mut builder = BookBuilder::new(flag(args, "--depth", 16)?, flag(args, "--min-count", 1)?);
+    let games = builder.add_pgn(&std::fs::read_to_string(pgn)?)?;
+    let built = builder.build();
+    built.save(Path::new(book))?;
+    println!("{} entries from {games} games written to {book}", built.len());
+    Ok(())
+}
+
+// Generates the tables into the directory, along with the smaller ones they need unless the directory has them already
+pub fn generate_tables(args: &[String]) -> io::Result<()> {
+    let [directory, signatures @ ..] = args else {
+        return Err(usage());
+    };
+    let signatures = signatures.iter().map(|name| name.parse()).collect::<io::Result<Vec<Signature>>>()?;
+    if signatures.is_empty() {
+        return Err(usage());
+    }
+    let directory = Path::new(directory);
+    let mut tables = if directory.is_dir() {
+        Tables::open(directory)?
+    } else {
+        Tables::new()
+    };
+    for signature in &signatures {
+        for generated in tables.generate(signature) {
+            let table = tables.get(&generated).unwrap();
+            println!(
+                "{generated}: {} positions, longest mate {} plies",
+                table.len(),
+                table.longest_mate()
+            );
+        }
+    }
+    tables.save(directory)?;
+    println!("{} tables written to {}", tables.len(), directory.display());
+    Ok(())
+}
+
+// Counts every position of the suite up to the depth, the first mismatch is bisected by divide against the reference engine,
+// stockfish from the PATH unless one is given
+pub fn perft_suite(args: &[String]) -> io::Result<()> {
+    let [file, ..] = args else {
+        return Err(usage());
+    };
+    let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+    let runner = SuiteRunner::new(flag(args, "--hash", 64)?, flag(args, "--threads", threads)?);
+    let mut reference = match argument(args, "--reference")? {
+        Some(command) => Some(UciClient::start(command)?),
+        None => UciClient::start("stockfish").ok(),
+    };
+    let positions = suite::parse_suite(&std::fs::read_to_string(file)?)?;
+    let start = Instant::now();
+    let (passed, failed) = runner.run(&positions, flag(args, "--depth", 5)?, reference.as_mut(), |line| println!("{line}"))?;
+    println!("{passed} passed, {failed} failed in {}ms", start.elapsed().as_millis());
+    match failed {
+        0 => Ok(()),
+        _ => Err(errors::invalid_input(format!("{failed} perft counts differ"))),
+    }
+}
+
+// Searches every position of a 'bm', 'am', 'dm' or STS suite, a second per position unless nodes or depth are given
+pub fn solve_suite(args: &[String]) -> io::Result<()> {
+    let [file, ..] = args else {
+        return Err(usage());
+    };
+    let positions = epd::parse_epd(&std::fs::read_to_string(file)?)?;
+    let mut engine = engine(args)?;
+    let limits = search_limits(args, 1000)?;
+    let mut total = SuiteScore::default();
+    for (number, position) in positions.iter().enumerate() {
+        match engine.solve(position, &limits) {
+            Ok(solution) => {
+                total.add(&solution);
+                println!("{}", solution.report(position, number));
+            }
+            Err(err) => {
+                total.add_error();
+                println!("{}", solver::error_report(position, number, &err));
+            }
+        }
+    }
+    println!("{total}");
+    Ok(())
+}
+
+// Plays the first engine against the second, the score, Elo and the SPRT are those of the first after every game
+pub fn play_match(args: &[String]) -> io::Result<()> {
+    let [first, second, ..] = args else {
+        return Err(usage());
+    };
+    let sprt = match args.iter().position(|arg| arg == "--sprt") {
+        None => None,
+        Some(index) => {
+            let bound = |offset: usize| args.get(index + offset).and_then(|value| value.parse().ok());
+            let (Some(elo0), Some(elo1)) = (bound(1), bound(2)) else {
+                return Err(errors::invalid_input(String::from("Expected elo0 and elo1 after --sprt")));
+            };
+            Some(Sprt::new(elo0, elo1))
+        }
+    };
+    let settings = MatchSettings {
+        engines: [first.clone(), second.clone()],
+        time_control: flag(args, "--tc", String::from("10+0.1"))?.parse::<TimeControl>()?,
+        games: flag(args, "--games", 100)?,
+        concurrency: flag(args, "--concurrency", 1)?,
+        sprt,
+    };
+    let openings = openings(args)?;
+    let mut pgn = argument(args, "--pgn")?.map(std::fs::File::create).transpose()?;
+
+    let mut written = Ok(());
+    let score = tournament::run_match(&settings, &openings, |played, score, names| {
+        println!("{}", played.report(names));
+        println!("Score of {} vs {}: {score}", names[0], names[1]);
+        println!("{}", score.elo_difference());
+        if let Some(sprt) = settings.sprt {
+            println!("{}", sprt.report(score));
+        }
+        if let (Some(file), Ok(())) = (pgn.as_mut(), &written) {
+            written = writeln!(file, "{}", played.to_pgn(names));
+        }
+    })?;
+    written?;
+    if let Some(sprt) = settings.sprt {
+        println!("{}", sprt.verdict(&score));
+    }
+    Ok(())
+}
+
+// Plays every skill level or Elo against full strength searches of fixed depths, the levels get the time per move
+// before their own limits apply
+pub fn calibrate(args: &[String]) -> io::Result<()> {
+    let list = |name: &str, default: &str| -> io::Result<Vec<u32>> {
+        flag(args, name, String::from(default))?
+            .split(',')
+            .map(|value| {
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| errors::invalid_input(format!("Expected numbers after {name}")))
+            })
+            .collect()
+    };
+    let strengths: Vec<(String, Settings)> = match args.contains(&String::from("--elo")) {
+        true => list("--elo", "")?
+            .into_iter()
+            .map(|elo| {
+                let settings = Settings {
+                    limit_strength: true,
+                    elo,
+                    ..Settings::default()
+                };
+                (format!("UCI_Elo {elo}"), settings)
+            })
+            .collect(),
+        false => list("--levels", "0,5,10,15,20")?
+            .into_iter()
+            .map(|level| {
+                let settings = Settings {
+                    skill_level: level.min(skill::MAX_LEVEL as u32) as u8,
+                    ..Settings::default()
+                };
+                (format!("Skill Level {level}"), settings)
+            })
+            .collect(),
+    };
+    let depths = list("--depths", "1,2,3,4")?;
+    let games = flag(args, "--games", 10)?;
+    let limits = search_limits(args, 100)?;
+    let openings = openings(args)?;
+
+    for (name, settings) in &strengths {
+        for &depth in &depths {
+            let score = skill::calibrate(settings, &limits, depth as u8, games, &openings, |_, _| ());
+            println!("{name} vs depth {depth}: {score}, {}", score.elo_difference());
+        }
+    }
+    Ok(())
+}
+
+// A game against the engine in the terminal, 'help' lists the commands
+pub fn play(args: &[String]) -> io::Result<()> {
+    let human = match flag(args, "--color", String::from("white"))?.as_str() {
+        "white" => Color::White,
+        "black" => Color::Black,
+        _ => return Err(usage()),
+    };
+    let unicode = args.contains(&String::from("--unicode"));
+    let mut console = Console::new(|text: &str| println!("{text}"), human, search_limits(args, 1000)?, unicode);
+    console.start();
+    let mut lines = io::stdin().lines();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+        let Some(line) = lines.next() else {
+            return Ok(());
+        };
+        if !console.execute_line(&line?) {
+            return Ok(());
+        }
+    }
+}
+
+// Best move, score and PV of a FEN, or every move of the games in a PGN file judged against the best one. The games
+// are written annotated to '--output file', or after the report without it
+pub fn analyze(args: &[String]) -> io::Result<()> {
+    let [input, ..] = args else {
+        return Err(usage());
+    };
+    let mut engine = engine(args)?;
+    let limits = search_limits(args, 1000)?;
+    if !input.ends_with(".pgn") {
+        engine.game = Game::from_fen(input)?;
+        println!("{}", engine.analyze_position(&limits));
+        return Ok(());
+    }
+    let mut annotated = Vec::new();
+    for (number, game) in pgn::parse_games(&std::fs::read_to_string(input)?)?.iter().enumerate() {
+        let name = |tag: &str| game.tag(tag).unwrap_or("?").to_string();
+        println!("Game {}: {} - {}", number + 1, name("White"), name("Black"));
+        let moves = engine.analyze_game(&game.game, &limits, |analysis| println!("{analysis}"));
+        for color in Color::ALL {
+            println!("{}", analysis::summary(&moves, color));
+        }
+        annotated.push(analysis::annotate(game, &moves));
+    }
+    let annotated: Vec<String> = annotated.iter().map(PgnGame::to_string).collect();
+    match argument(args, "--output")? {
+        None => print!("\n{}", annotated.join("\n")),
+        Some(file) => std::fs::write(file, annotated.join("\n"))?,
+    }
+    Ok(())
+}
+
+pub fn usage() -> io::Error {
+    errors::invalid_input(String::from(USAGE))
+}
+
+// Value of an optional '--name value' argument
+pub fn flag<T: FromStr>(args: &[String], name: &str, default: T) -> io::Result<T> {
+    Ok(optional(args, name)?.unwrap_or(default))
+}
+
+// Value of a '--name value' argument, None without it
+pub fn optional<T: FromStr>(args: &[String], name: &str) -> io::Result<Option<T>> {
+    match argument(args, name)? {
+        None => Ok(None),
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| errors::invalid_input(format!("Unexpected value '{value}' after {name}"))),
+    }
+}
+
+// The word after '--name', like a file or an engine command
+pub fn argument<'a>(args: &'a [String], name: &str) -> io::Result<Option<&'a str>> {
+    match args.iter().position(|arg| arg == name) {
+        None => Ok(None),
+        Some(index) => match args.get(index + 1) {
+            Some(value) => Ok(Some(value.as_str())),
+            None => Err(errors::invalid_input(format!("Expected a value after {name}"))),
+        },
+    }
+}
+
+// '--depth plies', '--nodes count' and '--time ms' per search, `default_time` milliseconds without any of them
+pub fn search_limits(args: &[String], default_time: u64) -> io::Result<SearchLimits> {
+    let (depth, nodes) = (optional(args, "--depth")?, optional(args, "--nodes")?);
+    let time = match (depth, nodes) {
+        (None, None) => Some(flag(args, "--time", default_time)?),
+        _ => optional(args, "--time")?,
+    };
+    Ok(SearchLimits {
+        depth,
+        nodes,
+        move_time: time.map(Duration::from_millis),
+        ..SearchLimits::default()
+    })
+}
+
+// An engine without output for the subcommands, with '--hash MB' and '--threads count'
+pub fn engine(args: &[String]) -> io::Result<Engine> {
+    let mut engine = Engine::with_output(Arc::new(|_: Response| ()));
+    for (name, value) in [("Hash", flag(args, "--hash", 64)?), ("Threads", flag(args, "--threads", 1)?)] {
+        engine.execute_uci(Command::SetOption {
+            name: String::from(name),
+            value: Some(value.to_string()),
+        })?;
+    }
+    Ok(engine)
+}
+
+// From '--openings file' with PGN games or EPD positions, the start position without it
+pub fn openings(args: &[String]) -> io::Result<Vec<Game>> {
+    match argument(args, "--openings")? {
+        None => tournament::load_openings("", false),
+        Some(file) => tournament::load_openings(&std::fs::read_to_string(file)?, file.ends_with(".pgn")),
+    }
+}
@@ -0,0 +1,39 @@
+#[cfg(test)]
+use crate::cli::*;
+
+#[cfg(test)]
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn flags_and_arguments() {
+    let args = args("suite.epd --depth 6 --pgn games.pgn --threads");
+    assert_eq!(6, flag(&args, "--depth", 1).unwrap());
+    assert_eq!(64, flag(&args, "--hash", 64).unwrap());
+    assert_eq!(Some("games.pgn"), argument(&args, "--pgn").unwrap());
+    assert_eq!(None, argument(&args, "--output").unwrap());
+    assert!(flag(&args, "--threads", 1).is_err());
+    assert!(optional::<u8>(&args, "--pgn").is_err());
+}
+
+#[test]
+fn limits_from_arguments() {
+    let default = search_limits(&args("analyze game.pgn"), 1000).unwrap();
+    assert_eq!(
+        (None, None, Some(Duration::from_millis(1000))),
+        (default.depth, default.nodes, default.move_time)
+    );
+    let depth = search_limits(&args("--depth 8"), 1000).unwrap();
+    assert_eq!((Some(8), None), (depth.depth, depth.move_time));
+    let both = search_limits(&args("--nodes 5000 --time 200"), 1000).unwrap();
+    assert_eq!((Some(5000), Some(Duration::from_millis(200))), (both.nodes, both.move_time));
+}
+
+#[test]
+fn engine_options() {
+    let engine = engine(&args("--hash 16 --threads 2")).unwrap();
+    assert_eq!((16, 2), (engine.settings.hash_mb, engine.settings.threads));
+    assert!(crate::cli::engine(&args("--threads 0")).is_err());
+    assert_eq!(1, openings(&[]).unwrap().len());
+}
@@ -0,0 +1,211 @@
+use crate::engine::output::Response;
+use crate::engine::searcher::SearchLimits;
+use crate::engine::{Engine, ENGINE_NAME};
+use crate::model::pgn::{self, PgnGame};
+use crate::model::util::errors;
+use crate::model::*;
+use std::io::Result;
+use std::sync::Arc;
+
+mod tests;
+
+const HELP: &str = "Moves in SAN (Nf3) or coordinates (g1f3). Commands: undo, flip, new [white|black], save <file.pgn>, \
+    load <file.pgn>, hint, go, board, help, quit";
+
+// What a human types at the console, anything that isn't a command is taken for a move
+pub enum Command {
+    Move(String),
+    Undo,
+    Flip,
+    // the color the human plays in the new game, the same as before without it
+    New(Option<Color>),
+    Save(String),
+    Load(String),
+    Hint,
+    // the engine takes over the side to move
+    Go,
+    Board,
+    Help,
+    Quit,
+}
+
+// A game of a human against the engine in the terminal. The engine answers every move of the human right away
+pub struct Console {
+    engine: Engine,
+    human: Color,
+    flipped: bool,
+    unicode: bool,
+    limits: SearchLimits,
+    write: Box<dyn Fn(&str)>,
+}
+
+impl Command {
+    pub fn parse(raw: &str) -> Result<Command> {
+        let mut split = raw.split_whitespace();
+        let command = split
+            .next()
+            .ok_or(errors::invalid_input(String::from("Unexpected empty console input")))?;
+        let argument = split.next();
+        let path = |command: &str| {
+            argument
+                .map(String::from)
+                .ok_or(errors::invalid_input(format!("Expected a file after '{command}'")))
+        };
+        match command {
+            "undo" => Ok(Command::Undo),
+            "flip" => Ok(Command::Flip),
+            "new" => match argument {
+                None => Ok(Command::New(None)),
+                Some("white") => Ok(Command::New(Some(Color::White))),
+                Some("black") => Ok(Command::New(Some(Color::Black))),
+                Some(other) => Err(errors::invalid_input(format!(
+                    "Unexpected color '{other}', expected white or black"
+                ))),
+            },
+            "save" => Ok(Command::Save(path(command)?)),
+            "load" => Ok(Command::Load(path(command)?)),
+            "hint" => Ok(Command::Hint),
+            "go" => Ok(Command::Go),
+            "board" | "d" => Ok(Command::Board),
+            "help" | "?" => Ok(Command::Help),
+            "quit" | "exit" => Ok(Command::Quit),
+            mv => Ok(Command::Move(String::from(mv))),
+        }
+    }
+}
+
+impl Console {
+    pub fn new(write: impl Fn(&str) + 'static, human: Color, limits: SearchLimits, unicode: bool) -> Console {
+        Console {
+            engine: Engine::with_output(Arc::new(|_: Response| ())),
+            human,
+            flipped: human == Color::Black,
+            unicode,
+            limits,
+            write: Box::new(write),
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.engine.game
+    }
+
+    // Draws the board and lets the engine open the game when the human plays black
+    pub fn start(&mut self) {
+        self.show();
+        self.reply();
+    }
+
+    // False once the human quits
+    pub fn execute_line(&mut self, line: &str) -> bool {
+        if line.trim().is_empty() {
+            return true;
+        }
+        match Command::parse(line) {
+            Ok(Command::Quit) => false,
+            Ok(command) => {
+                if let Err(err) = self.execute(command) {
+                    (self.write)(&err.to_string());
+                }
+                true
+            }
+            Err(err) => {
+                (self.write)(&err.to_string());
+                true
+            }
+        }
+    }
+
+    pub fn execute(&mut self, command: Command) -> Result<()> {
+        match command {
+            Command::Move(mv) => {
+                if self.game().result().is_over() {
+                    return Err(errors::invalid_input(String::from("The game is over, start a new one with 'new'")));
+                }
+                let game = &mut self.engine.game;
+                let played = match mv.parse::<Move>() {
+                    Ok(parsed) => game.make_move(&parsed),
+                    Err(_) => game.make_san_move(&mv),
+                };
+                if played.is_err() {
+                    return Err(errors::invalid_input(format!("Illegal move: {mv}")));
+                }
+                self.show();
+                self.reply();
+            }
+            Command::Undo => {
+                // back to the last position the human had to move in
+                let mut taken = self.engine.game.takeback().is_some();
+                while taken && self.game().board().turn != self.human {
+                    taken = self.engine.game.takeback().is_some();
+                }
+                self.show();
+            }
+            Command::Flip => {
+                self.flipped = !self.flipped;
+                self.show();
+            }
+            Command::New(color) => {
+                self.human = color.unwrap_or(self.human);
+                self.flipped = self.human == Color::Black;
+                self.engine.game = Game::new();
+                self.start();
+            }
+            Command::Save(path) => {
+                let mut pgn = PgnGame::from(self.game().clone());
+                let (white, black) = match self.human {
+                    Color::White => ("Human", ENGINE_NAME),
+                    Color::Black => (ENGINE_NAME, "Human"),
+                };
+                pgn.set_tag("White", white);
+                pgn.set_tag("Black", black);
+                std::fs::write(&path, pgn.to_string())?;
+                (self.write)(&format!("Saved the game to {path}"));
+            }
+            Command::Load(path) => {
+                let games = pgn::parse_games(&std::fs::read_to_string(&path)?)?;
+                let loaded = games
+                    .into_iter()
+                    .next()
+                    .ok_or(errors::invalid_input(format!("No game in {path}")))?;
+                self.engine.game = loaded.game;
+                self.show();
+            }
+            Command::Hint => {
+                if let Some(mv) = self.engine.search(&self.limits) {
+                    (self.write)(&format!("Hint: {}", self.game().board().to_san(&mv)));
+                }
+            }
+            Command::Go => {
+                self.human = self.human.flip();
+                self.reply();
+            }
+            Command::Board => self.show(),
+            Command::Help => (self.write)(HELP),
+            Command::Quit => {}
+        }
+        Ok(())
+    }
+
+    // The engine's move when it is its turn, and the result once the game is over
+    fn reply(&mut self) {
+        let engine_move = match !self.game().result().is_over() && self.game().board().turn != self.human {
+            true => self.engine.search(&self.limits),
+            false => None,
+        };
+        if let Some(mv) = engine_move {
+            let san = self.game().board().to_san(&mv);
+            self.engine.game.make_move(&mv).expect("The engine plays legal moves");
+            (self.write)(&format!("{ENGINE_NAME} plays {san}"));
+            self.show();
+        }
+        let result = self.game().result();
+        if result.is_over() {
+            (self.write)(&format!("Game over: {} {{{result}}}", result.score()));
+        }
+    }
+
+    fn show(&self) {
+        (self.write)(&self.engine.describe(self.unicode, self.flipped));
+    }
+}
@@ -0,0 +1,112 @@
+#[cfg(test)]
+use crate::console::*;
+#[cfg(test)]
+use std::sync::Mutex;
+
+#[cfg(test)]
+fn session(human: Color) -> (Console, Arc<Mutex<Vec<String>>>) {
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let written = lines.clone();
+    let limits = SearchLimits {
+        depth: Some(2),
+        ..SearchLimits::default()
+    };
+    let console = Console::new(
+        move |text: &str| written.lock().unwrap().push(String::from(text)),
+        human,
+        limits,
+        false,
+    );
+    (console, lines)
+}
+
+#[cfg(test)]
+fn run(console: &mut Console, input: &[&str]) {
+    for line in input {
+        assert!(console.execute_line(line));
+    }
+}
+
+#[test]
+fn parse_commands() {
+    assert!(matches!(Command::parse("e2e4"), Ok(Command::Move(mv)) if mv == "e2e4"));
+    assert!(matches!(Command::parse("Nf3"), Ok(Command::Move(mv)) if mv == "Nf3"));
+    assert!(matches!(Command::parse("new black"), Ok(Command::New(Some(Color::Black)))));
+    assert!(matches!(Command::parse("new"), Ok(Command::New(None))));
+    assert!(Command::parse("new red").is_err());
+    assert!(matches!(Command::parse("save game.pgn"), Ok(Command::Save(path)) if path == "game.pgn"));
+    assert!(Command::parse("load").is_err());
+    assert!(matches!(Command::parse("quit"), Ok(Command::Quit)));
+}
+
+#[test]
+fn moves_and_replies() {
+    let (mut console, lines) = session(Color::White);
+    console.start();
+    run(&mut console, &["e4", "g1f3"]);
+    assert_eq!(4, console.game().moves().len());
+    assert_eq!("e2e4", console.game().moves()[0].to_string());
+    assert_eq!("g1f3", console.game().moves()[2].to_string());
+    assert!(lines
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|line| line.starts_with(&format!("{ENGINE_NAME} plays "))));
+
+    run(&mut console, &["e2e5"]);
+    assert_eq!("Illegal move: e2e5", lines.lock().unwrap().last().unwrap());
+    assert_eq!(4, console.game().moves().len());
+
+    run(&mut console, &["undo"]);
+    assert_eq!(2, console.game().moves().len());
+    assert_eq!(Color::White, console.game().board().turn);
+    assert!(!console.execute_line("quit"));
+}
+
+#[test]
+fn engine_opens_as_white() {
+    let (mut console, lines) = session(Color::Black);
+    console.start();
+    assert_eq!(1, console.game().moves().len());
+    // black sits at the bottom
+    let board = lines.lock().unwrap().last().unwrap().clone();
+    assert!(board.lines().nth(1).unwrap().starts_with("1 |"));
+
+    run(&mut console, &["flip"]);
+    let board = lines.lock().unwrap().last().unwrap().clone();
+    assert!(board.lines().nth(1).unwrap().starts_with("8 |"));
+
+    run(&mut console, &["new white"]);
+    assert!(console.game().moves().is_empty());
+}
+
+#[test]
+fn hint_and_game_over() {
+    let (mut console, lines) = session(Color::White);
+    console.engine.game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    run(&mut console, &["hint"]);
+    assert_eq!("Hint: Ra8#", lines.lock().unwrap().last().unwrap());
+    assert!(console.game().moves().is_empty());
+
+    run(&mut console, &["Ra8"]);
+    assert_eq!("Game over: 1-0 {white mates}", lines.lock().unwrap().last().unwrap());
+    run(&mut console, &["Kg2"]);
+    assert!(lines.lock().unwrap().last().unwrap().starts_with("The game is over"));
+}
+
+#[test]
+fn save_and_load() {
+    let path = std::env::temp_dir().join(format!("crust-console-{}.pgn", std::process::id()));
+    let path = path.to_str().unwrap();
+    let (mut console, _) = session(Color::Black);
+    console.start();
+    run(&mut console, &["e5", &format!("save {path}")]);
+    let saved = std::fs::read_to_string(path).unwrap();
+    assert!(saved.contains("[White \"crust"));
+    assert!(saved.contains("[Black \"Human\"]"));
+
+    let (mut loaded, _) = session(Color::Black);
+    run(&mut loaded, &[&format!("load {path}")]);
+    assert_eq!(console.game().moves(), loaded.game().moves());
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,221 @@
+use crate::engine::evaluation::MATE_BOUND;
+use crate::engine::output::Score;
+use crate::engine::searcher::SearchLimits;
+use crate::engine::{Engine, ENGINE_NAME};
+use crate::model::pgn::PgnGame;
+use crate::model::*;
+use std::fmt::{Display, Formatter};
+
+mod tests;
+
+// Least drop of the score in centipawns, from the best move to the one played, for each judgement
+const INACCURACY: i32 = 50;
+const MISTAKE: i32 = 100;
+const BLUNDER: i32 = 300;
+// Scores are capped before the drop is taken, so a slower mate or a bigger win thrown away in a won position is no blunder
+const SCORE_CAP: i32 = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Judgement {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+// The engine's view of a position with the score from the side to move, positions without legal moves have no best move
+#[derive(Clone, Debug)]
+pub struct PositionAnalysis {
+    pub turn: Color,
+    pub best: Option<String>,
+    pub score: i32,
+    pub depth: u8,
+    pub pv: Vec<String>,
+}
+
+// A move of the game against the best one in the position it was played in
+#[derive(Clone, Debug)]
+pub struct MoveAnalysis {
+    pub number: u32,
+    pub san: String,
+    pub before: PositionAnalysis,
+    pub after: PositionAnalysis,
+    // centipawns lost against the best move, 0 when it was played
+    pub loss: i32,
+    pub judgement: Option<Judgement>,
+}
+
+impl Judgement {
+    pub fn from_loss(loss: i32) -> Option<Judgement> {
+        match loss {
+            loss if loss >= BLUNDER => Some(Judgement::Blunder),
+            loss if loss >= MISTAKE => Some(Judgement::Mistake),
+            loss if loss >= INACCURACY => Some(Judgement::Inaccuracy),
+            _ => None,
+        }
+    }
+
+    // '$6' for '?!', '$2' for '?' and '$4' for '??'
+    pub fn nag(self) -> u8 {
+        match self {
+            Judgement::Inaccuracy => 6,
+            Judgement::Mistake => 2,
+            Judgement::Blunder => 4,
+        }
+    }
+}
+
+impl Display for Judgement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Judgement::Inaccuracy => write!(f, "Inaccuracy"),
+            Judgement::Mistake => write!(f, "Mistake"),
+            Judgement::Blunder => write!(f, "Blunder"),
+        }
+    }
+}
+
+impl PositionAnalysis {
+    // From white's point of view in pawns like '+0.35' or '-1.20', mates in moves like '#3' or '#-2'
+    pub fn evaluation(&self) -> String {
+        let white = if self.turn == Color::White { self.score } else { -self.score };
+        match (&self.best, Score::from_search(white)) {
+            (None, _) if self.score <= MATE_BOUND => String::from("mate"),
+            (None, _) => String::from("draw"),
+            (_, Score::Centipawns(score)) => format!("{:+.2}", score as f64 / 100.0),
+            (_, Score::Mate(moves)) => format!("#{moves}"),
+        }
+    }
+}
+
+// 'Best move Nf3, score +0.35 at depth 12, pv Nf3 d5 d4'
+impl Display for PositionAnalysis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let best = self.best.as_deref().unwrap_or("none");
+        write!(f, "Best move {best}, score {} at depth {}, pv {}", self.evaluation(), self.depth, self.pv.join(" "))
+    }
+}
+
+// '3... Nf6    #1, Blunder: best was Qe7 (+0.00), pv Qxf7#' with the score after the move
+impl Display for MoveAnalysis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:<14} {:>7}", self.numbered_san(), self.after.evaluation())?;
+        if let (Some(judgement), Some(best)) = (self.judgement, &self.before.best) {
+            write!(f, ", {judgement}: best was {best} ({})", self.before.evaluation())?;
+        }
+        if !self.after.pv.is_empty() {
+            write!(f, ", pv {}", self.after.pv.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl MoveAnalysis {
+    pub fn turn(&self) -> Color {
+        self.before.turn
+    }
+
+    // Like '1. e4' for white and '1... e5' for black
+    pub fn numbered_san(&self) -> String {
+        match self.turn() {
+            Color::White => format!("{}. {}", self.number, self.san),
+            Color::Black => format!("{}... {}", self.number, self.san),
+        }
+    }
+
+    // NAG and comment for the annotated PGN, like '$2 {-0.80 Mistake. Best was Nf3 (+0.30) Nf3 d5 d4}'
+    pub fn annotation(&self) -> String {
+        let (Some(judgement), Some(best)) = (self.judgement, &self.before.best) else {
+            return format!("{{{}}}", self.after.evaluation());
+        };
+        format!(
+            "${} {{{} {judgement}. Best was {best} ({}) {}}}",
+            judgement.nag(),
+            self.after.evaluation(),
+            self.before.evaluation(),
+            self.before.pv.join(" ")
+        )
+    }
+}
+
+impl Engine {
+    // Searches the position of the game with the full strength of the settings
+    pub fn analyze_position(&mut self, limits: &SearchLimits) -> PositionAnalysis {
+        self.wait_for_search();
+        let lines = self.search_lines(limits);
+        let board = *self.board();
+        match lines.first() {
+            Some(line) => PositionAnalysis {
+                turn: board.turn,
+                best: Some(board.to_san(&line.mv)),
+                score: line.score,
+                depth: line.depth,
+                pv: board.san_line(&line.pv),
+            },
+            None => PositionAnalysis {
+                turn: board.turn,
+                best: None,
+                score: board.evaluate(&self.settings.eval, 0),
+                depth: 0,
+                pv: Vec::new(),
+            },
+        }
+    }
+
+    // Searches every position of the game and judges its moves by the score lost against the best move,
+    // `report` gets the moves as they are judged
+    pub fn analyze_game(&mut self, game: &Game, limits: &SearchLimits, mut report: impl FnMut(&MoveAnalysis)) -> Vec<MoveAnalysis> {
+        self.wait_for_search();
+        self.shared.tt.clear();
+        self.game = game.clone();
+        while self.game.takeback().is_some() {}
+        let mut before = self.analyze_position(limits);
+        let mut moves = Vec::new();
+        for mv in game.moves() {
+            let (number, san) = (self.game.fullmove_number(), self.board().to_san(mv));
+            self.game.make_move(mv).expect("Moves of the game are legal");
+            let after = self.analyze_position(limits);
+            let loss = match before.best.as_ref() == Some(&san) {
+                true => 0,
+                false => (before.score.clamp(-SCORE_CAP, SCORE_CAP) + after.score.clamp(-SCORE_CAP, SCORE_CAP)).max(0),
+            };
+            let analysis = MoveAnalysis {
+                number,
+                san,
+                before,
+                after: after.clone(),
+                loss,
+                judgement: Judgement::from_loss(loss),
+            };
+            report(&analysis);
+            moves.push(analysis);
+            before = after;
+        }
+        moves
+    }
+}
+
+// The game with the evaluations as comments and the judgements as NAGs, games read without a result get the one on the board
+pub fn annotate(pgn: &PgnGame, moves: &[MoveAnalysis]) -> PgnGame {
+    let mut annotated = pgn.clone();
+    annotated.annotations = moves.iter().map(MoveAnalysis::annotation).collect();
+    if annotated.tag("Result").is_none() {
+        annotated.set_tag("Result", annotated.game.result().score());
+    }
+    annotated.set_tag("Annotator", ENGINE_NAME);
+    annotated
+}
+
+// Judgements and the average centipawn loss of one side, like 'White: inaccuracies 1, mistakes 0, blunders 2, average loss 35cp'
+pub fn summary(moves: &[MoveAnalysis], color: Color) -> String {
+    let played: Vec<&MoveAnalysis> = moves.iter().filter(|analysis| analysis.turn() == color).collect();
+    let count = |judgement: Judgement| played.iter().filter(|analysis| analysis.judgement == Some(judgement)).count();
+    let loss: i32 = played.iter().map(|analysis| analysis.loss).sum();
+    format!(
+        "{}: inaccuracies {}, mistakes {}, blunders {}, average loss {}cp",
+        if color == Color::White { "White" } else { "Black" },
+        count(Judgement::Inaccuracy),
+        count(Judgement::Mistake),
+        count(Judgement::Blunder),
+        loss / played.len().max(1) as i32
+    )
+}
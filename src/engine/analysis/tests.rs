@@ -0,0 +1,83 @@
+#[cfg(test)]
+use crate::engine::analysis::*;
+#[cfg(test)]
+use crate::engine::output::Response;
+#[cfg(test)]
+use crate::model::pgn;
+#[cfg(test)]
+use std::sync::Arc;
+
+#[cfg(test)]
+fn analyze(sans: &[&str], depth: u8) -> (Game, Vec<MoveAnalysis>) {
+    let mut game = Game::new();
+    for san in sans {
+        game.make_san_move(san).unwrap();
+    }
+    let mut engine = Engine::with_output(Arc::new(|_: Response| ()));
+    let limits = SearchLimits {
+        depth: Some(depth),
+        ..SearchLimits::default()
+    };
+    let mut reported = 0;
+    let moves = engine.analyze_game(&game, &limits, |_| reported += 1);
+    assert_eq!(sans.len(), reported);
+    (game, moves)
+}
+
+#[test]
+fn judgements_by_loss() {
+    assert_eq!(None, Judgement::from_loss(0));
+    assert_eq!(None, Judgement::from_loss(49));
+    assert_eq!(Some(Judgement::Inaccuracy), Judgement::from_loss(50));
+    assert_eq!(Some(Judgement::Mistake), Judgement::from_loss(150));
+    assert_eq!(Some(Judgement::Blunder), Judgement::from_loss(5000));
+    assert_eq!(4, Judgement::Blunder.nag());
+}
+
+#[test]
+fn position_analysis() {
+    let mut engine = Engine::with_output(Arc::new(|_: Response| ()));
+    engine.game = Game::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4").unwrap();
+    let limits = SearchLimits {
+        depth: Some(2),
+        ..SearchLimits::default()
+    };
+    let position = engine.analyze_position(&limits);
+    assert_eq!(Some("Qxf7#"), position.best.as_deref());
+    assert_eq!(vec!["Qxf7#"], position.pv);
+    assert_eq!("#1", position.evaluation());
+
+    engine.game.make_san_move("Qxf7#").unwrap();
+    let mated = engine.analyze_position(&limits);
+    assert_eq!((None, "mate"), (mated.best.as_deref(), mated.evaluation().as_str()));
+}
+
+#[test]
+fn blunders_and_annotations() {
+    let (game, moves) = analyze(&["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7#"], 2);
+    assert_eq!(7, moves.len());
+    assert_eq!("3... Nf6", moves[5].numbered_san());
+    assert_eq!(Some(Judgement::Blunder), moves[5].judgement);
+    assert_eq!(Some("Qxf7#"), moves[6].before.best.as_deref());
+    assert_eq!((0, None), (moves[6].loss, moves[6].judgement));
+    assert_eq!("mate", moves[6].after.evaluation());
+
+    let annotated = annotate(&pgn::PgnGame::from(game), &moves);
+    let text = annotated.to_string();
+    assert!(text.contains("[Annotator \"crust"));
+    let movetext = text.replace('\n', " ");
+    assert!(movetext.contains("Nf6 $4 {#1 Blunder. Best was "));
+    assert!(movetext.contains("Qxf7# {mate} 1-0"));
+    assert!(text.lines().all(|line| line.len() <= 80));
+    let read = pgn::parse_games(&text).unwrap();
+    assert_eq!(7, read[0].game.moves().len());
+}
+
+#[test]
+fn lost_queen() {
+    let (_, moves) = analyze(&["e4", "e5", "Qh5", "Nc6", "Qxf7+", "Kxf7"], 2);
+    assert_eq!(Some(Judgement::Blunder), moves[4].judgement);
+    assert!(moves[4].loss >= 300);
+    assert_eq!(None, moves[5].judgement);
+    assert!(moves[5].after.evaluation().starts_with('-'));
+}
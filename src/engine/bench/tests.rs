@@ -0,0 +1,34 @@
+#[cfg(test)]
+use crate::engine::bench::*;
+#[cfg(test)]
+use crate::engine::output::CollectingOutput;
+
+#[test]
+fn bench_positions() {
+    for fen in BENCH_FENS {
+        let game = Game::from_fen(fen).unwrap();
+        assert!(!game.board().gen_moves(false).is_empty(), "{fen}");
+    }
+    let mut unique = BENCH_FENS.to_vec();
+    unique.sort_unstable();
+    unique.dedup();
+    assert_eq!(BENCH_FENS.len(), unique.len());
+}
+
+#[test]
+fn deterministic_node_count() {
+    let output = Arc::new(CollectingOutput::default());
+    let mut engine = Engine::with_output(output.clone());
+    let (nodes, _) = engine.bench(2);
+    assert!(nodes > 0);
+    // earlier searches and the options don't change the count
+    engine.execute_line("setoption name Threads value 2");
+    engine.execute_line("setoption name Hash value 2");
+    engine.execute_line("go depth 3");
+    engine.wait_for_search();
+    engine.execute_line("bench 2");
+    assert_eq!(nodes, engine.bench(2).0);
+    let benches: Vec<String> = output.lines().into_iter().filter(|line| line.starts_with("Bench.")).collect();
+    assert_eq!(3, benches.len());
+    assert!(benches.iter().all(|line| line.contains(&format!("nodes: {nodes}\t"))));
+}
@@ -0,0 +1,247 @@
+use crate::model::pgn;
+use crate::model::util::errors;
+use crate::model::*;
+use rand::Rng;
+use std::collections::HashMap;
+use std::path::Path;
+
+mod keys;
+mod tests;
+
+use keys::RANDOM64;
+
+// One 16 byte record of a Polyglot .bin file, the file is sorted by key
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BookEntry {
+    pub key: u64,
+    pub mv: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BookSelection {
+    Best,
+    // random, proportional to the weights
+    Weighted,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Book {
+    entries: Vec<BookEntry>,
+}
+
+// Collects moves of a PGN collection into a book
+#[derive(Clone, Debug)]
+pub struct BookBuilder {
+    // only the first `max_ply` half moves of every game go into the book
+    pub max_ply: usize,
+    // moves played fewer times are left out
+    pub min_count: u32,
+    counts: HashMap<(u64, u16), u32>,
+}
+
+const ENTRY_SIZE: usize = 16;
+
+// Polyglot hash of the position, not the same as Board::hash
+pub fn key(board: &Board) -> u64 {
+    let mut key = 0;
+    for square in Square::all() {
+        if let Some(piece) = board.piece_at(square) {
+            // black pawn, white pawn, black knight, ...
+            let kind = 2 * piece.kind.index() + (piece.color == Color::White) as usize;
+            key ^= RANDOM64[64 * kind + 8 * square.rank() as usize + square.file() as usize];
+        }
+    }
+    let rights = [
+        (Color::White, CastleSide::King),
+        (Color::White, CastleSide::Queen),
+        (Color::Black, CastleSide::King),
+        (Color::Black, CastleSide::Queen),
+    ];
+    for (index, (color, side)) in rights.into_iter().enumerate() {
+        if board.castling.rook_file(color, side).is_some() {
+            key ^= RANDOM64[768 + index];
+        }
+    }
+    // en passant only counts when a pawn of the side to move stands next to the pushed one
+    if let Some(target) = board.en_passant {
+        let rank = if board.turn == Color::White {
+            target.rank() - 1
+        } else {
+            target.rank() + 1
+        };
+        let pawn = Some(Piece::new(PieceKind::Pawn, board.turn));
+        let capturers = [target.file().checked_sub(1), Some(target.file() + 1).filter(|&file| file < 8)];
+        if capturers
+            .into_iter()
+            .flatten()
+            .any(|file| board.piece_at(Square::new(file, rank)) == pawn)
+        {
+            key ^= RANDOM64[772 + target.file() as usize];
+        }
+    }
+    if board.turn == Color::White {
+        key ^= RANDOM64[780];
+    }
+    key
+}
+
+// to file, to rank, from file, from rank and promotion in 3 bits each. Castles are king takes rook like our moves
+pub fn encode_move(mv: &Move) -> u16 {
+    let promotion = match mv.promote_to() {
+        None => 0,
+        Some(PieceKind::Knight) => 1,
+        Some(PieceKind::Bishop) => 2,
+        Some(PieceKind::Rook) => 3,
+        Some(_) => 4,
+    };
+    let (from, to) = (mv.from(), mv.to());
+    (to.file() as u16) | (to.rank() as u16) << 3 | (from.file() as u16) << 6 | (from.rank() as u16) << 9 | promotion << 12
+}
+
+// The legal move for a book move, None if the book is broken or the key collided
+fn decode_move(board: &Board, raw: u16) -> Option<Move> {
+    let square = |bits: u16| Square::new((bits & 7) as u8, (bits >> 3 & 7) as u8);
+    let (to, from) = (square(raw), square(raw >> 6));
+    let promotion = match raw >> 12 & 7 {
+        0 => None,
+        1 => Some(PieceKind::Knight),
+        2 => Some(PieceKind::Bishop),
+        3 => Some(PieceKind::Rook),
+        _ => Some(PieceKind::Queen),
+    };
+    board
+        .gen_moves(false)
+        .into_iter()
+        .find(|mv| mv.from() == from && mv.to() == to && mv.promote_to() == promotion)
+}
+
+impl Book {
+    pub fn open(path: &Path) -> std::io::Result<Book> {
+        Book::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> std::io::Result<Book> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(errors::invalid_input(format!(
+                "Book size must be a multiple of {ENTRY_SIZE}, but got {}",
+                bytes.len()
+            )));
+        }
+        let entries = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| BookEntry {
+                key: u64::from_be_bytes(chunk[0..8].try_into().unwrap()),
+                mv: u16::from_be_bytes(chunk[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(chunk[10..12].try_into().unwrap()),
+                learn: u32::from_be_bytes(chunk[12..16].try_into().unwrap()),
+            })
+            .collect();
+        Ok(Book { entries })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.key.to_be_bytes());
+            bytes.extend_from_slice(&entry.mv.to_be_bytes());
+            bytes.extend_from_slice(&entry.weight.to_be_bytes());
+            bytes.extend_from_slice(&entry.learn.to_be_bytes());
+        }
+        bytes
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // All entries for a Polyglot key, found by binary search
+    pub fn entries(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = start + self.entries[start..].partition_point(|entry| entry.key == key);
+        &self.entries[start..end]
+    }
+
+    // Legal book moves of the position with their weights
+    pub fn moves(&self, board: &Board) -> Vec<(Move, u16)> {
+        self.entries(key(board))
+            .iter()
+            .filter_map(|entry| decode_move(board, entry.mv).map(|mv| (mv, entry.weight)))
+            .collect()
+    }
+
+    pub fn pick(&self, board: &Board, selection: BookSelection) -> Option<Move> {
+        let moves = self.moves(board);
+        let total: u32 = moves.iter().map(|&(_, weight)| weight as u32).sum();
+        match selection {
+            _ if total == 0 => moves.first().map(|&(mv, _)| mv),
+            BookSelection::Best => moves.iter().max_by_key(|&&(_, weight)| weight).map(|&(mv, _)| mv),
+            BookSelection::Weighted => {
+                let mut left = rand::thread_rng().gen_range(0..total);
+                moves
+                    .into_iter()
+                    .find(|&(_, weight)| {
+                        let found = left < weight as u32;
+                        left = left.saturating_sub(weight as u32);
+                        found
+                    })
+                    .map(|(mv, _)| mv)
+            }
+        }
+    }
+}
+
+impl BookBuilder {
+    pub fn new(max_ply: usize, min_count: u32) -> BookBuilder {
+        BookBuilder {
+            max_ply,
+            min_count,
+            counts: HashMap::new(),
+        }
+    }
+
+    pub fn add_game(&mut self, game: &Game) {
+        for (board, mv) in game.boards().iter().zip(game.moves()).take(self.max_ply) {
+            *self.counts.entry((key(board), encode_move(mv))).or_default() += 1;
+        }
+    }
+
+    // Adds every game of a PGN collection, returns how many there were
+    pub fn add_pgn(&mut self, pgn: &str) -> std::io::Result<usize> {
+        let games = pgn::parse_games(pgn)?;
+        games.iter().for_each(|game| self.add_game(&game.game));
+        Ok(games.len())
+    }
+
+    // Weights are how often a move was played, most popular first within a position
+    pub fn build(&self) -> Book {
+        self.counts
+            .iter()
+            .filter(|(_, &count)| count >= self.min_count)
+            .map(|(&(key, mv), &count)| BookEntry {
+                key,
+                mv,
+                weight: count.min(u16::MAX as u32) as u16,
+                learn: 0,
+            })
+            .collect()
+    }
+}
+
+// Sorts the entries the way Polyglot expects them, by key and the heaviest move first
+impl FromIterator<BookEntry> for Book {
+    fn from_iter<T: IntoIterator<Item = BookEntry>>(iter: T) -> Self {
+        let mut entries: Vec<BookEntry> = iter.into_iter().collect();
+        entries.sort_unstable_by_key(|entry| (entry.key, std::cmp::Reverse(entry.weight), entry.mv));
+        Book { entries }
+    }
+}
@@ -0,0 +1,77 @@
+#[cfg(test)]
+use crate::engine::book::*;
+#[cfg(test)]
+use crate::model::pgn;
+
+#[test]
+fn polyglot_keys() {
+    let reference = [
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 0x463b96181691fc9c),
+        ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1", 0x823c9b50fd114196),
+        ("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", 0x0756b94461c50fb0),
+        ("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2", 0x662fafb965db29d4),
+        ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", 0x22a48b5a8e47ff78),
+        ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 1 3", 0x652a607ca3f242c1),
+        ("rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 2 4", 0x00fdd303c946bdd9),
+        ("rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3", 0x3c8123ea7b067637),
+        ("rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 1 4", 0x5c3f9b829b279560),
+    ];
+    for (fen, expected) in reference {
+        assert_eq!(expected, key(&Board::from_fen(String::from(fen)).unwrap()), "{fen}");
+    }
+}
+
+#[test]
+fn build_and_probe_book() {
+    let games = pgn::parse_games(
+        "[Event \"?\"]\n[Result \"1-0\"]\n\n1. e4 {best by test} e5 2. Nf3 (2. f4 exf4) Nc6 3. Bb5 $1 a6 1-0\n\n\
+         [Result \"0-1\"]\n1.e4 c5 2.Nf3 d6 0-1\n\n[Result \"*\"]\n1. d4 d5 2. O-O-O *\n",
+    );
+    assert!(games.is_err());
+
+    let games =
+        pgn::parse_games("1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 1-0\n\n[Result \"0-1\"]\n1.e4 c5 2.Nf3 d6 0-1\n\n1. d4 d5 2. c4 *\n").unwrap();
+    assert_eq!(3, games.len());
+    assert_eq!(Some("0-1"), games[1].tag("Result"));
+    assert_eq!(6, games[0].game.moves().len());
+
+    let mut builder = BookBuilder::new(3, 1);
+    games.iter().for_each(|pgn| builder.add_game(&pgn.game));
+    let book = builder.build();
+    let start = Board::new();
+    let legal = |mv: &str| start.find_move(&mv.parse().unwrap()).unwrap();
+    assert_eq!(vec![(legal("e2e4"), 2), (legal("d2d4"), 1)], book.moves(&start));
+    assert_eq!(Some(legal("e2e4")), book.pick(&start, BookSelection::Best));
+    assert!(book.pick(&start, BookSelection::Weighted).is_some());
+    // 'Bb5' is past the depth limit
+    assert!(book.moves(&games[0].game.boards()[4]).is_empty());
+
+    let frequent = {
+        let mut builder = BookBuilder::new(10, 2);
+        games.iter().for_each(|pgn| builder.add_game(&pgn.game));
+        builder.build()
+    };
+    assert_eq!(1, frequent.len());
+
+    let loaded = Book::from_bytes(&book.to_bytes()).unwrap();
+    assert_eq!(book.len(), loaded.len());
+    assert_eq!(book.moves(&start), loaded.moves(&start));
+    assert!(Book::from_bytes(&[0; 17]).is_err());
+}
+
+#[test]
+fn castle_book_moves() {
+    let board = Board::from_fen(String::from("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")).unwrap();
+    let castle = board.find_move(&"e1g1".parse().unwrap()).unwrap();
+    // Polyglot writes castles as king takes rook, e1h1
+    assert_eq!(0b000_000_100_000_111, encode_move(&castle));
+    let book: Book = [BookEntry {
+        key: key(&board),
+        mv: encode_move(&castle),
+        weight: 1,
+        learn: 0,
+    }]
+    .into_iter()
+    .collect();
+    assert_eq!(vec![(castle, 1)], book.moves(&board));
+}
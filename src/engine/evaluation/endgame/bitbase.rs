@@ -0,0 +1,128 @@
+use lazy_static::*;
+
+// Every KPK position with white having the pawn on files a..d: the side to move, the pawn and both kings
+const POSITIONS: usize = 2 * 24 * 64 * 64;
+
+// Results while classifying, flags so that the results of all moves can be or-ed together
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+lazy_static! {
+    // one bit per position, set when white wins
+    static ref KPK: Vec<u32> = generate();
+}
+
+// Squares here are rank * 8 + file, a1 is 0
+fn index(white_to_move: bool, white_king: u8, black_king: u8, pawn: u8) -> usize {
+    let (file, rank) = ((pawn % 8) as usize, (pawn / 8) as usize);
+    white_king as usize | (black_king as usize) << 6 | (!white_to_move as usize) << 12 | file << 13 | (6 - rank) << 15
+}
+
+// Whether white wins, the pawn has to be on files a..d and ranks 2..7
+pub fn probe(white_to_move: bool, white_king: u8, black_king: u8, pawn: u8) -> bool {
+    let index = index(white_to_move, white_king, black_king, pawn);
+    KPK[index / 32] & (1 << (index % 32)) != 0
+}
+
+fn distance(a: u8, b: u8) -> u8 {
+    (a % 8).abs_diff(b % 8).max((a / 8).abs_diff(b / 8))
+}
+
+fn king_moves(square: u8) -> impl Iterator<Item = u8> {
+    (0..64).filter(move |&target| distance(square, target) == 1)
+}
+
+fn pawn_attacks(pawn: u8, square: u8) -> bool {
+    square / 8 == pawn / 8 + 1 && (square % 8).abs_diff(pawn % 8) == 1
+}
+
+// Positions start as draws, wins and invalid when that is clear from the position alone.
+// The rest is classified from the results of their moves until nothing changes
+fn generate() -> Vec<u32> {
+    let mut results = vec![INVALID; POSITIONS];
+    for (index, result) in results.iter_mut().enumerate() {
+        *result = initial(index);
+    }
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for index in 0..POSITIONS {
+            if results[index] == UNKNOWN {
+                results[index] = classify(&results, index);
+                changed |= results[index] != UNKNOWN;
+            }
+        }
+    }
+    let mut bits = vec![0u32; POSITIONS / 32];
+    for (index, &result) in results.iter().enumerate() {
+        if result == WIN {
+            bits[index / 32] |= 1 << (index % 32);
+        }
+    }
+    bits
+}
+
+fn decode(index: usize) -> (bool, u8, u8, u8) {
+    let pawn = (6 - (index >> 15)) * 8 + ((index >> 13) & 3);
+    ((index >> 12) & 1 == 0, (index & 63) as u8, ((index >> 6) & 63) as u8, pawn as u8)
+}
+
+fn initial(index: usize) -> u8 {
+    let (white_to_move, white_king, black_king, pawn) = decode(index);
+    let promotion = pawn + 8;
+    if distance(white_king, black_king) <= 1
+        || white_king == pawn
+        || black_king == pawn
+        || (white_to_move && pawn_attacks(pawn, black_king))
+    {
+        return INVALID;
+    }
+    // the pawn promotes and the queen can't be taken
+    if white_to_move
+        && pawn / 8 == 6
+        && white_king != promotion
+        && (distance(black_king, promotion) > 1 || distance(white_king, promotion) == 1)
+    {
+        return WIN;
+    }
+    if !white_to_move {
+        let attacked = |square: u8| distance(white_king, square) == 1 || pawn_attacks(pawn, square);
+        let stalemate = king_moves(black_king).all(attacked);
+        let takes_pawn = distance(black_king, pawn) == 1 && distance(white_king, pawn) > 1;
+        if stalemate || takes_pawn {
+            return DRAW;
+        }
+    }
+    UNKNOWN
+}
+
+fn classify(results: &[u8], index: usize) -> u8 {
+    let (white_to_move, white_king, black_king, pawn) = decode(index);
+    let mut reached = INVALID;
+    if white_to_move {
+        for square in king_moves(white_king) {
+            reached |= results[self::index(false, square, black_king, pawn)];
+        }
+        let push = pawn + 8;
+        if pawn / 8 < 6 {
+            reached |= results[self::index(false, white_king, black_king, push)];
+        }
+        if pawn / 8 == 1 && push != white_king && push != black_king {
+            reached |= results[self::index(false, white_king, black_king, push + 8)];
+        }
+    } else {
+        for square in king_moves(black_king) {
+            reached |= results[self::index(true, white_king, square, pawn)];
+        }
+    }
+    let (good, bad) = if white_to_move { (WIN, DRAW) } else { (DRAW, WIN) };
+    if reached & good != 0 {
+        good
+    } else if reached & UNKNOWN != 0 {
+        UNKNOWN
+    } else {
+        bad
+    }
+}
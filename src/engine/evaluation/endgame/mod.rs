@@ -0,0 +1,253 @@
+use crate::engine::evaluation::EvalParams;
+use crate::engine::tablebase::{Signature, MAX_PIECES};
+use crate::model::*;
+use lazy_static::*;
+use std::collections::HashMap;
+
+mod bitbase;
+mod tests;
+
+// Added to endgames that are known to be won, keeps them above any material imbalance
+pub const KNOWN_WIN: i32 = 10_000;
+// Scale factors are out of 64, 0 makes the position a draw
+pub const SCALE_NORMAL: i32 = 64;
+pub const SCALE_DRAW: i32 = 0;
+
+// Score of the position for `strong`, the side the endgame is named after first
+type Evaluator = fn(&Board, Color, &EvalParams) -> i32;
+
+lazy_static! {
+    static ref ENDGAMES: HashMap<Signature, (Evaluator, Color)> = endgames();
+}
+
+fn endgames() -> HashMap<Signature, (Evaluator, Color)> {
+    let evaluators: [(&str, Evaluator); 4] = [("KBNK", kbnk), ("KPK", kpk), ("KRKP", krkp), ("KQKP", kqkp)];
+    let mut endgames = HashMap::new();
+    for (name, evaluator) in evaluators {
+        let signature: Signature = name.parse().unwrap();
+        endgames.insert(signature.flipped(), (evaluator, Color::Black));
+        endgames.insert(signature, (evaluator, Color::White));
+    }
+    endgames
+}
+
+// Piece counts by color and PieceKind
+struct Material {
+    counts: [[u8; 6]; 2],
+    non_pawn: [i32; 2],
+}
+
+impl Material {
+    fn new(board: &Board, params: &EvalParams) -> Material {
+        let mut material = Material {
+            counts: [[0; 6]; 2],
+            non_pawn: [0; 2],
+        };
+        for piece in board.squares.into_iter().flatten() {
+            material.counts[piece.color.index()][piece.kind.index()] += 1;
+            if piece.kind != PieceKind::Pawn {
+                material.non_pawn[piece.color.index()] += params.piece_value(piece.kind);
+            }
+        }
+        material
+    }
+
+    fn count(&self, color: Color, kind: PieceKind) -> u8 {
+        self.counts[color.index()][kind.index()]
+    }
+
+    fn pieces(&self) -> usize {
+        self.counts.iter().flatten().map(|&count| count as usize).sum()
+    }
+
+    fn is_bare_king(&self, color: Color) -> bool {
+        self.counts[color.index()].iter().sum::<u8>() == 1
+    }
+
+    // only the king, bishops and pawns, with exactly one bishop
+    fn is_lone_bishop(&self, color: Color, params: &EvalParams) -> bool {
+        self.count(color, PieceKind::Bishop) == 1 && self.non_pawn[color.index()] == params.piece_value(PieceKind::Bishop)
+    }
+}
+
+// The score of a known endgame from white's view, None for the rest
+pub fn evaluate(board: &Board, params: &EvalParams) -> Option<i32> {
+    let material = Material::new(board, params);
+    let signed = |strong: Color, score: i32| if strong == Color::White { score } else { -score };
+    if material.pieces() <= MAX_PIECES {
+        if let Some((evaluator, strong)) = ENDGAMES.get(&Signature::from_board(board)) {
+            return Some(signed(*strong, evaluator(board, *strong, params)));
+        }
+    }
+    for strong in Color::ALL {
+        if material.is_bare_king(strong.flip()) && material.non_pawn[strong.index()] >= params.piece_value(PieceKind::Rook) {
+            return Some(signed(strong, kxk(board, strong, params, &material)));
+        }
+    }
+    None
+}
+
+// How much of the advantage of `strong` is kept in drawish material, out of SCALE_NORMAL
+pub fn scale(board: &Board, params: &EvalParams, strong: Color) -> i32 {
+    let material = Material::new(board, params);
+    let weak = strong.flip();
+    let (strong_pieces, weak_pieces) = (material.non_pawn[strong.index()], material.non_pawn[weak.index()]);
+    let bishop = params.piece_value(PieceKind::Bishop);
+
+    // without pawns a minor piece more is rarely enough
+    if material.count(strong, PieceKind::Pawn) == 0 && strong_pieces - weak_pieces <= bishop {
+        return match (strong_pieces < params.piece_value(PieceKind::Rook), weak_pieces <= bishop) {
+            (true, _) => SCALE_DRAW,
+            (false, true) => 4,
+            (false, false) => 14,
+        };
+    }
+    if material.is_lone_bishop(strong, params) && is_wrong_bishop_draw(board, strong) {
+        return SCALE_DRAW;
+    }
+    let one_bishop_each = material.count(strong, PieceKind::Bishop) == 1 && material.count(weak, PieceKind::Bishop) == 1;
+    if one_bishop_each && is_dark(bishop_square(board, strong)) != is_dark(bishop_square(board, weak)) {
+        let only_bishops = material.is_lone_bishop(strong, params) && material.is_lone_bishop(weak, params);
+        return if only_bishops { 16 } else { 46 };
+    }
+    SCALE_NORMAL
+}
+
+// Rook pawns of one file whose promotion square the bishop doesn't cover, with the defending king in the corner
+fn is_wrong_bishop_draw(board: &Board, strong: Color) -> bool {
+    let pawns: Vec<Square> = squares_of(board, PieceKind::Pawn, strong).collect();
+    let file = pawns[0].file();
+    if (file != 0 && file != 7) || pawns.iter().any(|pawn| pawn.file() != file) {
+        return false;
+    }
+    let promotion = Square::new(file, relative_rank(strong, 7));
+    let defender = board.king_square(strong.flip()).unwrap();
+    is_dark(promotion) != is_dark(bishop_square(board, strong)) && distance(defender, promotion) <= 1
+}
+
+// The weak king is pushed to the edge and the strong king follows it
+fn kxk(board: &Board, strong: Color, params: &EvalParams, material: &Material) -> i32 {
+    let (strong_king, weak_king) = kings(board, strong);
+    let pawns = material.count(strong, PieceKind::Pawn) as i32 * params.piece_value(PieceKind::Pawn);
+    let mut score = material.non_pawn[strong.index()] + pawns + push_to_edge(weak_king) + push_close(strong_king, weak_king);
+    let count = |kind| material.count(strong, kind);
+    let bishop_colors = || squares_of(board, PieceKind::Bishop, strong).map(is_dark).collect::<Vec<_>>();
+    let mates = count(PieceKind::Queen) > 0
+        || count(PieceKind::Rook) > 0
+        || (count(PieceKind::Bishop) > 0 && count(PieceKind::Knight) > 0)
+        || (bishop_colors().contains(&true) && bishop_colors().contains(&false));
+    if mates {
+        score += KNOWN_WIN;
+    }
+    score
+}
+
+// Mate is only forced in the corners of the bishop's color
+fn kbnk(board: &Board, strong: Color, params: &EvalParams) -> i32 {
+    let (strong_king, weak_king) = kings(board, strong);
+    let corners = match is_dark(bishop_square(board, strong)) {
+        true => [Square::A1, Square::H8],
+        false => [Square::A8, Square::H1],
+    };
+    let corner = corners.iter().map(|&corner| manhattan(weak_king, corner)).min().unwrap();
+    let pieces = params.piece_value(PieceKind::Bishop) + params.piece_value(PieceKind::Knight);
+    KNOWN_WIN + pieces + push_close(strong_king, weak_king) + 100 * (7 - corner.min(7)) as i32
+}
+
+fn kpk(board: &Board, strong: Color, params: &EvalParams) -> i32 {
+    let (strong_king, weak_king) = kings(board, strong);
+    let pawn = squares_of(board, PieceKind::Pawn, strong).next().unwrap();
+    // seen from the strong side with the pawn on files a..d
+    let mirror = pawn.file() >= 4;
+    let normalize = |square: Square| {
+        let file = if mirror { 7 - square.file() } else { square.file() };
+        relative_rank(strong, square.rank()) * 8 + file
+    };
+    let win = bitbase::probe(board.turn == strong, normalize(strong_king), normalize(weak_king), normalize(pawn));
+    match win {
+        true => KNOWN_WIN + params.piece_value(PieceKind::Pawn) + relative_rank(strong, pawn.rank()) as i32,
+        false => 0,
+    }
+}
+
+// Won unless the pawn is far advanced and supported while the rook's king is away
+fn krkp(board: &Board, strong: Color, params: &EvalParams) -> i32 {
+    let weak = strong.flip();
+    let (strong_king, weak_king) = kings(board, strong);
+    let rook = squares_of(board, PieceKind::Rook, strong).next().unwrap();
+    let pawn = squares_of(board, PieceKind::Pawn, weak).next().unwrap();
+    // the pawn runs towards the strong side's back rank
+    let rank = |square: Square| relative_rank(strong, square.rank());
+    let ahead = Square::new(pawn.file(), relative_rank(strong, rank(pawn) - 1));
+    let promotion = Square::new(pawn.file(), relative_rank(strong, 0));
+    let rook_value = params.piece_value(PieceKind::Rook);
+    let tempo = |color: Color| (board.turn == color) as u8;
+    // the strong king blocks the pawn or the weak king is too far from it
+    let blocked = strong_king.file() == pawn.file() && rank(strong_king) < rank(pawn);
+    let unsupported = distance(weak_king, pawn) >= 3 + tempo(weak) && distance(weak_king, rook) >= 3;
+
+    if blocked || unsupported {
+        rook_value - distance(strong_king, pawn) as i32
+    } else if rank(weak_king) <= 2
+        && distance(weak_king, pawn) == 1
+        && rank(strong_king) >= 3
+        && distance(strong_king, pawn) > 2 + tempo(strong)
+    {
+        80 - 8 * distance(strong_king, pawn) as i32
+    } else {
+        200 - 8 * (distance(strong_king, ahead) as i32 - distance(weak_king, ahead) as i32 - distance(pawn, promotion) as i32)
+    }
+}
+
+// A pawn on the seventh next to its king draws on the rook and bishop files
+fn kqkp(board: &Board, strong: Color, params: &EvalParams) -> i32 {
+    let (strong_king, weak_king) = kings(board, strong);
+    let pawn = squares_of(board, PieceKind::Pawn, strong.flip()).next().unwrap();
+    let mut score = push_close(strong_king, weak_king);
+    let drawish_file = [0, 2, 5, 7].contains(&pawn.file());
+    if relative_rank(strong.flip(), pawn.rank()) != 6 || distance(weak_king, pawn) != 1 || !drawish_file {
+        score += params.piece_value(PieceKind::Queen) - params.piece_value(PieceKind::Pawn);
+    }
+    score
+}
+
+fn kings(board: &Board, strong: Color) -> (Square, Square) {
+    (board.king_square(strong).unwrap(), board.king_square(strong.flip()).unwrap())
+}
+
+fn squares_of(board: &Board, kind: PieceKind, color: Color) -> impl Iterator<Item = Square> + '_ {
+    Square::all().filter(move |&square| board.piece_at(square) == Some(Piece::new(kind, color)))
+}
+
+fn bishop_square(board: &Board, color: Color) -> Square {
+    squares_of(board, PieceKind::Bishop, color).next().unwrap()
+}
+
+// The rank as seen by `color`, 0 is its back rank
+fn relative_rank(color: Color, rank: u8) -> u8 {
+    match color {
+        Color::White => rank,
+        Color::Black => 7 - rank,
+    }
+}
+
+fn is_dark(square: Square) -> bool {
+    (square.file() + square.rank()).is_multiple_of(2)
+}
+
+fn distance(a: Square, b: Square) -> u8 {
+    a.file().abs_diff(b.file()).max(a.rank().abs_diff(b.rank()))
+}
+
+fn manhattan(a: Square, b: Square) -> u8 {
+    a.file().abs_diff(b.file()) + a.rank().abs_diff(b.rank())
+}
+
+fn push_to_edge(square: Square) -> i32 {
+    let from_center = |coordinate: u8| (2 * coordinate as i32 - 7).abs() / 2;
+    20 * (from_center(square.file()) + from_center(square.rank()))
+}
+
+fn push_close(a: Square, b: Square) -> i32 {
+    140 - 20 * distance(a, b) as i32
+}
@@ -0,0 +1,68 @@
+#[cfg(test)]
+use crate::engine::evaluation::endgame::*;
+
+#[cfg(test)]
+fn white_score(fen: &str) -> i32 {
+    let board = Board::from_fen(String::from(fen)).unwrap();
+    let score = board.evaluate(&EvalParams::default(), 0);
+    if board.turn == Color::White {
+        score
+    } else {
+        -score
+    }
+}
+
+#[test]
+fn king_pawn_bitbase() {
+    // opposition decides
+    assert_eq!(0, white_score("8/4k3/8/4K3/4P3/8/8/8 w - - 0 1"));
+    assert!(white_score("8/4k3/8/4K3/4P3/8/8/8 b - - 0 1") > KNOWN_WIN);
+    assert_eq!(0, white_score("8/8/8/4p3/4k3/8/4K3/8 b - - 0 1"));
+    assert!(white_score("8/8/8/4p3/4k3/8/4K3/8 w - - 0 1") < -KNOWN_WIN);
+    // the king is outside the square of the pawn, but not of the rook pawn in front of the other king
+    assert!(white_score("7k/8/8/8/8/8/P7/K7 b - - 0 1") > KNOWN_WIN);
+    assert_eq!(0, white_score("8/8/8/8/8/k7/P7/K7 w - - 0 1"));
+    assert_eq!(0, white_score("7k/8/8/8/8/8/7P/7K w - - 0 1"));
+    assert!(white_score("7k/8/6K1/7P/8/8/8/8 w - - 0 1") < KNOWN_WIN);
+}
+
+#[test]
+fn mating_material() {
+    let center = white_score("8/8/8/3k4/8/3K4/8/R7 w - - 0 1");
+    let edge = white_score("3k4/8/3K4/8/8/8/8/R7 w - - 0 1");
+    assert!(KNOWN_WIN < center && center < edge);
+    assert!(white_score("8/8/8/8/8/8/8/kb2K1b1 w - - 0 1") < -KNOWN_WIN);
+    // bishop and knight mate in the corner of the bishop's color
+    let right = white_score("8/8/8/8/4N3/4B3/2K5/k7 w - - 0 1");
+    let wrong = white_score("k7/2K5/8/8/4N3/4B3/8/8 w - - 0 1");
+    assert!(KNOWN_WIN < wrong && wrong < right);
+}
+
+#[test]
+fn rook_and_queen_against_pawn() {
+    let far = white_score("R7/8/8/8/8/p7/8/K5k1 w - - 0 1");
+    assert!(far > 500 && far < 600);
+    assert!(white_score("K7/8/8/8/8/8/2kp4/7R w - - 0 1") < 100);
+    assert!(white_score("Q7/8/8/8/8/8/p7/1k5K w - - 0 1") < 100);
+    assert!(white_score("Q7/8/8/8/8/8/3p4/2k4K w - - 0 1") > 700);
+    assert!(white_score("7k/P7/1K6/8/8/8/8/8 w - - 0 1") > KNOWN_WIN);
+}
+
+#[test]
+fn drawish_material() {
+    let params = EvalParams::default();
+    // opposite colored bishops
+    assert_eq!(100 * 16 / SCALE_NORMAL, white_score("2b1k3/p7/8/8/8/8/PP6/2B1K3 w - - 0 1"));
+    assert_eq!(100, white_score("3bk3/p7/8/8/8/8/PP6/2B1K3 w - - 0 1"));
+    // the bishop doesn't cover the promotion square of the rook pawn
+    assert_eq!(0, white_score("k7/8/8/8/8/P7/8/2B1K3 w - - 0 1"));
+    assert_eq!(410, white_score("k7/8/8/8/8/P7/8/3BK3 w - - 0 1"));
+    assert_eq!(410, white_score("8/8/4k3/8/8/P7/8/2B1K3 w - - 0 1"));
+    // no pawns and at most a minor piece more
+    assert_eq!(0, white_score("4k3/8/8/8/8/8/8/2N1K3 w - - 0 1"));
+    assert_eq!(290 * 4 / SCALE_NORMAL, white_score("4k3/8/8/8/8/8/8/2b1K2R w - - 0 1"));
+    assert_eq!(-(300 * 14 / SCALE_NORMAL), white_score("3rkr2/8/8/8/8/8/8/3NKR2 w - - 0 1"));
+    let board = Board::from_fen(String::from("4k3/8/8/8/8/8/8/2b1K2R w - - 0 1")).unwrap();
+    assert_eq!(4, scale(&board, &params, Color::White));
+    assert_eq!(None, evaluate(&board, &params));
+}
@@ -1,37 +1,66 @@
-mod move_extensions;
+use crate::model::{Board, Color, Piece, PieceKind};
 
-use crate::model::Board;
-use crate::model::pieces::*;
+pub mod endgame;
 
+pub const MATE_SCORE: i32 = i32::MIN + 10;
+// mate scores are offset by the ply they were found at, so anything this close to MATE_SCORE is a mate
+pub const MATE_BOUND: i32 = MATE_SCORE + 1000;
+// indexed by PieceKind: pawn, knight, bishop, rook, queen, king
+static PIECE_VALUES: [i32; 6] = [100, 300, 310, 600, 900, 0];
 
-const MATE_SCORE: i32 = i32::MIN + 10;
-// NONE, KING, PAWN, KNIGHT. BISHOP, ROOK, QUEEN
-static PIECE_VALUES: [i32; 7] = [0, 0, 100, 300, 310, 600, 900];
+// Evaluation parameters that can be tuned with 'setoption'
+#[derive(Clone, Copy, Debug)]
+pub struct EvalParams {
+    pub piece_values: [i32; 6],
+}
 
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams {
+            piece_values: PIECE_VALUES,
+        }
+    }
+}
 
 impl Board {
-    pub fn evaluate(&self, depth: u32) -> i32 {
+    pub fn evaluate(&self, params: &EvalParams, depth: u32) -> i32 {
+        // without legal moves the side to move is mated in check and stalemated otherwise
         if self.gen_moves(false).is_empty() {
-            return MATE_SCORE.wrapping_add(depth as i32);
+            return match self.is_check() {
+                true => MATE_SCORE.wrapping_add(depth as i32),
+                false => 0,
+            };
         }
         let mut result = 0;
-        for piece in self.squares {
-            result += Board::value(piece);
+        for piece in self.squares.into_iter().flatten() {
+            result += Board::value(params, piece);
         }
+        // known endgames have their own evaluation, drawish material keeps only part of the advantage
+        let result = match endgame::evaluate(self, params) {
+            Some(score) => score,
+            None => {
+                let strong = if result > 0 { Color::White } else { Color::Black };
+                result * endgame::scale(self, params, strong) / endgame::SCALE_NORMAL
+            }
+        };
 
-        if self.white_is_active {
-            result
-        } else {
-            -result
+        match self.turn {
+            Color::White => result,
+            Color::Black => -result,
         }
     }
 
-    fn value(piece: u8) -> i32 {
-        let index = piece.without_color() as usize;
-        let mut value = PIECE_VALUES[index];
-        if piece.is_color(BLACK) {
-            value *= -1;
+    fn value(params: &EvalParams, piece: Piece) -> i32 {
+        let value = params.piece_values[piece.kind.index()];
+        match piece.color {
+            Color::White => value,
+            Color::Black => -value,
         }
-        value
+    }
+}
+
+impl EvalParams {
+    pub fn piece_value(&self, kind: PieceKind) -> i32 {
+        self.piece_values[kind.index()]
     }
 }
\ No newline at end of file
@@ -1,168 +1,216 @@
 use crate::engine::generation::static_data::*;
-use crate::model::pieces::*;
 use crate::model::*;
 
 mod static_data;
 mod tests;
 
 impl Board {
-    pub fn gen_moves(&self, only_captures: bool) -> Vec<Move> {
-        self.gen_pseudo_legal_moves()
-            .into_iter()
-            .filter(|mv| {
-                let inactive_color = self.inactive_color();
-                let next_pos = self.make_move(mv);
-                let pseudo = next_pos.gen_pseudo_legal_moves();
-
-                if mv.castle {
-                    let castle_index = match mv.to {
-                        2 => 0,
-                        6 => 1,
-                        58 => 2,
-                        _ => 3,
-                    };
-                    let castle_squares = CASTLE_SQUARES[castle_index];
-                    let castle_pawns = CASTLE_PAWNS[castle_index];
-                    let no_attack = !pseudo.iter().any(|next_move| castle_squares.contains(&next_move.to));
-                    let no_pawns = !castle_pawns.iter().any(|&square| self.squares[square].is(PAWN, inactive_color));
-                    no_attack && no_pawns
-                } else {
-                    !pseudo.into_iter().any(|next_move| next_pos.squares[next_move.to].is_king())
+    pub fn gen_moves(&self, only_captures: bool) -> MoveList {
+        let mut moves = self.gen_pseudo_legal_moves();
+        moves.retain(|mv| (!only_captures || mv.is_capture()) && self.is_legal(mv));
+        moves
+    }
+
+    // The legal move with the squares and promotion of `mv`, used for moves parsed from UCI
+    pub fn find_move(&self, mv: &Move) -> Option<Move> {
+        self.gen_moves(false).into_iter().find(|legal| legal.same_squares(*mv))
+    }
+
+    fn is_legal(&self, mv: &Move) -> bool {
+        if mv.is_castle() && !self.is_castle_path_safe(mv) {
+            return false;
+        }
+        let next_pos = self.make_move(mv);
+        next_pos.king_square(self.turn).is_none_or(|king| !next_pos.is_attacked(king, next_pos.turn))
+    }
+
+    // The king may not castle out of, through or into check. Landing next to a rook that was
+    // only blocked by the castling rook is caught by the regular check test after the move
+    fn is_castle_path_safe(&self, mv: &Move) -> bool {
+        let (king, rook) = (mv.from(), mv.to());
+        let side = if rook.file() > king.file() { CastleSide::King } else { CastleSide::Queen };
+        let target = side.king_file();
+        let files = king.file().min(target)..=king.file().max(target);
+        files.map(|file| Square::new(file, king.rank())).all(|square| !self.is_attacked(square, self.inactive_color()))
+    }
+
+    // Whether the side to move is attacked
+    pub fn is_check(&self) -> bool {
+        self.king_square(self.turn).is_some_and(|king| self.is_attacked(king, self.inactive_color()))
+    }
+
+    pub fn is_attacked(&self, square: Square, by: Color) -> bool {
+        self.find_attackers(square, by, |_| true)
+    }
+
+    // The pieces of the side to move's opponent giving check
+    pub fn checkers(&self) -> Vec<Square> {
+        let mut checkers = Vec::new();
+        if let Some(king) = self.king_square(self.turn) {
+            self.find_attackers(king, self.inactive_color(), |from| {
+                checkers.push(from);
+                false
+            });
+        }
+        checkers
+    }
+
+    // Calls `found` with the squares of the pieces attacking `square` until it returns true
+    fn find_attackers(&self, square: Square, by: Color, mut found: impl FnMut(Square) -> bool) -> bool {
+        let attacker = |square: Square, kinds: &[PieceKind]| self.piece_at(square).is_some_and(|piece| piece.color == by && kinds.contains(&piece.kind));
+        for &from in KNIGHT_MOVES[square.index()].iter() {
+            if attacker(Square::from_index(from), &[PieceKind::Knight]) && found(Square::from_index(from)) {
+                return true;
+            }
+        }
+        // a white pawn attacks from below, so it's found one step down-right or down-left
+        let pawn_directions = if by == Color::White { [5, 6] } else { [4, 7] };
+        for direction in 0..8 {
+            if EDGE_DISTANCE[square.index()][direction] == 0 {
+                continue;
+            }
+            let next = square.offset(DIRECTIONS[direction]);
+            if (attacker(next, &[PieceKind::King]) || (pawn_directions.contains(&direction) && attacker(next, &[PieceKind::Pawn]))) && found(next) {
+                return true;
+            }
+            let sliders: &[PieceKind] = if direction < 4 { &[PieceKind::Rook, PieceKind::Queen] } else { &[PieceKind::Bishop, PieceKind::Queen] };
+            for distance in 1..EDGE_DISTANCE[square.index()][direction] + 1 {
+                let from = square.offset(DIRECTIONS[direction] * distance);
+                if self.piece_at(from).is_some() {
+                    if attacker(from, sliders) && found(from) {
+                        return true;
+                    }
+                    break;
                 }
-            })
-            .filter(|mv| {
-                !only_captures || self.squares[mv.to].is_color(self.inactive_color())
-            })
-            .collect()
+            }
+        }
+        false
     }
 
-    fn gen_pseudo_legal_moves(&self) -> Vec<Move> {
-        let mut buffer: Vec<Move> = Vec::new();
+    fn is_color(&self, square: Square, color: Color) -> bool {
+        self.piece_at(square).is_some_and(|piece| piece.color == color)
+    }
+
+    fn gen_pseudo_legal_moves(&self) -> MoveList {
+        let mut moves = MoveList::new();
         let active_color = self.active_color();
-        for (pos, &piece) in self.squares.iter().enumerate() {
-            if piece.is_color(active_color) {
-                buffer.append(&mut self.gen_piece_moves(piece, pos));
+        for square in Square::all() {
+            match self.piece_at(square) {
+                Some(piece) if piece.color == active_color => self.gen_piece_moves(&mut moves, piece.kind, square),
+                _ => (),
             }
         }
+        moves
+    }
 
-        buffer
-    }
-
-    fn gen_piece_moves(&self, piece: u8, pos: usize) -> Vec<Move> {
-        if piece.is_pawn() {
-            self.pawn_moves(pos)
-        } else if piece.is_king() {
-            self.king_moves(pos)
-        } else if piece.is_bishop() {
-            self.sliding_moves(pos, 4..8)
-        } else if piece.is_rook() {
-            self.sliding_moves(pos, 0..4)
-        } else if piece.is_queen() {
-            self.sliding_moves(pos, 0..8)
-        } else if piece.is_knight() {
-            self.knight_moves(pos)
-        } else {
-            panic!("Unknonw piece type to gen moves")
+    fn gen_piece_moves(&self, moves: &mut MoveList, kind: PieceKind, pos: Square) {
+        match kind {
+            PieceKind::Pawn => self.pawn_moves(moves, pos),
+            PieceKind::King => self.king_moves(moves, pos),
+            PieceKind::Bishop => self.sliding_moves(moves, pos, 4..8),
+            PieceKind::Rook => self.sliding_moves(moves, pos, 0..4),
+            PieceKind::Queen => self.sliding_moves(moves, pos, 0..8),
+            PieceKind::Knight => self.knight_moves(moves, pos),
         }
     }
 
-    fn knight_moves(&self, pos: usize) -> Vec<Move> {
-        let active_color = self.active_color();
-        KNIGHT_MOVES[pos]
-            .into_iter()
-            .filter(|&&x| !self.squares[x as usize].is_color(active_color))
-            .map(|&x| Move::regular(pos, x))
-            .collect()
+    // quiet move or capture, None if the target holds an own piece
+    fn step(&self, pos: Square, to: Square) -> Option<Move> {
+        match self.piece_at(to) {
+            None => Some(Move::regular(pos, to)),
+            Some(piece) if piece.color != self.active_color() => Some(Move::capture(pos, to)),
+            Some(_) => None,
+        }
     }
 
-    fn sliding_moves(&self, pos: usize, directions: std::ops::Range<usize>) -> Vec<Move> {
-        let mut result: Vec<Move> = Vec::new();
-        let active_color = self.active_color();
-        let inactive_color = self.inactive_color();
+    fn knight_moves(&self, moves: &mut MoveList, pos: Square) {
+        for &to in KNIGHT_MOVES[pos.index()].iter() {
+            if let Some(mv) = self.step(pos, Square::from_index(to)) {
+                moves.push(mv);
+            }
+        }
+    }
+
+    fn sliding_moves(&self, moves: &mut MoveList, pos: Square, directions: std::ops::Range<usize>) {
         for direction in directions {
-            for distance in 1..EDGE_DISTANCE[pos][direction] + 1 {
-                let to = (pos as i8 + DIRECTIONS[direction] * distance) as usize;
-                if self.squares[to].is_color(active_color) {
-                    break;
-                }
-                result.push(Move::regular(pos, to));
-                if self.squares[to].is_color(inactive_color) {
-                    break;
+            for distance in 1..EDGE_DISTANCE[pos.index()][direction] + 1 {
+                let to = pos.offset(DIRECTIONS[direction] * distance);
+                match self.step(pos, to) {
+                    Some(mv) => {
+                        moves.push(mv);
+                        if mv.is_capture() {
+                            break;
+                        }
+                    }
+                    None => break,
                 }
             }
         }
-        result
     }
 
-    fn king_moves(&self, pos: usize) -> Vec<Move> {
-        let mut result: Vec<Move> = Vec::new();
+    fn king_moves(&self, moves: &mut MoveList, pos: Square) {
         let active_color = self.active_color();
         for direction in 0..8 {
-            if EDGE_DISTANCE[pos][direction] > 0 {
-                let to = (pos as i8 + DIRECTIONS[direction]) as usize;
-                if !self.squares[to].is_color(active_color) {
-                    result.push(Move::regular(pos, to));
+            if EDGE_DISTANCE[pos.index()][direction] > 0 {
+                if let Some(mv) = self.step(pos, pos.offset(DIRECTIONS[direction])) {
+                    moves.push(mv);
                 }
             }
         }
-        if active_color == WHITE && self.castle_white_king && self.squares[61..63] == [NONE, NONE] {
-            result.push(Move::castle(60, 62));
-        }
-        if active_color == WHITE && self.castle_white_queen && self.squares[57..60] == [NONE, NONE, NONE] {
-            result.push(Move::castle(60, 58));
-        }
-        if active_color == BLACK && self.castle_black_king && self.squares[5..7] == [NONE, NONE] {
-            result.push(Move::castle(4, 6));
-        }
-        if active_color == BLACK && self.castle_black_queen && self.squares[1..4] == [NONE, NONE, NONE] {
-            result.push(Move::castle(4, 2));
+        let rook_piece = Some(Piece::new(PieceKind::Rook, active_color));
+        let flags = if self.chess960 { Move::CASTLE_960 } else { Move::CASTLE };
+        for side in CastleSide::ALL {
+            let Some(rook) = self.castling_rook(active_color, side).filter(|&rook| rook.rank() == pos.rank() && self.piece_at(rook) == rook_piece) else {
+                continue;
+            };
+            // everything between the king, the rook and their destinations must be empty
+            let files = [pos.file(), rook.file(), side.king_file(), side.rook_file()];
+            let (low, high) = (*files.iter().min().unwrap(), *files.iter().max().unwrap());
+            let clear = (low..=high).map(|file| Square::new(file, pos.rank())).all(|square| square == pos || square == rook || self.piece_at(square).is_none());
+            if clear {
+                moves.push(Move::new(pos, rook, flags));
+            }
         }
-
-        result
     }
 
-    fn pawn_moves(&self, pos: usize) -> Vec<Move> {
-        let mut result: Vec<Move> = Vec::new();
+    fn pawn_moves(&self, moves: &mut MoveList, pos: Square) {
         let active_color = self.active_color();
-        let diff: i8 = if active_color == pieces::WHITE { -8 } else { 8 };
-        let regular_move = (pos as i8 + diff) as usize;
-        let rank = regular_move / 8;
+        let diff: i8 = if active_color == Color::White { -8 } else { 8 };
+        let regular_move = pos.offset(diff);
+        let last_rank = regular_move.rank() == 0 || regular_move.rank() == 7;
 
-        if self.squares[regular_move] == pieces::NONE {
-            if rank == 0 || rank == 7 {
-                result.append(&mut self.gen_promotions(pos, regular_move));
+        if self.piece_at(regular_move).is_none() {
+            if last_rank {
+                self.gen_promotions(moves, Move::regular(pos, regular_move));
             } else {
-                result.push(Move::regular(pos, regular_move));
+                moves.push(Move::regular(pos, regular_move));
             }
-        }
-        let double_move_avaliable = (rank == 2 && active_color == BLACK) || (rank == 5 && active_color == WHITE);
-        let double_move = (regular_move as i8 + diff) as usize;
-        if double_move_avaliable && result.len() > 0 && self.squares[double_move] == NONE {
-            result.push(Move::regular(pos, double_move));
-        }
-        if pos % 8 != 0 && (self.squares[regular_move - 1].is_color(self.inactive_color()) || self.en_passant == Some(regular_move - 1)) {
-            if rank == 0 || rank == 7 {
-                result.append(&mut self.gen_promotions(pos, regular_move - 1));
-            } else {
-                result.push(Move::regular(pos, regular_move - 1));
+            let double_move_avaliable = (pos.rank() == 6 && active_color == Color::Black) || (pos.rank() == 1 && active_color == Color::White);
+            if double_move_avaliable && self.piece_at(regular_move.offset(diff)).is_none() {
+                moves.push(Move::new(pos, regular_move.offset(diff), Move::DOUBLE_PUSH));
             }
         }
-        if pos % 8 != 7 && (self.squares[regular_move + 1].is_color(self.inactive_color()) || self.en_passant == Some(regular_move + 1)) {
-            if rank == 0 || rank == 7 {
-                result.append(&mut self.gen_promotions(pos, regular_move + 1));
-            } else {
-                result.push(Move::regular(pos, regular_move + 1));
+        for (file_diff, allowed) in [(-1, pos.file() > 0), (1, pos.file() < 7)] {
+            if !allowed {
+                continue;
+            }
+            let to = regular_move.offset(file_diff);
+            if self.en_passant == Some(to) {
+                moves.push(Move::new(pos, to, Move::EN_PASSANT));
+            } else if self.is_color(to, self.inactive_color()) {
+                if last_rank {
+                    self.gen_promotions(moves, Move::capture(pos, to));
+                } else {
+                    moves.push(Move::capture(pos, to));
+                }
             }
         }
-        result
     }
 
-    fn gen_promotions(&self, from: usize, to: usize) -> Vec<Move> {
-        vec![
-            Move::promotion(from, to, QUEEN),
-            Move::promotion(from, to, ROOK),
-            Move::promotion(from, to, KNIGHT),
-            Move::promotion(from, to, BISHOP),
-        ]
+    fn gen_promotions(&self, moves: &mut MoveList, mv: Move) {
+        for kind in PieceKind::PROMOTIONS {
+            let promotion = Move::promotion(mv.from(), mv.to(), kind);
+            moves.push(if mv.is_capture() { promotion.with_capture() } else { promotion });
+        }
     }
 }
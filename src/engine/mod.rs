@@ -1,105 +1,399 @@
+pub mod analysis;
+pub mod bench;
+pub mod book;
 mod generation;
-mod searcher;
-mod evaluation;
+pub mod searcher;
+pub mod skill;
+pub mod syzygy;
+pub mod tablebase;
+pub mod tournament;
+pub mod evaluation;
 mod ordering;
+pub mod output;
+pub mod perft;
+pub mod solver;
+mod transposition;
 
-use crate::model::pieces::Piece;
+use crate::model::util::errors;
 use crate::model::*;
 use crate::uci;
+use crate::uci::options::{self, OptionValue};
 use crate::uci::Command;
 use std::io::*;
-use std::time::SystemTime;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use book::{Book, BookSelection};
+use syzygy::Tablebase;
+use tablebase::Tables;
+use evaluation::EvalParams;
+use output::{Output, Response, StdoutOutput};
+use transposition::TranspositionTable;
+
+pub const ENGINE_NAME: &str = concat!("crust ", env!("CARGO_PKG_VERSION"));
+pub const ENGINE_AUTHOR: &str = "mcsim4s";
 
 pub struct Engine {
-    pub board: Board,
+    // the game from 'position', searches start from its current board
+    pub game: Game,
+    pub settings: Settings,
+    shared: Arc<SharedState>,
+    // where responses and search info go, stdout unless the engine is embedded or tested
+    pub output: Arc<dyn Output>,
+    book: Option<Arc<Book>>,
+    tablebase: Option<Arc<Tablebase>>,
+    tables: Option<Arc<Tables>>,
+    search_thread: Option<JoinHandle<()>>,
+}
+
+// Everything that can be changed with 'setoption'
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub hash_mb: usize,
+    pub threads: usize,
+    pub multi_pv: usize,
+    pub ponder: bool,
+    pub move_overhead: Duration,
+    pub eval: EvalParams,
+    pub opponent: String,
+    // castles are sent and expected as king takes rook
+    pub chess960: bool,
+    pub own_book: bool,
+    pub book_file: String,
+    pub best_book_move: bool,
+    // directories with Syzygy tables
+    pub syzygy_path: String,
+    // directory with the generated endgame tables
+    pub tablebase_path: String,
+    // the strength is set by the level unless limit_strength asks for the Elo
+    pub skill_level: u8,
+    pub limit_strength: bool,
+    pub elo: u32,
+}
+
+// State shared with the background search started by `go`
+pub struct SharedState {
+    tt: TranspositionTable,
+    stop: AtomicBool,
+    pondering: AtomicBool,
+    last_nodes: AtomicU64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            hash_mb: transposition::DEFAULT_HASH_MB,
+            threads: 1,
+            multi_pv: 1,
+            ponder: false,
+            move_overhead: Duration::from_millis(10),
+            eval: EvalParams::default(),
+            opponent: String::new(),
+            chess960: false,
+            own_book: false,
+            book_file: String::new(),
+            best_book_move: false,
+            syzygy_path: String::new(),
+            tablebase_path: String::new(),
+            skill_level: skill::MAX_LEVEL as u8,
+            limit_strength: false,
+            elo: 1500,
+        }
+    }
+}
+
+impl SharedState {
+    fn new(hash_mb: usize) -> SharedState {
+        SharedState {
+            tt: TranspositionTable::new(hash_mb),
+            stop: AtomicBool::new(false),
+            pondering: AtomicBool::new(false),
+            last_nodes: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::new()
+    }
 }
 
 impl Engine {
     pub fn execute_uci(&mut self, command: Command) -> Result<()> {
         match command {
-            Command::Uci => println!("uciok"),
-            Command::IsReady => println!("readyok"),
-            Command::NewGame => self.board = Board::new(),
+            Command::Uci => {
+                self.output.send(Response::Id {
+                    name: ENGINE_NAME,
+                    author: ENGINE_AUTHOR,
+                });
+                for option in options::OPTIONS {
+                    self.output.send(Response::Option(option));
+                }
+                self.output.send(Response::UciOk);
+            }
+            Command::IsReady => self.output.send(Response::ReadyOk),
+            Command::NewGame => {
+                self.wait_for_search();
+                self.game = Game::new();
+                self.game.set_chess960(self.settings.chess960);
+                self.shared.tt.clear();
+            }
+            Command::SetOption { name, value } => {
+                self.wait_for_search();
+                self.set_option(&name, value.as_deref())?
+            }
             Command::SetPosition { position, moves } => {
-                match position {
-                    uci::Position::Start => self.board = Board::new(),
-                    uci::Position::Fen(fen) => self.board = Board::from_fen(fen)?,
+                self.wait_for_search();
+                let mut game = match position {
+                    uci::Position::Start => Game::new(),
+                    uci::Position::Fen(fen) => Game::from_fen(&fen)?,
                 };
+                game.set_chess960(game.board().chess960 || self.settings.chess960);
                 for mv in &moves {
-                    self.board = self.board.make_move(&self.uci_move_to_inner_model(&mv));
+                    game.make_move(mv)?;
                 }
+                self.game = game;
             }
-            Command::Go(_) => {
-                let search_result = self.search();
-
-                println!("bestmove {}", search_result.to_notation());
+            Command::Go(go) => {
+                self.wait_for_search();
+                if let Some(mv) = self.book_move(&go) {
+                    self.output.send(Response::BestMove { mv: Some(mv), ponder: None });
+                    return Ok(());
+                }
+                let limits = self.search_limits(&go);
+                self.shared.stop.store(false, Ordering::Relaxed);
+                self.shared.pondering.store(go.ponder, Ordering::Relaxed);
+                let engine = self.background();
+                self.search_thread = Some(thread::spawn(move || {
+                    let lines = engine.run_search(&limits);
+                    // the GUI expects no bestmove before 'stop' or 'ponderhit' in these modes
+                    while engine.shared.pondering.load(Ordering::Relaxed)
+                        || (limits.infinite && !engine.shared.stop.load(Ordering::Relaxed))
+                    {
+                        thread::sleep(Duration::from_millis(1));
+                    }
+                    engine.output.send(engine.best_move(&lines, &limits.search_moves));
+                }));
+            }
+            Command::Stop => {
+                self.shared.pondering.store(false, Ordering::Relaxed);
+                self.shared.stop.store(true, Ordering::Relaxed);
+                self.wait_for_search();
             }
-            Command::Perft(depth) => {
-                self.performance_test_print(depth);
+            Command::PonderHit => self.shared.pondering.store(false, Ordering::Relaxed),
+            Command::Perft(perft) => {
+                self.wait_for_search();
+                self.perft(&perft);
+            }
+            Command::Bench { depth } => {
+                self.wait_for_search();
+                self.bench(depth);
             }
             Command::Display => {
-                println!("{}", self.board.to_fen());
+                self.output.send(Response::Board(self.describe(false, false)));
             }
         }
         Ok(())
     }
 
-    fn uci_move_to_inner_model(&self, mv: &uci::Move) -> Move {
-        let castle = self.board.squares[mv.from].is_king() && mv.from.abs_diff(mv.to) == 2;
-        Move {
-            from: mv.from,
-            to: mv.to,
-            promote_to: mv.promote_to,
-            castle,
+    // The board with the state that isn't visible on it and the static evaluation from white's point of view
+    pub fn describe(&self, unicode: bool, flipped: bool) -> String {
+        let board = self.board();
+        let fen = self.game.to_fen();
+        let fields: Vec<&str> = fen.split(' ').collect();
+        let checkers: Vec<String> = board.checkers().iter().map(Square::to_string).collect();
+        let eval = match board.evaluate(&self.settings.eval, 0) {
+            _ if board.gen_moves(false).is_empty() => String::from("-"),
+            eval if board.turn == Color::White => format!("{:+.2}", eval as f64 / 100.0),
+            eval => format!("{:+.2}", -eval as f64 / 100.0),
+        };
+        [
+            board.diagram(unicode, flipped),
+            format!("Side to move: {}", if board.turn == Color::White { "white" } else { "black" }),
+            format!("Castling: {}", fields[2]),
+            format!("En passant: {}", fields[3]),
+            format!("Key: {:016X}", board.hash),
+            format!("Checkers: {}", if checkers.is_empty() { String::from("-") } else { checkers.join(" ") }),
+            format!("Eval: {eval}"),
+            format!("Fen: {fen}"),
+        ]
+        .join("\n")
+    }
+
+    // Nodes searched by all threads in the last search
+    pub fn searched_nodes(&self) -> u64 {
+        self.shared.last_nodes.load(Ordering::Relaxed)
+    }
+
+    pub fn wait_for_search(&mut self) {
+        if let Some(handle) = self.search_thread.take() {
+            handle.join().expect("Search thread panicked");
         }
     }
 
-    pub fn new() -> Engine {
-        Engine { board: Board::new() }
-    }
-
-    #[allow(dead_code)]
-    pub fn performance_test(&self, depth: u8) -> u64 {
-        self.performance_test_inner(depth, false)
-    }
-    pub fn performance_test_print(&self, depth: u8) -> u64 {
-        self.performance_test_inner(depth, true)
-    }
-    fn performance_test_inner(&self, depth: u8, print: bool) -> u64 {
-        let now = SystemTime::now();
-        let result = self.performance_test_recursive(&self.board, depth, print);
-        let elapsed = now.elapsed().unwrap();
-        println!(
-            "Perf. depth: {depth}\telapsed: {}ms\tnodes: {result}\tnps: {:.0}",
-            elapsed.as_millis(),
-            result as f64 / (elapsed.as_millis() as f64 / 1000f64)
-        );
-        result
-    }
-    fn performance_test_recursive(&self, board: &Board, depth: u8, print: bool) -> u64 {
-        match depth {
-            0 => 0,
-            1 => {
-                let moves = board.gen_moves(false);
-                if print {
-                    for mv in &moves {
-                        println!("{}: 1", mv.to_notation());
+    // A copy of the engine for the background search, it shares the hash table and the stop flag
+    fn background(&self) -> Engine {
+        Engine {
+            game: self.game.clone(),
+            settings: self.settings.clone(),
+            shared: self.shared.clone(),
+            output: self.output.clone(),
+            book: self.book.clone(),
+            tablebase: self.tablebase.clone(),
+            tables: self.tables.clone(),
+            search_thread: None,
+        }
+    }
+
+    // 'bestmove X ponder Y', the ponder move comes from the pv or, if the pv was cut by a hash hit, from the hash table.
+    // Below full strength the move may be one of the weaker lines
+    fn best_move(&self, lines: &[searcher::SearchResult], search_moves: &[Move]) -> Response {
+        let Some(line) = self.settings.skill().choose(lines, &mut rand::thread_rng()) else {
+            return Response::BestMove {
+                mv: self.fallback_move(search_moves),
+                ponder: None,
+            };
+        };
+        let next = self.board().make_move(&line.mv);
+        let ponder = line
+            .pv
+            .get(1)
+            .copied()
+            .or_else(|| self.shared.tt.probe(next.hash).and_then(|entry| entry.best_move))
+            .filter(|mv| next.gen_moves(false).contains(mv));
+        Response::BestMove { mv: Some(line.mv), ponder }
+    }
+
+    // A move from the opening book instead of a search, never while pondering or analysing
+    fn book_move(&self, go: &uci::GoCommand) -> Option<Move> {
+        let book = self.book.as_ref().filter(|_| self.settings.own_book && !go.infinite && !go.ponder)?;
+        let selection = if self.settings.best_book_move { BookSelection::Best } else { BookSelection::Weighted };
+        let mv = book.pick(self.board(), selection)?;
+        let allowed = go.search_moves.is_empty() || go.search_moves.iter().any(|search_move| search_move.same_squares(mv));
+        allowed.then_some(mv)
+    }
+
+    fn search_limits(&self, go: &uci::GoCommand) -> searcher::SearchLimits {
+        let (time_left, increment) = match self.board().turn {
+            Color::White => (go.white_time, go.white_increment),
+            Color::Black => (go.black_time, go.black_increment),
+        };
+        let overhead = self.settings.move_overhead;
+        let with_overhead = |time: u64| Duration::from_millis(time).saturating_sub(overhead).max(Duration::from_millis(1));
+        searcher::SearchLimits {
+            depth: go.depth,
+            nodes: go.nodes,
+            move_time: go.move_time.map(with_overhead),
+            time_left: time_left.map(with_overhead),
+            increment: Duration::from_millis(increment.unwrap_or(0)),
+            moves_to_go: go.moves_to_go,
+            infinite: go.infinite,
+            search_moves: go.search_moves.iter().filter_map(|mv| self.board().find_move(mv)).collect(),
+        }
+    }
+
+    fn set_option(&mut self, name: &str, value: Option<&str>) -> Result<()> {
+        let option = options::find(name).ok_or(errors::invalid_input(format!("Unknown option: '{name}'")))?;
+        let value = option.parse(value)?;
+        let settings = &mut self.settings;
+        match (option.name, value) {
+            (options::HASH, OptionValue::Spin(size)) => {
+                settings.hash_mb = size as usize;
+                self.shared = Arc::new(SharedState::new(settings.hash_mb));
+            }
+            (options::CLEAR_HASH, _) => self.shared.tt.clear(),
+            (options::THREADS, OptionValue::Spin(threads)) => settings.threads = threads as usize,
+            (options::MULTI_PV, OptionValue::Spin(lines)) => settings.multi_pv = lines as usize,
+            (options::PONDER, OptionValue::Check(ponder)) => settings.ponder = ponder,
+            (options::MOVE_OVERHEAD, OptionValue::Spin(millis)) => settings.move_overhead = Duration::from_millis(millis as u64),
+            (options::PAWN_VALUE, OptionValue::Spin(value)) => settings.eval.piece_values[PieceKind::Pawn.index()] = value as i32,
+            (options::KNIGHT_VALUE, OptionValue::Spin(value)) => settings.eval.piece_values[PieceKind::Knight.index()] = value as i32,
+            (options::BISHOP_VALUE, OptionValue::Spin(value)) => settings.eval.piece_values[PieceKind::Bishop.index()] = value as i32,
+            (options::ROOK_VALUE, OptionValue::Spin(value)) => settings.eval.piece_values[PieceKind::Rook.index()] = value as i32,
+            (options::QUEEN_VALUE, OptionValue::Spin(value)) => settings.eval.piece_values[PieceKind::Queen.index()] = value as i32,
+            (options::UCI_OPPONENT, OptionValue::String(opponent)) => settings.opponent = opponent,
+            (options::UCI_CHESS960, OptionValue::Check(chess960)) => {
+                settings.chess960 = chess960;
+                // positions whose castling can't be written in standard notation stay in Chess960 mode
+                self.game.set_chess960(chess960 || !self.game.start().has_classical_castling());
+            }
+            (options::OWN_BOOK, OptionValue::Check(own_book)) => settings.own_book = own_book,
+            (options::BOOK_FILE, OptionValue::String(file)) => {
+                self.book = match file.as_str() {
+                    "" => None,
+                    path => Some(Arc::new(Book::open(Path::new(path))?)),
+                };
+                settings.book_file = file;
+            }
+            (options::BEST_BOOK_MOVE, OptionValue::Check(best)) => settings.best_book_move = best,
+            (options::SYZYGY_PATH, OptionValue::String(path)) => {
+                self.tablebase = match path.as_str() {
+                    "" => None,
+                    paths => {
+                        let tablebase = Tablebase::open(paths)?;
+                        let found = format!("found {} tablebases with up to {} pieces", tablebase.len(), tablebase.max_pieces());
+                        self.output.send(Response::Info(output::Info::String(found)));
+                        Some(Arc::new(tablebase))
                     }
-                }
-                moves.len() as u64
+                };
+                settings.syzygy_path = path;
             }
-            other => {
-                let mut result = 0;
-                let moves = board.gen_moves(false);
-                for mv in &moves {
-                    let acc = self.performance_test_recursive(&board.make_move(mv), other - 1, false);
-                    if print {
-                        println!("{}: {acc}", mv.to_notation());
+            (options::TABLEBASE_PATH, OptionValue::String(path)) => {
+                self.tables = match path.as_str() {
+                    "" => None,
+                    directory => {
+                        let tables = Tables::open(Path::new(directory))?;
+                        let found = format!("found {} endgame tables with up to {} pieces", tables.len(), tables.max_pieces());
+                        self.output.send(Response::Info(output::Info::String(found)));
+                        Some(Arc::new(tables))
                     }
-                    result += acc;
-                }
-                result
+                };
+                settings.tablebase_path = path;
+            }
+            (options::SKILL_LEVEL, OptionValue::Spin(level)) => settings.skill_level = level as u8,
+            (options::UCI_LIMIT_STRENGTH, OptionValue::Check(limit)) => settings.limit_strength = limit,
+            (options::UCI_ELO, OptionValue::Spin(elo)) => settings.elo = elo as u32,
+            (name, value) => return Err(errors::invalid_input(format!("Unexpected value {value:?} for option {name}"))),
+        }
+        Ok(())
+    }
+
+    pub fn board(&self) -> &Board {
+        self.game.board()
+    }
+
+    pub fn new() -> Engine {
+        Engine::with_output(Arc::new(StdoutOutput))
+    }
+
+    pub fn with_output(output: Arc<dyn Output>) -> Engine {
+        let settings = Settings::default();
+        Engine {
+            game: Game::new(),
+            shared: Arc::new(SharedState::new(settings.hash_mb)),
+            settings,
+            output,
+            book: None,
+            tablebase: None,
+            tables: None,
+            search_thread: None,
+        }
+    }
+
+    // Parses and runs one line of input, errors are sent to the output. Returns false on 'quit'
+    pub fn execute_line(&mut self, line: &str) -> bool {
+        let input = line.trim();
+        if input == "quit" {
+            return false;
+        }
+        if !input.is_empty() {
+            if let Err(err) = Command::parse(input).and_then(|command| self.execute_uci(command)) {
+                self.output.send(Response::Error(err.to_string()));
             }
         }
+        true
     }
 }
@@ -1,24 +1,43 @@
-use std::cmp::Ordering;
 use crate::model::{Board, Move};
 
+// Quiet moves that caused a beta cutoff, weighted by the depth they did it at.
+// Every search thread keeps its own table so the threads drift apart in move order.
+pub struct History {
+    scores: [[i32; 64]; 64],
+}
+
+impl History {
+    pub fn new() -> History {
+        History { scores: [[0; 64]; 64] }
+    }
+
+    pub fn update(&mut self, mv: &Move, depth: i8) {
+        let bonus = depth as i32 * depth as i32;
+        let score = &mut self.scores[mv.from().index()][mv.to().index()];
+        *score = (*score + bonus).min(i32::MAX / 2);
+    }
+
+    pub fn score(&self, mv: &Move) -> i32 {
+        self.scores[mv.from().index()][mv.to().index()]
+    }
+}
+
 impl Board {
-    pub fn order(&self, moves: &Vec<Move>) -> Vec<Move> {
-        let mut result = moves.clone();
-        result.sort_by(|left, right| {
-            if left.is_capture(self) {
-                if right.is_capture(self) {
-                    Ordering::Equal
-                } else {
-                    Ordering::Less
-                }
+    // captures first
+    pub fn order(&self, moves: &mut [Move]) {
+        moves.sort_unstable_by_key(|mv| !mv.is_capture());
+    }
+
+    // hash move first, then captures, then quiet moves by history score
+    pub fn order_with_history(&self, moves: &mut [Move], hash_move: Option<Move>, history: &History) {
+        moves.sort_unstable_by_key(|mv| {
+            if hash_move == Some(*mv) {
+                i32::MIN
+            } else if mv.is_capture() {
+                i32::MIN + 1
             } else {
-                if !right.is_capture(self) {
-                    Ordering::Equal
-                } else {
-                    Ordering::Greater
-                }
+                -history.score(mv)
             }
         });
-        result.to_vec()
     }
-}
\ No newline at end of file
+}
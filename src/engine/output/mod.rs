@@ -0,0 +1,212 @@
+mod tests;
+
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+use std::time::Duration;
+use crate::engine::evaluation::{MATE_BOUND, MATE_SCORE};
+use crate::engine::perft::PerftCounts;
+use crate::model::Move;
+use crate::uci::options::UciOption;
+
+// Everything the engine writes back to the GUI
+#[derive(Clone, Debug)]
+pub enum Response {
+    Id { name: &'static str, author: &'static str },
+    Option(&'static UciOption),
+    UciOk,
+    ReadyOk,
+    BestMove { mv: Option<Move>, ponder: Option<Move> },
+    Info(Info),
+    // perft divide, one line per line of moves
+    Divide { moves: Vec<Move>, nodes: u64 },
+    Perft { depth: u8, counts: PerftCounts, detailed: bool, time: Duration },
+    Bench { depth: u8, nodes: u64, time: Duration },
+    // the board and position details drawn for 'd'
+    Board(String),
+    Error(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Score {
+    Centipawns(i32),
+    // in moves, negative when the side to move gets mated
+    Mate(i32),
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchStats {
+    pub depth: u8,
+    pub seldepth: u32,
+    pub nodes: u64,
+    pub time: Duration,
+    // permille of the hash table used by the current search
+    pub hashfull: u32,
+    // positions found in the endgame tablebases
+    pub tbhits: u64,
+}
+
+#[derive(Clone, Debug)]
+pub enum Info {
+    // a finished iteration, one per MultiPV line
+    Line { stats: SearchStats, multi_pv: usize, score: Score, pv: Vec<Move> },
+    // sent periodically while a long iteration is running
+    Progress(SearchStats),
+    CurrentMove { depth: u8, mv: Move, number: usize },
+    String(String),
+}
+
+pub trait Output: Send + Sync {
+    fn send(&self, response: Response);
+}
+
+pub struct StdoutOutput;
+
+// Keeps everything sent, so that tests can look at it
+#[derive(Default)]
+pub struct CollectingOutput {
+    pub responses: Mutex<Vec<Response>>,
+}
+
+impl Output for StdoutOutput {
+    fn send(&self, response: Response) {
+        println!("{response}");
+    }
+}
+
+// Any closure can be an output, handy for callbacks when the engine is embedded
+impl<F: Fn(Response) + Send + Sync> Output for F {
+    fn send(&self, response: Response) {
+        self(response)
+    }
+}
+
+impl Output for CollectingOutput {
+    fn send(&self, response: Response) {
+        self.responses.lock().unwrap().push(response);
+    }
+}
+
+impl CollectingOutput {
+    // The responses as they would have been printed
+    pub fn lines(&self) -> Vec<String> {
+        self.responses.lock().unwrap().iter().map(|response| response.to_string()).collect()
+    }
+
+    pub fn infos(&self) -> Vec<Info> {
+        let responses = self.responses.lock().unwrap();
+        responses
+            .iter()
+            .filter_map(|response| match response {
+                Response::Info(info) => Some(info.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Score {
+    pub fn from_search(score: i32) -> Score {
+        if (MATE_SCORE..MATE_BOUND).contains(&score) {
+            let plies = score - MATE_SCORE;
+            Score::Mate(-(plies + 1) / 2)
+        } else if (-MATE_BOUND + 1..=-MATE_SCORE).contains(&score) {
+            let plies = -MATE_SCORE - score;
+            Score::Mate((plies + 1) / 2)
+        } else {
+            Score::Centipawns(score)
+        }
+    }
+}
+
+impl SearchStats {
+    pub fn nps(&self) -> u64 {
+        (self.nodes as f64 / self.time.as_secs_f64().max(0.001)) as u64
+    }
+}
+
+impl Display for Response {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Response::Id { name, author } => write!(f, "id name {name}\nid author {author}"),
+            Response::Option(option) => write!(f, "{option}"),
+            Response::UciOk => write!(f, "uciok"),
+            Response::ReadyOk => write!(f, "readyok"),
+            Response::BestMove { mv: None, .. } => write!(f, "bestmove 0000"),
+            Response::BestMove { mv: Some(mv), ponder: None } => write!(f, "bestmove {}", mv.to_notation()),
+            Response::BestMove { mv: Some(mv), ponder: Some(ponder) } => {
+                write!(f, "bestmove {} ponder {}", mv.to_notation(), ponder.to_notation())
+            }
+            Response::Info(info) => write!(f, "{info}"),
+            Response::Divide { moves, nodes } => {
+                let moves: Vec<String> = moves.iter().map(|mv| mv.to_notation()).collect();
+                write!(f, "{}: {nodes}", moves.join(" "))
+            }
+            Response::Perft { depth, counts, detailed, time } => {
+                write!(
+                    f,
+                    "Perf. depth: {depth}\telapsed: {}ms\tnodes: {}\tnps: {:.0}",
+                    time.as_millis(),
+                    counts.nodes,
+                    counts.nodes as f64 / time.as_secs_f64()
+                )?;
+                if *detailed {
+                    write!(
+                        f,
+                        "\ncaptures: {}\tep: {}\tcastles: {}\tpromotions: {}\tchecks: {}\tmates: {}",
+                        counts.captures, counts.en_passants, counts.castles, counts.promotions, counts.checks, counts.mates
+                    )?;
+                }
+                Ok(())
+            }
+            Response::Bench { depth, nodes, time } => write!(
+                f,
+                "Bench. depth: {depth}\telapsed: {}ms\tnodes: {nodes}\tnps: {:.0}",
+                time.as_millis(),
+                *nodes as f64 / time.as_secs_f64()
+            ),
+            Response::Board(board) => write!(f, "{board}"),
+            Response::Error(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl Display for Score {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Score::Centipawns(score) => write!(f, "cp {score}"),
+            Score::Mate(moves) => write!(f, "mate {moves}"),
+        }
+    }
+}
+
+impl Display for SearchStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "depth {} seldepth {}", self.depth, self.seldepth)
+    }
+}
+
+impl Display for Info {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let counters = |stats: &SearchStats| {
+            format!(
+                "nodes {} nps {} hashfull {} tbhits {} time {}",
+                stats.nodes,
+                stats.nps(),
+                stats.hashfull,
+                stats.tbhits,
+                stats.time.as_millis()
+            )
+        };
+        match self {
+            Info::Line { stats, multi_pv, score, pv } => {
+                let pv: Vec<String> = pv.iter().map(|mv| mv.to_notation()).collect();
+                write!(f, "info {stats} multipv {multi_pv} score {score} {} pv {}", counters(stats), pv.join(" "))
+            }
+            Info::Progress(stats) => write!(f, "info {stats} {}", counters(stats)),
+            Info::CurrentMove { depth, mv, number } => {
+                write!(f, "info depth {depth} currmove {} currmovenumber {number}", mv.to_notation())
+            }
+            Info::String(message) => write!(f, "info string {message}"),
+        }
+    }
+}
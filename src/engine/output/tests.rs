@@ -0,0 +1,95 @@
+#[cfg(test)]
+use crate::engine::output::*;
+#[cfg(test)]
+use crate::engine::Engine;
+#[cfg(test)]
+use std::sync::Arc;
+
+#[test]
+fn info_line_format() {
+    let stats = SearchStats {
+        depth: 5,
+        seldepth: 9,
+        nodes: 2000,
+        time: Duration::from_millis(500),
+        hashfull: 12,
+        tbhits: 3,
+    };
+    let line = Info::Line {
+        stats,
+        multi_pv: 1,
+        score: Score::Centipawns(-35),
+        pv: vec!["e2e4".parse().unwrap()],
+    };
+    assert_eq!(
+        "info depth 5 seldepth 9 multipv 1 score cp -35 nodes 2000 nps 4000 hashfull 12 tbhits 3 time 500 pv e2e4",
+        line.to_string()
+    );
+    assert_eq!("info string hello", Info::String(String::from("hello")).to_string());
+}
+
+#[test]
+fn uci_handshake() {
+    let output = Arc::new(CollectingOutput::default());
+    let mut engine = Engine::with_output(output.clone());
+    assert!(engine.execute_line("uci"));
+    assert!(engine.execute_line("isready"));
+    assert!(!engine.execute_line("quit"));
+
+    let lines = output.lines();
+    assert!(lines[0].starts_with("id name crust"));
+    assert!(lines.contains(&String::from("option name Hash type spin default 16 min 1 max 4096")));
+    assert_eq!(["uciok", "readyok"], lines[lines.len() - 2..]);
+}
+
+#[test]
+fn errors_go_to_output() {
+    let output = Arc::new(CollectingOutput::default());
+    let mut engine = Engine::with_output(output.clone());
+    assert!(engine.execute_line("setoption name Hash value lots"));
+    assert!(engine.execute_line("   "));
+    assert_eq!(1, output.responses.lock().unwrap().len());
+    assert!(matches!(output.responses.lock().unwrap()[0], Response::Error(_)));
+}
+
+#[test]
+fn search_ends_with_best_move() {
+    let output = Arc::new(CollectingOutput::default());
+    let mut engine = Engine::with_output(output.clone());
+    engine.execute_line("position fen k7/6R1/8/8/8/8/8/6KR w - - 0 1");
+    engine.execute_line("go depth 2");
+    engine.wait_for_search();
+
+    let lines = output.lines();
+    assert!(lines.iter().any(|line| line.starts_with("info depth 2 ")));
+    assert!(lines.last().unwrap().starts_with("bestmove h1h8"));
+}
+
+#[test]
+fn perft_divide() {
+    let output = Arc::new(CollectingOutput::default());
+    let mut engine = Engine::with_output(output.clone());
+    engine.execute_line("go perft 2");
+
+    let lines = output.lines();
+    assert_eq!(21, lines.len());
+    assert!(lines.contains(&String::from("e2e4: 20")));
+    assert!(lines[20].contains("nodes: 400"));
+}
+
+#[test]
+fn display_command() {
+    let output = Arc::new(CollectingOutput::default());
+    let mut engine = Engine::with_output(output.clone());
+    assert!(engine.execute_line("position startpos moves e2e4 d7d5 f1b5"));
+    assert!(engine.execute_line("d"));
+    let lines = output.lines();
+    let shown: Vec<&str> = lines.iter().flat_map(|line| line.lines()).collect();
+    assert_eq!("5 |   | B |   | p |   |   |   |   |", shown[7]);
+    assert!(shown.contains(&"Side to move: black"));
+    assert!(shown.contains(&"Castling: KQkq"));
+    assert!(shown.contains(&"Checkers: b5"));
+    assert!(shown.contains(&"Eval: +0.00"));
+    assert!(shown.iter().any(|line| line.starts_with("Key: ") && line.len() == 21));
+    assert!(shown.contains(&"Fen: rnbqkbnr/ppp1pppp/8/1B1p4/4P3/8/PPPP1PPP/RNBQK1NR b KQkq - 1 2"));
+}
@@ -0,0 +1,207 @@
+use crate::engine::output::Response;
+use crate::engine::Engine;
+use crate::model::*;
+use crate::uci::PerftCommand;
+use std::ops::AddAssign;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::SystemTime;
+
+pub mod suite;
+mod tests;
+
+// Leaf counters as in the perft result tables, the move kinds are counted for the moves of the last ply
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct PerftCounts {
+    pub nodes: u64,
+    pub captures: u64,
+    pub en_passants: u64,
+    pub castles: u64,
+    pub promotions: u64,
+    pub checks: u64,
+    pub mates: u64,
+}
+
+// Counts of subtrees by position and depth, shared by all perft threads
+struct PerftTable {
+    entries: Vec<Mutex<Option<(u64, u8, PerftCounts)>>>,
+}
+
+impl PerftCounts {
+    fn leaf(board: &Board, mv: &Move) -> PerftCounts {
+        let next = board.make_move(mv);
+        let check = next.is_check();
+        PerftCounts {
+            nodes: 1,
+            captures: mv.is_capture() as u64,
+            en_passants: mv.is_en_passant() as u64,
+            castles: mv.is_castle() as u64,
+            promotions: mv.promote_to().is_some() as u64,
+            checks: check as u64,
+            mates: (check && next.gen_moves(false).is_empty()) as u64,
+        }
+    }
+}
+
+impl AddAssign for PerftCounts {
+    fn add_assign(&mut self, other: PerftCounts) {
+        self.nodes += other.nodes;
+        self.captures += other.captures;
+        self.en_passants += other.en_passants;
+        self.castles += other.castles;
+        self.promotions += other.promotions;
+        self.checks += other.checks;
+        self.mates += other.mates;
+    }
+}
+
+impl PerftTable {
+    fn new(size_mb: usize) -> PerftTable {
+        let entry_size = std::mem::size_of::<Mutex<Option<(u64, u8, PerftCounts)>>>();
+        let count = (size_mb * 1024 * 1024 / entry_size).max(1);
+        PerftTable {
+            entries: (0..count).map(|_| Mutex::new(None)).collect(),
+        }
+    }
+
+    fn entry(&self, hash: u64) -> &Mutex<Option<(u64, u8, PerftCounts)>> {
+        &self.entries[(hash % self.entries.len() as u64) as usize]
+    }
+
+    fn probe(&self, hash: u64, depth: u8) -> Option<PerftCounts> {
+        match *self.entry(hash).lock().unwrap() {
+            Some((key, stored, counts)) if key == hash && stored == depth => Some(counts),
+            _ => None,
+        }
+    }
+
+    fn store(&self, hash: u64, depth: u8, counts: PerftCounts) {
+        *self.entry(hash).lock().unwrap() = Some((hash, depth, counts));
+    }
+}
+
+// Leaves `depth` plies below the board. Without `detailed` only the nodes are counted, which skips making the last moves
+fn perft(board: &Board, depth: u8, detailed: bool, table: Option<&PerftTable>) -> PerftCounts {
+    if depth == 0 {
+        return PerftCounts {
+            nodes: 1,
+            ..PerftCounts::default()
+        };
+    }
+    if let Some(counts) = table.and_then(|table| table.probe(board.hash, depth)) {
+        return counts;
+    }
+    let moves = board.gen_moves(false);
+    let mut counts = PerftCounts::default();
+    if depth == 1 && !detailed {
+        counts.nodes = moves.len() as u64;
+    } else if depth == 1 {
+        for mv in &moves {
+            counts += PerftCounts::leaf(board, mv);
+        }
+    } else {
+        for mv in &moves {
+            counts += perft(&board.make_move(mv), depth - 1, detailed, table);
+        }
+    }
+    if let Some(table) = table {
+        table.store(board.hash, depth, counts);
+    }
+    counts
+}
+
+// Every line of `plies` moves from the board, with the position before its last move
+fn lines(board: &Board, plies: u8) -> Vec<(Vec<Move>, Board)> {
+    let mut lines = vec![(Vec::new(), *board)];
+    for _ in 0..plies {
+        let mut longer = Vec::new();
+        for (moves, before) in lines {
+            let board = moves.last().map_or(before, |mv| before.make_move(mv));
+            for mv in &board.gen_moves(false) {
+                let mut line = moves.clone();
+                line.push(*mv);
+                longer.push((line, board));
+            }
+        }
+        lines = longer;
+    }
+    lines
+}
+
+fn line_perft(moves: &[Move], before: &Board, depth: u8, detailed: bool, table: Option<&PerftTable>) -> PerftCounts {
+    match moves.last() {
+        None => perft(before, depth, detailed, table),
+        Some(mv) if depth == 0 => PerftCounts::leaf(before, mv),
+        Some(mv) => perft(&before.make_move(mv), depth, detailed, table),
+    }
+}
+
+// The counts of every line of the first `divide` plies in move generation order, the lines are shared out to the threads
+fn divide(
+    board: &Board,
+    depth: u8,
+    divide: u8,
+    detailed: bool,
+    table: Option<&PerftTable>,
+    threads: usize,
+) -> Vec<(Vec<Move>, PerftCounts)> {
+    let divide = divide.min(depth);
+    let lines = lines(board, divide);
+    let next = AtomicUsize::new(0);
+    let results: Vec<Mutex<PerftCounts>> = lines.iter().map(|_| Mutex::new(PerftCounts::default())).collect();
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some((moves, before)) = lines.get(index) else {
+                    break;
+                };
+                *results[index].lock().unwrap() = line_perft(moves, before, depth - divide, detailed, table);
+            });
+        }
+    });
+    lines
+        .into_iter()
+        .zip(results)
+        .map(|((moves, _), counts)| (moves, counts.into_inner().unwrap()))
+        .collect()
+}
+
+impl Engine {
+    // Leaf nodes of the current position, single threaded and without output
+    pub fn performance_test(&self, depth: u8) -> u64 {
+        perft(self.board(), depth, false, None).nodes
+    }
+
+    // 'go perft': the lines of the first `divide` plies are sent in move generation order, then the total
+    pub fn perft(&self, command: &PerftCommand) -> PerftCounts {
+        let start = SystemTime::now();
+        let table = (command.hash_mb > 0).then(|| PerftTable::new(command.hash_mb));
+        let threads = command.threads.unwrap_or(self.settings.threads);
+        let mut total = PerftCounts::default();
+        for (moves, counts) in divide(
+            self.board(),
+            command.depth,
+            command.divide,
+            command.detailed,
+            table.as_ref(),
+            threads,
+        ) {
+            if !moves.is_empty() {
+                self.output.send(Response::Divide {
+                    moves,
+                    nodes: counts.nodes,
+                });
+            }
+            total += counts;
+        }
+        self.output.send(Response::Perft {
+            depth: command.depth,
+            counts: total,
+            detailed: command.detailed,
+            time: start.elapsed().unwrap(),
+        });
+        total
+    }
+}
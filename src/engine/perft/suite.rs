@@ -0,0 +1,196 @@
+use crate::engine::perft::*;
+use crate::model::util::errors;
+use std::fmt::{Display, Formatter};
+use crate::uci::client::UciClient;
+use std::io;
+use std::time::Instant;
+
+// A position of an EPD perft file with the expected leaf nodes by depth
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SuitePosition {
+    pub fen: String,
+    pub counts: Vec<(u8, u64)>,
+}
+
+// Where the counts of the engine and the reference first part, the last move of the path is generated by one side only
+// unless both counts are known
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Mismatch {
+    pub path: Vec<String>,
+    pub actual: Option<u64>,
+    pub expected: Option<u64>,
+}
+
+// Counts of a position on all threads, sharing one perft table between the positions
+pub struct SuiteRunner {
+    table: Option<PerftTable>,
+    threads: usize,
+}
+
+// Perft divide of another move generator, the counts of the moves after the position and the moves played from it
+pub trait Reference {
+    fn divide(&mut self, fen: &str, moves: &[String], depth: u8) -> io::Result<Vec<(String, u64)>>;
+}
+
+// 'fen ;D1 20 ;D2 400 ...' per line, empty lines and lines starting with '#' are skipped
+pub fn parse_suite(text: &str) -> io::Result<Vec<SuitePosition>> {
+    let mut positions = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split(';');
+        let fen = fields.next().unwrap().trim();
+        Board::from_fen(String::from(fen))?;
+        let mut counts = Vec::new();
+        for field in fields.map(str::trim) {
+            let invalid = || errors::invalid_input(format!("Invalid perft count '{field}' on line {}", number + 1));
+            let (depth, nodes) = field
+                .strip_prefix('D')
+                .and_then(|field| field.split_once(' '))
+                .ok_or_else(invalid)?;
+            counts.push((depth.parse().map_err(|_| invalid())?, nodes.trim().parse().map_err(|_| invalid())?));
+        }
+        positions.push(SuitePosition {
+            fen: String::from(fen),
+            counts,
+        });
+    }
+    Ok(positions)
+}
+
+impl SuiteRunner {
+    pub fn new(hash_mb: usize, threads: usize) -> SuiteRunner {
+        SuiteRunner {
+            table: (hash_mb > 0).then(|| PerftTable::new(hash_mb)),
+            threads,
+        }
+    }
+
+    pub fn count(&self, board: &Board, depth: u8) -> u64 {
+        self.divide(board, depth).iter().map(|(_, nodes)| nodes).sum()
+    }
+
+    fn divide(&self, board: &Board, depth: u8) -> Vec<(Move, u64)> {
+        let lines = divide(board, depth, 1, false, self.table.as_ref(), self.threads);
+        match depth {
+            0 => Vec::new(),
+            _ => lines.into_iter().map(|(moves, counts)| (moves[0], counts.nodes)).collect(),
+        }
+    }
+
+    // Counts every position up to `max_depth` and returns the passed and failed counts. The first mismatch is bisected
+    // against the reference, `report` gets a line per count and for the bisection
+    pub fn run<R: Reference>(
+        &self,
+        positions: &[SuitePosition],
+        max_depth: u8,
+        mut reference: Option<&mut R>,
+        mut report: impl FnMut(&str),
+    ) -> io::Result<(usize, usize)> {
+        let (mut passed, mut failed) = (0, 0);
+        for (number, position) in positions.iter().enumerate() {
+            let board = Board::from_fen(position.fen.clone())?;
+            for &(depth, expected) in position.counts.iter().filter(|(depth, _)| *depth <= max_depth) {
+                let counted = Instant::now();
+                let nodes = self.count(&board, depth);
+                let elapsed = counted.elapsed().as_millis();
+                if nodes == expected {
+                    passed += 1;
+                    report(&format!("#{} depth {depth}: {nodes} ok in {elapsed}ms", number + 1));
+                    continue;
+                }
+                failed += 1;
+                report(&format!(
+                    "#{} depth {depth}: {nodes} FAILED, expected {expected} in {elapsed}ms: {}",
+                    number + 1,
+                    position.fen
+                ));
+                if failed == 1 {
+                    match reference.as_deref_mut() {
+                        None => report("bisect with --reference <engine>"),
+                        Some(reference) => match self.bisect(&position.fen, depth, reference)? {
+                            None => report("the reference engine agrees with crust"),
+                            Some(mismatch) => report(&mismatch.to_string()),
+                        },
+                    }
+                }
+                // the deeper counts differ as well
+                break;
+            }
+        }
+        Ok((passed, failed))
+    }
+
+    // Follows the first move whose count differs from the reference until a move is generated by one side only.
+    // Moves missing on one side are reported before the differing counts of the same position
+    pub fn bisect(&self, fen: &str, depth: u8, reference: &mut impl Reference) -> io::Result<Option<Mismatch>> {
+        let mut board = Board::from_fen(String::from(fen))?;
+        let mut path = Vec::new();
+        for depth in (1..=depth).rev() {
+            let ours = self.divide(&board, depth);
+            let theirs = reference.divide(fen, &path, depth)?;
+            let actual = |notation: &str| ours.iter().find(|(mv, _)| mv.to_notation() == notation).copied();
+            let expected = |notation: &str| theirs.iter().find(|(mv, _)| mv == notation).map(|&(_, nodes)| nodes);
+            let mut differences: Vec<(String, Option<u64>, Option<u64>)> = ours
+                .iter()
+                .map(|(mv, nodes)| (mv.to_notation(), Some(*nodes), expected(&mv.to_notation())))
+                .chain(
+                    theirs
+                        .iter()
+                        .filter(|(mv, _)| actual(mv).is_none())
+                        .map(|(mv, nodes)| (mv.clone(), None, Some(*nodes))),
+                )
+                .filter(|(_, actual, expected)| actual != expected)
+                .collect();
+            differences.sort_by_key(|(_, actual, expected)| actual.is_some() && expected.is_some());
+            let Some((notation, actual_nodes, expected_nodes)) = differences.into_iter().next() else {
+                return Ok(None);
+            };
+            path.push(notation);
+            if actual_nodes.is_none() || expected_nodes.is_none() {
+                return Ok(Some(Mismatch {
+                    path,
+                    actual: actual_nodes,
+                    expected: expected_nodes,
+                }));
+            }
+            board = board.make_move(&actual(path.last().unwrap()).unwrap().0);
+        }
+        Ok(None)
+    }
+}
+
+// A UCI engine answering 'go perft' with a 'move: nodes' line per move
+impl Reference for UciClient {
+    fn divide(&mut self, fen: &str, moves: &[String], depth: u8) -> io::Result<Vec<(String, u64)>> {
+        let chess960 = Board::from_fen(String::from(fen))?.chess960;
+        self.set_option("UCI_Chess960", &chess960.to_string())?;
+        match moves.is_empty() {
+            true => self.send(&format!("position fen {fen}"))?,
+            false => self.send(&format!("position fen {fen} moves {}", moves.join(" ")))?,
+        }
+        self.send(&format!("go perft {depth}"))?;
+        // perft answers before 'isready' in the engines that run it synchronously
+        self.send("isready")?;
+        let lines = self.read_until("readyok", None)?;
+        Ok(lines
+            .iter()
+            .filter_map(|line| line.split_once(": "))
+            .filter(|(mv, _)| !mv.contains(char::is_whitespace))
+            .filter_map(|(mv, nodes)| Some((String::from(mv), nodes.trim().parse().ok()?)))
+            .collect())
+    }
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.path.join(" "))?;
+        match (self.actual, self.expected) {
+            (Some(actual), Some(expected)) => write!(f, "{actual} nodes, expected {expected}"),
+            (Some(_), None) => write!(f, "generated but not legal"),
+            _ => write!(f, "legal but not generated"),
+        }
+    }
+}
@@ -0,0 +1,175 @@
+#[cfg(test)]
+use crate::engine::output::CollectingOutput;
+#[cfg(test)]
+use crate::engine::perft::*;
+#[cfg(test)]
+use std::sync::Arc;
+
+#[cfg(test)]
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+// nodes, captures, en passants, castles, promotions, checks and mates from the perft result tables
+#[cfg(test)]
+fn counts(fen: &str, depth: u8) -> [u64; 7] {
+    let counts = perft(&Board::from_fen(String::from(fen)).unwrap(), depth, true, None);
+    [
+        counts.nodes,
+        counts.captures,
+        counts.en_passants,
+        counts.castles,
+        counts.promotions,
+        counts.checks,
+        counts.mates,
+    ]
+}
+
+#[cfg(test)]
+fn perft_command(depth: u8, divide: u8, hash_mb: usize, threads: usize) -> PerftCommand {
+    PerftCommand {
+        depth,
+        divide,
+        hash_mb,
+        threads: Some(threads),
+        detailed: true,
+    }
+}
+
+#[test]
+fn detailed_counts() {
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    assert_eq!([8_902, 34, 0, 0, 0, 12, 0], counts(start, 3));
+    assert_eq!([197_281, 1_576, 0, 0, 0, 469, 8], counts(start, 4));
+    assert_eq!([2_039, 351, 1, 91, 0, 3, 0], counts(KIWIPETE, 2));
+    assert_eq!([97_862, 17_102, 45, 3_162, 0, 993, 1], counts(KIWIPETE, 3));
+    assert_eq!(
+        [43_238, 3_348, 123, 0, 0, 1_680, 17],
+        counts("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 4)
+    );
+    assert_eq!(
+        [9_467, 1_021, 4, 0, 120, 38, 22],
+        counts("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 3)
+    );
+    assert_eq!([1, 0, 0, 0, 0, 0, 0], counts(start, 0));
+}
+
+#[test]
+fn hashed_and_parallel_perft() {
+    let mut engine = Engine::with_output(Arc::new(CollectingOutput::default()));
+    engine.game = Board::from_fen(String::from(KIWIPETE)).unwrap().into();
+    let plain = engine.perft(&perft_command(3, 1, 0, 1));
+    assert_eq!(97_862, plain.nodes);
+    assert_eq!(plain, engine.perft(&perft_command(3, 1, 1, 1)));
+    assert_eq!(plain, engine.perft(&perft_command(3, 2, 1, 3)));
+    assert_eq!(plain, engine.perft(&perft_command(3, 3, 0, 2)));
+    assert_eq!(plain, engine.perft(&perft_command(3, 0, 0, 2)));
+}
+
+#[test]
+fn divide_at_any_depth() {
+    let output = Arc::new(CollectingOutput::default());
+    let mut engine = Engine::with_output(output.clone());
+    engine.execute_line("go perft 3 divide 2 threads 2 detailed");
+    let lines = output.lines();
+    assert_eq!(400 + 1, lines.len());
+    assert!(lines.contains(&String::from("a2a3 a7a6: 19")));
+    assert!(lines.contains(&String::from("e2e4 d7d5: 31")));
+    assert!(lines[400].contains("nodes: 8902"));
+    assert!(lines[400].ends_with("\ncaptures: 34\tep: 0\tcastles: 0\tpromotions: 0\tchecks: 12\tmates: 0"));
+}
+
+// Divide of crust itself with the move `dropped` left out or `added` made up after the line `after`
+#[cfg(test)]
+struct BrokenReference {
+    after: Vec<String>,
+    dropped: Option<&'static str>,
+    added: Option<&'static str>,
+}
+
+#[cfg(test)]
+impl BrokenReference {
+    fn moves(&self, board: &Board, line: &[String]) -> Vec<(String, Option<Move>)> {
+        let mut moves: Vec<(String, Option<Move>)> = board.gen_moves(false).iter().map(|mv| (mv.to_notation(), Some(*mv))).collect();
+        if line == self.after {
+            moves.retain(|(notation, _)| Some(notation.as_str()) != self.dropped);
+            moves.extend(self.added.map(|notation| (String::from(notation), None)));
+        }
+        moves
+    }
+
+    // a made up move counts as a single leaf
+    fn count(&self, board: &Board, line: &mut Vec<String>, depth: u8) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let mut nodes = 0;
+        for (notation, mv) in self.moves(board, line) {
+            line.push(notation);
+            nodes += mv.map_or(1, |mv| self.count(&board.make_move(&mv), line, depth - 1));
+            line.pop();
+        }
+        nodes
+    }
+}
+
+#[cfg(test)]
+impl suite::Reference for BrokenReference {
+    fn divide(&mut self, fen: &str, moves: &[String], depth: u8) -> std::io::Result<Vec<(String, u64)>> {
+        let mut board = Board::from_fen(String::from(fen))?;
+        let mut line = moves.to_vec();
+        for notation in moves {
+            let mv = *board.gen_moves(false).iter().find(|mv| &mv.to_notation() == notation).unwrap();
+            board = board.make_move(&mv);
+        }
+        let divide = self.moves(&board, &line).into_iter().map(|(notation, mv)| {
+            line.push(notation.clone());
+            let nodes = mv.map_or(1, |mv| self.count(&board.make_move(&mv), &mut line, depth - 1));
+            line.pop();
+            (notation, nodes)
+        });
+        Ok(divide.collect())
+    }
+}
+
+#[test]
+fn suite_file() {
+    let positions = suite::parse_suite("# comment\n\n8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1 ;D1 14 ;D2 191\n").unwrap();
+    assert_eq!(1, positions.len());
+    assert_eq!("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", positions[0].fen);
+    assert_eq!(vec![(1, 14), (2, 191)], positions[0].counts);
+    assert!(suite::parse_suite("8/8/8/8/8/8/8/8 w - - 0 1 ;D1").is_err());
+    assert!(suite::parse_suite("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1 ;E1 14").is_err());
+    assert!(suite::parse_suite("not a fen ;D1 14").is_err());
+}
+
+#[test]
+fn bisect_by_divide() {
+    let runner = suite::SuiteRunner::new(1, 2);
+    let board = Board::from_fen(String::from(KIWIPETE)).unwrap();
+    assert_eq!(97_862, runner.count(&board, 3));
+    let path = |moves: &[&str]| moves.iter().map(|mv| String::from(*mv)).collect::<Vec<_>>();
+    let mut agreeing = BrokenReference {
+        after: Vec::new(),
+        dropped: None,
+        added: None,
+    };
+    assert_eq!(None, runner.bisect(KIWIPETE, 3, &mut agreeing).unwrap());
+
+    let mut missing = BrokenReference {
+        after: path(&["e2a6", "b4c3"]),
+        dropped: None,
+        added: Some("a1a8"),
+    };
+    let mismatch = runner.bisect(KIWIPETE, 3, &mut missing).unwrap().unwrap();
+    assert_eq!(path(&["e2a6", "b4c3", "a1a8"]), mismatch.path);
+    assert_eq!((None, Some(1)), (mismatch.actual, mismatch.expected));
+    assert_eq!("e2a6 b4c3 a1a8: legal but not generated", mismatch.to_string());
+
+    let mut illegal = BrokenReference {
+        after: path(&["e1g1"]),
+        dropped: Some("e7d6"),
+        added: None,
+    };
+    let mismatch = runner.bisect(KIWIPETE, 3, &mut illegal).unwrap().unwrap();
+    assert_eq!(path(&["e1g1", "e7d6"]), mismatch.path);
+    assert_eq!("e1g1 e7d6: generated but not legal", mismatch.to_string());
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+use crate::engine::searcher::MAX_DEPTH;
+use crate::model::Move;
+
+// Depth used when `go` comes without any limit at all
+pub const DEFAULT_DEPTH: u8 = 3;
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+#[derive(Clone, Default, Debug)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub move_time: Option<Duration>,
+    pub time_left: Option<Duration>,
+    pub increment: Duration,
+    pub moves_to_go: Option<u32>,
+    pub infinite: bool,
+    pub search_moves: Vec<Move>,
+}
+
+// Iterations are not started after `soft` has passed, the search is aborted at `hard`
+#[derive(Clone, Copy, Debug)]
+pub struct TimeBudget {
+    pub soft: Duration,
+    pub hard: Duration,
+}
+
+impl SearchLimits {
+    pub fn max_depth(&self) -> u8 {
+        match self.depth {
+            Some(depth) => depth.clamp(1, MAX_DEPTH),
+            None if self.is_unlimited() => DEFAULT_DEPTH,
+            None => MAX_DEPTH,
+        }
+    }
+
+    pub fn time_budget(&self) -> Option<TimeBudget> {
+        if self.infinite {
+            return None;
+        }
+        if let Some(time) = self.move_time {
+            return Some(TimeBudget { soft: time, hard: time });
+        }
+        self.time_left.map(|left| {
+            let moves_to_go = self.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+            let target = left / moves_to_go + self.increment * 3 / 4;
+            let hard = (target * 2).min(left * 3 / 4);
+            TimeBudget {
+                soft: (target / 2).min(hard),
+                hard,
+            }
+        })
+    }
+
+    fn is_unlimited(&self) -> bool {
+        !self.infinite && self.nodes.is_none() && self.move_time.is_none() && self.time_left.is_none()
+    }
+}
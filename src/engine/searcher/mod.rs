@@ -1,72 +1,123 @@
+mod limits;
 mod tests;
 
-use std::collections::LinkedList;
-use std::fmt::{Display, Formatter};
+pub use limits::{SearchLimits, TimeBudget};
+
+use std::collections::{HashMap, LinkedList};
 use std::ops::Neg;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
 use std::time::{Duration, SystemTime};
-use crate::engine::Engine;
-use crate::model::{Board, Move};
+use crate::engine::{Engine, SharedState};
+use crate::engine::output::{Info, Output, Response, Score, SearchStats};
+use crate::engine::skill::EvalNoise;
+use crate::engine::syzygy::{RankedMove, Tablebase, Wdl};
+use crate::engine::tablebase::Tables;
+use crate::engine::evaluation::{EvalParams, MATE_BOUND, MATE_SCORE};
+use crate::engine::ordering::History;
+use crate::engine::transposition::{Bound, Entry, TranspositionTable};
+use crate::model::{Board, Move, PieceKind};
+
+pub const MAX_DEPTH: u8 = 64;
+// how often (in nodes) the main thread looks at the clock
+const CHECK_INTERVAL: u64 = 1024;
+// currmove and progress lines are only sent after this much time, and then at most this often
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+// Helper threads skip some iterations so that they don't all search the same depth.
+// Thread i uses row (i - 1) % 20.
+static SKIP_SIZE: [u8; 20] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+static SKIP_PHASE: [u8; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+// Syzygy tables are probed after captures and pawn moves, and otherwise only with this many plies left
+const TB_PROBE_DEPTH: i8 = 4;
 
 #[derive(Clone, Copy)]
 pub struct SearchState {
-    pub score: f32,
     pub board: Board,
     pub alpha: i32,
     pub beta: i32,
     pub depth_left: i8,
     pub current_depth: u32,
-    pub start: SystemTime,
+    // the move into the position was a capture or a pawn move, so the material or the pawns changed
+    pub zeroing: bool,
 }
 
+// Result of searching a single node: its score and the best line from it
 #[derive(Clone)]
-pub struct SearchResult {
+pub struct NodeResult {
     pub moves: LinkedList<Move>,
     pub score: i32,
-    pub evaluations: u64,
 }
 
-impl SearchResult {
+// One root move with its score and principal variation, MultiPV reports one of these per line
+#[derive(Clone)]
+pub struct SearchResult {
+    pub mv: Move,
+    pub score: i32,
+    pub depth: u8,
+    pub pv: Vec<Move>,
+}
+
+// The last completed iteration of a single search thread, lines sorted by score
+#[derive(Clone)]
+pub struct ThreadResult {
+    pub depth: u8,
+    pub lines: Vec<SearchResult>,
+}
+
+pub struct Searcher<'a> {
+    id: usize,
+    tt: &'a TranspositionTable,
+    stop: &'a AtomicBool,
+    pondering: &'a AtomicBool,
+    // node counters of all threads, this searcher writes to nodes[id]
+    nodes: &'a [AtomicU64],
+    tablebase: Option<&'a Tablebase>,
+    tables: Option<&'a Tables>,
+    tb_hits: &'a AtomicU64,
+    limits: &'a SearchLimits,
+    eval: &'a EvalParams,
+    noise: Option<EvalNoise>,
+    // MultiPV lines sent to the GUI, a weakened search has more candidates than it reports
+    reported_lines: usize,
+    budget: Option<TimeBudget>,
+    // the clock for the time budget starts again on 'ponderhit'
+    start: SystemTime,
+    was_pondering: bool,
+    search_start: SystemTime,
+    last_report: SystemTime,
+    root_depth: u8,
+    seldepth: u32,
+    // only the main thread reports
+    output: Option<&'a dyn Output>,
+    history: History,
+}
+
+impl NodeResult {
     pub fn flip(&self) -> Self {
         Self {
             score: self.score.neg(),
             moves: self.moves.clone(),
-            ..*self
         }
     }
 
-    pub fn new(score: i32, evaluations: u64) -> SearchResult {
-        SearchResult {
+    pub fn new(score: i32) -> NodeResult {
+        NodeResult {
             score,
             moves: LinkedList::new(),
-            evaluations,
         }
     }
 }
 
-impl Display for SearchResult {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let mut result = String::new();
-
-        result.push_str(format!("nodes {} score cp {} pv ", self.evaluations, self.score).as_str());
-        for mv in self.moves.iter() {
-            result.push_str(mv.to_notation().as_str());
-            result.push_str(" ");
-        }
-
-        write!(f, "{}", result)
-    }
-}
-
 impl SearchState {
     pub fn initial(board: Board, depth: u8) -> SearchState {
         SearchState {
-            score: 0f32,
             board,
             alpha: i32::MIN + 1,
             beta: i32::MAX - 1,
             depth_left: depth as i8,
             current_depth: 0,
-            start: SystemTime::now(),
+            zeroing: false,
         }
     }
 
@@ -77,50 +128,379 @@ impl SearchState {
             depth_left: self.depth_left - 1,
             board: self.board.make_move(mv),
             current_depth: self.current_depth + 1,
-            ..*self
+            zeroing: mv.is_capture() || self.board.piece_at(mv.from()).is_some_and(|piece| piece.kind == PieceKind::Pawn),
         }
     }
+}
+
+impl Engine {
+    // The move to play, None when there are no legal moves or none of the search moves is legal
+    pub fn search(&self, limits: &SearchLimits) -> Option<Move> {
+        match self.settings.skill().choose(&self.search_lines(limits), &mut rand::thread_rng()) {
+            None => self.fallback_move(&limits.search_moves),
+            Some(line) => Some(line.mv),
+        }
+    }
+
+    // The first legal move out of `search_moves`, for a search stopped before it finished a root move
+    pub(crate) fn fallback_move(&self, search_moves: &[Move]) -> Option<Move> {
+        let moves = self.board().gen_moves(false);
+        moves.into_iter().find(|mv| search_moves.is_empty() || search_moves.contains(mv))
+    }
 
-    pub fn elapsed(&self) -> Duration {
-        self.start.elapsed().unwrap().max(Duration::from_millis(1))
+    // Runs the search and returns up to `multi_pv` best root moves, best first. Below full strength there are
+    // enough lines for the skill to choose from
+    pub fn search_lines(&self, limits: &SearchLimits) -> Vec<SearchResult> {
+        self.shared.stop.store(false, Ordering::Relaxed);
+        self.shared.pondering.store(false, Ordering::Relaxed);
+        self.run_search(limits)
+    }
+
+    pub fn run_search(&self, limits: &SearchLimits) -> Vec<SearchResult> {
+        let shared = &*self.shared;
+        let threads = self.settings.threads;
+        let nodes: Vec<AtomicU64> = (0..threads).map(|_| AtomicU64::new(0)).collect();
+        let tb_hits = AtomicU64::new(0);
+        let skill = self.settings.skill();
+        let limits = &skill.limit(&self.tablebase_root(limits, &tb_hits));
+        let (multi_pv, noise) = (skill.multi_pv(self.settings.multi_pv), skill.eval_noise());
+        let start = SystemTime::now();
+        shared.tt.new_search();
+        if threads > 1 {
+            self.output.send(Response::Info(Info::String(format!("searching with {threads} threads"))));
+        }
+
+        let results: Vec<ThreadResult> = thread::scope(|scope| {
+            let helpers: Vec<_> = (1..threads)
+                .map(|id| {
+                    let mut searcher = Searcher::new(id, self, (&nodes, &tb_hits), limits, start);
+                    searcher.noise = noise;
+                    scope.spawn(move || searcher.iterate(*self.board(), limits.max_depth(), multi_pv))
+                })
+                .collect();
+
+            let mut main = Searcher::new(0, self, (&nodes, &tb_hits), limits, start);
+            main.noise = noise;
+            let main_result = main.iterate(*self.board(), limits.max_depth(), multi_pv);
+            shared.stop.store(true, Ordering::Relaxed);
+
+            let mut results = vec![main_result];
+            for helper in helpers {
+                results.push(helper.join().expect("Search thread panicked"));
+            }
+            results
+        });
+
+        let total: u64 = nodes.iter().map(|counter| counter.load(Ordering::Relaxed)).sum();
+        shared.last_nodes.store(total, Ordering::Relaxed);
+        let best = vote(&results);
+        results.into_iter().nth(best).map(|thread| thread.lines).unwrap_or_default()
+    }
+
+    // With the root in the tablebases only the moves keeping the best result are searched, and
+    // when winning only the one closest to zeroing the fifty move counter, so that the win makes progress
+    fn tablebase_root(&self, limits: &SearchLimits, tb_hits: &AtomicU64) -> SearchLimits {
+        let mut limits = limits.clone();
+        let Some(tablebase) = self.tablebase.as_deref().filter(|tablebase| tablebase.covers(self.board())) else {
+            return limits;
+        };
+        let Some(ranked) = tablebase.rank_moves(self.board(), self.game.halfmove_clock()) else {
+            return limits;
+        };
+        tb_hits.fetch_add(ranked.len() as u64, Ordering::Relaxed);
+        let allowed: Vec<&RankedMove> = ranked
+            .iter()
+            .filter(|line| limits.search_moves.is_empty() || limits.search_moves.contains(&line.mv))
+            .collect();
+        let Some(best) = allowed.first() else {
+            return limits;
+        };
+        limits.search_moves = match best.wdl {
+            Wdl::Win => vec![best.mv],
+            wdl => allowed.iter().filter(|line| line.wdl == wdl).map(|line| line.mv).collect(),
+        };
+        limits
     }
 }
 
-impl Engine {
-    pub fn search(&self) -> Move {
-        let mut result = SearchResult::new(0, 0);
+// Every thread votes for its best move with its score and completed depth,
+// the thread whose move collected the most votes wins.
+fn vote(results: &[ThreadResult]) -> usize {
+    let best_line = |thread: &ThreadResult| thread.lines.first().map(|line| (line.mv, line.score as i64));
+    let min_score = results.iter().filter_map(best_line).map(|(_, score)| score).min().unwrap_or(0);
+    let mut votes: HashMap<Move, i64> = HashMap::new();
+    for thread in results {
+        if let Some((mv, score)) = best_line(thread) {
+            *votes.entry(mv).or_insert(0) += (score - min_score + 14) * thread.depth as i64;
+        }
+    }
+    let votes_for = |thread: &ThreadResult| best_line(thread).map_or(0, |(mv, _)| votes[&mv]);
 
-        for i in 1..4 {
-            let state = SearchState::initial(self.board, i);
-            result = self.search_req(state);
-            println!("info depth {} {} time {} nps {:.0}", i, result, state.elapsed().as_millis(), result.evaluations as f64 / state.elapsed().as_secs_f64());
+    let mut best = 0;
+    for (index, thread) in results.iter().enumerate().skip(1) {
+        if votes_for(thread) > votes_for(&results[best]) {
+            best = index;
         }
+    }
+    best
+}
+
+impl<'a> Searcher<'a> {
+    // `counters` are the node counters of all threads and the tablebase hits
+    pub fn new(
+        id: usize,
+        engine: &'a Engine,
+        counters: (&'a [AtomicU64], &'a AtomicU64),
+        limits: &'a SearchLimits,
+        start: SystemTime,
+    ) -> Searcher<'a> {
+        let shared: &SharedState = &engine.shared;
+        let output: &dyn Output = &*engine.output;
+        Searcher {
+            id,
+            tt: &shared.tt,
+            stop: &shared.stop,
+            pondering: &shared.pondering,
+            nodes: counters.0,
+            tablebase: engine.tablebase.as_deref(),
+            tables: engine.tables.as_deref(),
+            tb_hits: counters.1,
+            limits,
+            eval: &engine.settings.eval,
+            noise: None,
+            reported_lines: engine.settings.multi_pv,
+            budget: limits.time_budget(),
+            start,
+            was_pondering: false,
+            search_start: start,
+            last_report: start,
+            root_depth: 0,
+            seldepth: 0,
+            output: if id == 0 { Some(output) } else { None },
+            history: History::new(),
+        }
+    }
+
+    pub fn iterate(&mut self, board: Board, max_depth: u8, multi_pv: usize) -> ThreadResult {
+        let mut completed = ThreadResult {
+            depth: 0,
+            lines: Vec::new(),
+        };
+        for depth in 1..=max_depth {
+            if self.stopped() {
+                break;
+            }
+            if self.skip_depth(depth) {
+                continue;
+            }
+            self.root_depth = depth;
+            self.seldepth = 0;
+            let mut lines: Vec<SearchResult> = Vec::new();
+            while lines.len() < multi_pv {
+                let excluded: Vec<Move> = lines.iter().map(|line| line.mv).collect();
+                match self.search_root(board, depth, &excluded) {
+                    Some(line) => lines.push(line),
+                    None => break,
+                }
+            }
+            lines.sort_by_key(|line| line.score.neg());
+            if self.stopped() {
+                // an interrupted first iteration is still better than no move at all
+                if completed.lines.is_empty() && !lines.is_empty() {
+                    completed = ThreadResult { depth, lines };
+                }
+                break;
+            }
+            self.report_lines(&lines);
+            completed = ThreadResult { depth, lines };
+            if self.id == 0 && self.out_of_time(|budget| budget.soft) {
+                break;
+            }
+        }
+        completed
+    }
 
-        match result.moves.pop_front() {
-            None => {
-                self.board.gen_moves(false).first().unwrap().clone()
+    // Finds the best root move that is not in `excluded`, so that every MultiPV line is a different move
+    fn search_root(&mut self, board: Board, depth: u8, excluded: &[Move]) -> Option<SearchResult> {
+        let mut state = SearchState::initial(board, depth);
+        self.count_node(0);
+        let hash_move = self.tt.probe(board.hash).and_then(|entry| entry.best_move);
+        let mut moves = board.gen_moves(false);
+        board.order_with_history(&mut moves, hash_move, &self.history);
+        let search_moves = &self.limits.search_moves;
+        let mut best: Option<SearchResult> = None;
+        let root_moves = moves
+            .into_iter()
+            .filter(|mv| !excluded.contains(mv))
+            .filter(|mv| search_moves.is_empty() || search_moves.contains(mv));
+        for (index, mv) in root_moves.enumerate() {
+            if let Some(output) = self.output.filter(|_| self.since(self.search_start) >= REPORT_INTERVAL) {
+                output.send(Response::Info(Info::CurrentMove {
+                    depth,
+                    mv,
+                    number: excluded.len() + index + 1,
+                }));
             }
-            Some(move_found) => {
-                move_found
+            let move_result = self.search_req(state.make_move(&mv)).flip();
+            if self.stopped() {
+                break;
+            }
+            if best.is_none() || move_result.score > state.alpha {
+                state.alpha = move_result.score;
+                let mut pv = vec![mv];
+                pv.extend(move_result.moves);
+                best = Some(SearchResult {
+                    mv,
+                    score: move_result.score,
+                    depth,
+                    pv,
+                });
             }
         }
+        if let (Some(line), true, false) = (&best, excluded.is_empty(), self.stopped()) {
+            self.store(&state, Some(line.mv), line.score, Bound::Exact);
+        }
+        best
+    }
+
+    fn since(&self, time: SystemTime) -> Duration {
+        time.elapsed().unwrap_or_default()
+    }
+
+    fn stats(&self, depth: u8) -> SearchStats {
+        SearchStats {
+            depth,
+            seldepth: self.seldepth,
+            nodes: self.total_nodes(),
+            time: self.since(self.search_start),
+            hashfull: self.tt.hashfull(),
+            tbhits: self.tb_hits.load(Ordering::Relaxed),
+        }
     }
 
+    fn report_lines(&mut self, lines: &[SearchResult]) {
+        let Some(output) = self.output else {
+            return;
+        };
+        for (index, line) in lines.iter().take(self.reported_lines).enumerate() {
+            output.send(Response::Info(Info::Line {
+                stats: self.stats(line.depth),
+                multi_pv: index + 1,
+                score: Score::from_search(line.score),
+                pv: line.pv.clone(),
+            }));
+        }
+        self.last_report = SystemTime::now();
+    }
+
+    // Long iterations send a progress line every REPORT_INTERVAL
+    fn report_progress(&mut self, depth: u8) {
+        let Some(output) = self.output else {
+            return;
+        };
+        if self.since(self.last_report) >= REPORT_INTERVAL {
+            output.send(Response::Info(Info::Progress(self.stats(depth))));
+            self.last_report = SystemTime::now();
+        }
+    }
 
-    pub fn search_req(&self, mut state: SearchState) -> SearchResult {
-        let moves = state.board.order(&state.board.gen_moves(false));
-        if state.depth_left == 0 || moves.is_empty() {
+    fn total_nodes(&self) -> u64 {
+        self.nodes.iter().map(|counter| counter.load(Ordering::Relaxed)).sum()
+    }
+
+    // Time never runs out while pondering
+    fn out_of_time(&mut self, limit: fn(&TimeBudget) -> Duration) -> bool {
+        let Some(budget) = self.budget else {
+            return false;
+        };
+        if self.pondering.load(Ordering::Relaxed) {
+            self.was_pondering = true;
+            return false;
+        }
+        if self.was_pondering {
+            self.was_pondering = false;
+            self.start = SystemTime::now();
+        }
+        self.since(self.start) >= limit(&budget)
+    }
+
+    // Counts a node and stops all threads once the node or time limit is reached
+    fn count_node(&mut self, ply: u32) {
+        self.seldepth = self.seldepth.max(ply);
+        let own = self.nodes[self.id].fetch_add(1, Ordering::Relaxed) + 1;
+        if self.limits.nodes.is_some_and(|limit| self.total_nodes() >= limit) {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+        if self.id == 0 && own.is_multiple_of(CHECK_INTERVAL) {
+            if self.out_of_time(|budget| budget.hard) {
+                self.stop.store(true, Ordering::Relaxed);
+            }
+            self.report_progress(self.root_depth);
+        }
+    }
+
+    fn skip_depth(&self, depth: u8) -> bool {
+        if self.id == 0 {
+            return false;
+        }
+        let row = (self.id - 1) % SKIP_SIZE.len();
+        ((depth + SKIP_PHASE[row]) / SKIP_SIZE[row]) % 2 == 1
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    pub fn search_req(&mut self, mut state: SearchState) -> NodeResult {
+        if self.stopped() {
+            return NodeResult::new(0);
+        }
+        if state.depth_left == 0 {
             return self.quiescence(state);
         }
-        let mut evaluation_counter = 0u64;
+        self.count_node(state.current_depth);
+        let hash = state.board.hash;
+        let entry = self.tt.probe(hash);
+        if let Some(entry) = entry {
+            if state.current_depth > 0 && entry.depth as i8 >= state.depth_left {
+                let score = score_from_tt(entry.score, state.current_depth);
+                match entry.bound {
+                    Bound::Exact => return NodeResult::new(score.clamp(state.alpha, state.beta)),
+                    Bound::Lower if score >= state.beta => return NodeResult::new(state.beta),
+                    Bound::Upper if score <= state.alpha => return NodeResult::new(state.alpha),
+                    _ => (),
+                }
+            }
+        }
+
+        let mut moves = state.board.gen_moves(false);
+        state.board.order_with_history(&mut moves, entry.and_then(|entry| entry.best_move), &self.history);
+        if moves.is_empty() {
+            let eval = state.board.evaluate(self.eval, state.current_depth);
+            return NodeResult::new(eval.clamp(state.alpha, state.beta));
+        }
+        if let Some(score) = self.probe_tables(&state.board, state.current_depth) {
+            self.store(&state, None, score, Bound::Exact);
+            return NodeResult::new(score.clamp(state.alpha, state.beta));
+        }
+        if let Some(wdl) = self.probe_tablebase(&state) {
+            let score = wdl.score(state.current_depth);
+            self.store(&state, None, score, Bound::Exact);
+            return NodeResult::new(score.clamp(state.alpha, state.beta));
+        }
+        let original_alpha = state.alpha;
         let mut best_line: LinkedList<Move> = LinkedList::new();
         for mv in moves {
-            self.debug(&state, format!("A: {}, B: {}, D: {}, ", state.alpha, state.beta, state.depth_left));
-            self.debug(&state, format!("Making move: {}", mv.to_notation()));
             let move_result = self.search_req(state.make_move(&mv)).flip();
-            evaluation_counter += move_result.evaluations;
+            if self.stopped() {
+                return NodeResult::new(0);
+            }
             if move_result.score >= state.beta {
-                return SearchResult::new(state.beta, evaluation_counter);
+                if !mv.is_capture() {
+                    self.history.update(&mv, state.depth_left);
+                }
+                self.store(&state, Some(mv), state.beta, Bound::Lower);
+                return NodeResult::new(state.beta);
             }
             if move_result.score > state.alpha {
                 state.alpha = move_result.score;
@@ -129,43 +509,95 @@ impl Engine {
             }
         }
 
-        SearchResult {
+        let bound = if state.alpha > original_alpha { Bound::Exact } else { Bound::Upper };
+        self.store(&state, best_line.front().copied(), state.alpha, bound);
+        NodeResult {
             score: state.alpha,
             moves: best_line,
-            evaluations: evaluation_counter,
         }
     }
 
-    fn quiescence(&self, mut state: SearchState) -> SearchResult {
-        let eval = state.board.evaluate(state.current_depth);
-        let mut evaluation_counter = 1u64;
-        self.debug(&state, format!("A: {}, B: {}, D: {}, ", state.alpha, state.beta, state.depth_left));
-        self.debug(&state, format!("Evaluation: {}", eval));
+    // Positions with few enough pieces get their exact result from the tables instead of a search. Only captures
+    // bring a position into the tables, so the other moves are probed only close to the root
+    fn probe_tablebase(&self, state: &SearchState) -> Option<Wdl> {
+        if !state.zeroing && state.depth_left < TB_PROBE_DEPTH {
+            return None;
+        }
+        let tablebase = self.tablebase.filter(|tablebase| tablebase.covers(&state.board))?;
+        let wdl = tablebase.probe_wdl(&state.board)?;
+        self.tb_hits.fetch_add(1, Ordering::Relaxed);
+        Some(wdl)
+    }
+
+    // Exact mate distances from the generated endgame tables, as a score `ply` plies from the root
+    fn probe_tables(&self, board: &Board, ply: u32) -> Option<i32> {
+        let dtm = self.tables?.probe(board)?;
+        self.tb_hits.fetch_add(1, Ordering::Relaxed);
+        Some(dtm.score(ply))
+    }
+
+    fn quiescence(&mut self, mut state: SearchState) -> NodeResult {
+        if self.stopped() {
+            return NodeResult::new(0);
+        }
+        self.count_node(state.current_depth);
+        if let Some(score) = self.probe_tables(&state.board, state.current_depth) {
+            return NodeResult::new(score.clamp(state.alpha, state.beta));
+        }
+        let mut eval = state.board.evaluate(self.eval, state.current_depth);
+        if let Some(noise) = self.noise {
+            eval = noise.apply(eval, state.board.hash);
+        }
         if eval >= state.beta {
-            return SearchResult::new(state.beta, evaluation_counter);
+            return NodeResult::new(state.beta);
         }
         state.alpha = state.alpha.max(eval);
 
-        for mv in state.board.order(&state.board.gen_moves(true)) {
-            self.debug(&state, format!("Making move: {}", mv.to_notation()));
+        let mut captures = state.board.gen_moves(true);
+        state.board.order(&mut captures);
+        for mv in captures {
             let move_result = self.quiescence(state.make_move(&mv)).flip();
-            evaluation_counter += move_result.evaluations;
             if move_result.score >= state.beta {
-                return SearchResult::new(state.beta, evaluation_counter);
+                return NodeResult::new(state.beta);
             }
             state.alpha = state.alpha.max(move_result.score);
         }
 
-        return SearchResult::new(state.alpha, evaluation_counter);
+        NodeResult::new(state.alpha)
     }
 
-    #[allow(dead_code)]
-    fn debug(&self, state: &SearchState, str: String) -> () {
-        if state.current_depth > 0 || !str.is_empty() {
-            // for _i in 0..state.current_depth {
-            //     print!("\t")
-            // }
-            // println!("{}", str);
+    fn store(&self, state: &SearchState, best_move: Option<Move>, score: i32, bound: Bound) {
+        // noisy scores of a weakened search must not reach later searches
+        if self.noise.is_some() {
+            return;
         }
+        let entry = Entry {
+            best_move,
+            score: score_to_tt(score, state.current_depth),
+            depth: state.depth_left.max(0) as u8,
+            bound,
+        };
+        self.tt.store(state.board.hash, entry);
     }
-}
\ No newline at end of file
+}
+
+// Mate scores are stored relative to the node instead of the root
+fn score_to_tt(score: i32, ply: u32) -> i32 {
+    if (MATE_SCORE..MATE_BOUND).contains(&score) {
+        score - ply as i32
+    } else if (-MATE_BOUND + 1..=-MATE_SCORE).contains(&score) {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: u32) -> i32 {
+    if (MATE_SCORE..MATE_BOUND).contains(&score) {
+        score + ply as i32
+    } else if (-MATE_BOUND + 1..=-MATE_SCORE).contains(&score) {
+        score - ply as i32
+    } else {
+        score
+    }
+}
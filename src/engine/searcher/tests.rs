@@ -1,7 +1,15 @@
 #[cfg(test)]
 use crate::engine::Engine;
+#[cfg(test)]
+use crate::engine::searcher::{SearchLimits, SearchState};
+#[cfg(test)]
 use std::time::{Duration, SystemTime};
+#[cfg(test)]
 use crate::uci;
+#[cfg(test)]
+use crate::engine::output::{CollectingOutput, Info, Score};
+#[cfg(test)]
+use std::sync::Arc;
 
 #[test]
 fn searcher_test_mate_in_1_1() {
@@ -13,8 +21,8 @@ fn searcher_test_mate_in_1_1() {
         })
         .unwrap();
 
-    let search = engine.search();
-    assert_eq!("h1h8", search.to_notation())
+    let search = engine.search(&SearchLimits::default());
+    assert_eq!("h1h8", search.unwrap().to_notation())
 }
 
 #[test]
@@ -28,6 +36,185 @@ fn perf_test_1() {
         .unwrap();
 
     let start = SystemTime::now();
-    let _ = engine.search();
+    let _ = engine.search(&SearchLimits::default());
     assert!(start.elapsed().unwrap() < Duration::from_secs(10))
-}
\ No newline at end of file
+}
+#[test]
+fn searcher_test_mate_in_1_threads() {
+    let mut engine = Engine::new();
+    engine.execute_uci(uci::Command::parse("setoption name Threads value 4").unwrap()).unwrap();
+    engine
+        .execute_uci(uci::Command::SetPosition {
+            position: uci::Position::Fen(String::from("k7/6R1/8/8/8/8/8/6KR w - - 0 1")),
+            moves: vec![],
+        })
+        .unwrap();
+
+    assert_eq!(4, engine.settings.threads);
+    let search = engine.search(&SearchLimits::default());
+    assert_eq!("h1h8", search.unwrap().to_notation())
+}
+
+#[test]
+fn searcher_test_threads_keep_depth_limit() {
+    let mut engine = Engine::new();
+    engine.execute_uci(uci::Command::parse("setoption name Threads value 4").unwrap()).unwrap();
+    let limits = SearchLimits {
+        depth: Some(1),
+        ..SearchLimits::default()
+    };
+    for _ in 0..5 {
+        let lines = engine.search_lines(&limits);
+        assert_eq!(1, lines[0].depth);
+        assert_eq!(1, lines[0].pv.len());
+        // every thread searches the root and its 20 moves, with no captures to resolve
+        let nodes = engine.searched_nodes();
+        assert!(nodes <= 4 * 21, "{nodes} nodes");
+    }
+}
+
+#[test]
+fn searcher_test_zeroing_moves() {
+    let board = crate::model::Board::from_fen(String::from("4k3/8/8/3p4/4P3/8/8/R3K3 w - - 0 1")).unwrap();
+    let state = SearchState::initial(board, 3);
+    assert!(!state.zeroing);
+    let after = |mv: &str| state.make_move(&board.find_move(&mv.parse().unwrap()).unwrap());
+    assert!(after("e4d5").zeroing);
+    assert!(after("e4e5").zeroing);
+    assert!(!after("a1a7").zeroing);
+}
+
+#[test]
+fn searcher_test_no_legal_moves() {
+    let mut engine = Engine::new();
+    for fen in ["k7/1Q6/1K6/8/8/8/8/8 b - - 0 1", "k7/8/1QK5/8/8/8/8/8 b - - 0 1"] {
+        engine.execute_line(&format!("position fen {fen}"));
+        assert_eq!(None, engine.search(&SearchLimits::default()));
+    }
+    engine.execute_line("position startpos");
+    let search_moves = vec![engine.board().find_move(&"g1f3".parse().unwrap()).unwrap()];
+    assert_eq!(Some(search_moves[0]), engine.fallback_move(&search_moves));
+    assert!(engine.fallback_move(&[]).is_some());
+}
+
+#[test]
+fn searcher_test_multi_pv() {
+    let mut engine = Engine::new();
+    engine.execute_uci(uci::Command::parse("setoption name MultiPV value 3").unwrap()).unwrap();
+    engine
+        .execute_uci(uci::Command::SetPosition {
+            position: uci::Position::Fen(String::from("k7/6R1/8/8/8/8/8/6KR w - - 0 1")),
+            moves: vec![],
+        })
+        .unwrap();
+
+    let lines = engine.search_lines(&SearchLimits::default());
+    assert_eq!(3, lines.len());
+    assert_eq!("h1h8", lines[0].mv.to_notation());
+    assert_eq!(lines[0].mv, lines[0].pv[0]);
+    assert_ne!(lines[0].mv, lines[1].mv);
+    assert_ne!(lines[1].mv, lines[2].mv);
+    assert!(lines.windows(2).all(|pair| pair[0].score >= pair[1].score));
+}
+
+#[test]
+fn searcher_test_node_limit() {
+    let engine = Engine::new();
+    let limits = SearchLimits {
+        nodes: Some(5_000),
+        ..Default::default()
+    };
+    let mv = engine.search(&limits);
+    assert!(engine.board().gen_moves(false).contains(&mv.unwrap()));
+    assert_eq!(5_000, engine.searched_nodes());
+}
+
+#[test]
+fn searcher_test_search_moves() {
+    let mut engine = Engine::new();
+    engine
+        .execute_uci(uci::Command::SetPosition {
+            position: uci::Position::Fen(String::from("k7/6R1/8/8/8/8/8/6KR w - - 0 1")),
+            moves: vec![],
+        })
+        .unwrap();
+
+    let limits = SearchLimits {
+        depth: Some(2),
+        search_moves: vec!["g7h7".parse().unwrap()],
+        ..Default::default()
+    };
+    let lines = engine.search_lines(&limits);
+    assert_eq!(1, lines.len());
+    assert_eq!("g7h7", lines[0].mv.to_notation());
+    assert_eq!(2, lines[0].depth);
+}
+
+#[test]
+fn searcher_test_move_time() {
+    let engine = Engine::new();
+    let start = SystemTime::now();
+    let limits = SearchLimits {
+        move_time: Some(Duration::from_millis(300)),
+        ..Default::default()
+    };
+    let _ = engine.search(&limits);
+    assert!(start.elapsed().unwrap() < Duration::from_secs(2))
+}
+
+#[test]
+fn searcher_test_ponder_until_ponderhit() {
+    let mut engine = Engine::new();
+    engine.execute_uci(uci::Command::parse("go ponder movetime 50").unwrap()).unwrap();
+    std::thread::sleep(Duration::from_millis(300));
+    assert!(engine.search_thread.as_ref().is_some_and(|handle| !handle.is_finished()));
+
+    let start = SystemTime::now();
+    engine.execute_uci(uci::Command::PonderHit).unwrap();
+    engine.wait_for_search();
+    assert!(start.elapsed().unwrap() < Duration::from_secs(5))
+}
+
+#[test]
+fn searcher_test_ponder_move() {
+    let engine = Engine::new();
+    let lines = engine.search_lines(&SearchLimits {
+        depth: Some(2),
+        ..Default::default()
+    });
+    let notation = engine.best_move(&lines, &[]).to_string();
+    assert!(notation.starts_with(&format!("bestmove {} ponder ", lines[0].mv.to_notation())));
+}
+
+#[test]
+fn searcher_test_reports_info() {
+    let output = Arc::new(CollectingOutput::default());
+    let mut engine = Engine::with_output(output.clone());
+    engine
+        .execute_uci(uci::Command::SetPosition {
+            position: uci::Position::Fen(String::from("k7/6R1/8/8/8/8/8/6KR w - - 0 1")),
+            moves: vec![],
+        })
+        .unwrap();
+
+    let _ = engine.search(&SearchLimits {
+        depth: Some(3),
+        ..Default::default()
+    });
+    let infos = output.infos();
+    let lines: Vec<_> = infos
+        .iter()
+        .filter_map(|info| match info {
+            Info::Line { stats, score, pv, .. } => Some((stats, score, pv)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(3, lines.len());
+    for (depth, (stats, score, pv)) in lines.into_iter().enumerate() {
+        assert_eq!(depth as u8 + 1, stats.depth);
+        assert!(stats.seldepth >= stats.depth as u32);
+        assert!(stats.nodes > 0);
+        assert_eq!(Score::Mate(1), *score);
+        assert_eq!("h1h8", pv[0].to_notation());
+    }
+}
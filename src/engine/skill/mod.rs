@@ -0,0 +1,183 @@
+use crate::engine::evaluation::MATE_BOUND;
+use crate::engine::output::Response;
+use crate::engine::searcher::{SearchLimits, SearchResult};
+use crate::engine::tournament::{MatchScore, PlayedGame};
+use crate::engine::{Engine, Settings};
+use crate::model::*;
+use rand::Rng;
+use std::sync::Arc;
+
+mod tests;
+
+pub const MAX_LEVEL: i64 = 20;
+// UCI_Elo is spread evenly over the skill levels
+pub const MIN_ELO: i64 = 600;
+pub const MAX_ELO: i64 = 2600;
+// Root moves a weakened engine chooses from
+const CANDIDATES: usize = 4;
+// Largest evaluation noise in centipawns, at level 0
+const MAX_NOISE: f64 = 200.0;
+
+// Playing strength from 'Skill Level', or from 'UCI_Elo' with 'UCI_LimitStrength'. Levels below MAX_LEVEL limit the depth
+// and the nodes, add noise to the evaluation and sometimes pick a weaker move among the best ones
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Skill {
+    pub level: f64,
+}
+
+// Pseudo random offsets of the static evaluation, the same for a position during one search
+#[derive(Clone, Copy, Debug)]
+pub struct EvalNoise {
+    pub amplitude: i32,
+    seed: u64,
+}
+
+impl Skill {
+    pub fn new(level: u8, limit_strength: bool, elo: u32) -> Skill {
+        let level = match limit_strength {
+            true => (elo as i64 - MIN_ELO) as f64 * MAX_LEVEL as f64 / (MAX_ELO - MIN_ELO) as f64,
+            false => level as f64,
+        };
+        Skill {
+            level: level.clamp(0.0, MAX_LEVEL as f64),
+        }
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.level < MAX_LEVEL as f64
+    }
+
+    // From 1 ply at level 0 to 10 plies just below full strength
+    pub fn max_depth(&self) -> Option<u8> {
+        self.is_limited().then(|| 1 + (self.level / 2.0) as u8)
+    }
+
+    // Doubles every two levels from 500 nodes at level 0
+    pub fn max_nodes(&self) -> Option<u64> {
+        self.is_limited().then(|| (500.0 * 2f64.powf(self.level / 2.0)) as u64)
+    }
+
+    // The limits of the search made no deeper and no longer than the level allows
+    pub fn limit(&self, limits: &SearchLimits) -> SearchLimits {
+        let mut limits = limits.clone();
+        if let Some(depth) = self.max_depth() {
+            limits.depth = Some(limits.depth.map_or(depth, |limit| limit.min(depth)));
+        }
+        if let Some(nodes) = self.max_nodes() {
+            limits.nodes = Some(limits.nodes.map_or(nodes, |limit| limit.min(nodes)));
+        }
+        limits
+    }
+
+    // MultiPV lines to search, enough for the candidates of a weaker move
+    pub fn multi_pv(&self, multi_pv: usize) -> usize {
+        match self.is_limited() {
+            true => multi_pv.max(CANDIDATES),
+            false => multi_pv,
+        }
+    }
+
+    // New noise for every search, none at full strength
+    pub fn eval_noise(&self) -> Option<EvalNoise> {
+        let amplitude = (MAX_NOISE * (1.0 - self.level / MAX_LEVEL as f64)) as i32;
+        (amplitude > 0).then(|| EvalNoise {
+            amplitude,
+            seed: rand::random(),
+        })
+    }
+
+    // The line to play out of the searched ones, best first. Worse lines make up most of the difference and get a random
+    // bonus, both growing the weaker the level and the random one also the wider the candidates are spread
+    pub fn choose<'a>(&self, lines: &'a [SearchResult], rng: &mut impl Rng) -> Option<&'a SearchResult> {
+        let top = lines.first()?;
+        if !self.is_limited() {
+            return Some(top);
+        }
+        let weakness = (120.0 - 2.0 * self.level) as i64;
+        let candidates = &lines[..lines.len().min(CANDIDATES)];
+        let spread = (top.score as i64 - candidates.last()?.score as i64).min(100);
+        candidates.iter().max_by_key(|line| {
+            let worse_by = top.score as i64 - line.score as i64;
+            line.score as i64 + (weakness * worse_by + spread * rng.gen_range(0..weakness)) / 128
+        })
+    }
+}
+
+impl EvalNoise {
+    // Mate scores are left alone
+    pub fn apply(&self, eval: i32, hash: u64) -> i32 {
+        if eval <= MATE_BOUND || eval >= -MATE_BOUND {
+            return eval;
+        }
+        // splitmix64 of the position and the seed
+        let mut mixed = hash ^ self.seed;
+        mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94d049bb133111eb);
+        mixed ^= mixed >> 31;
+        eval + (mixed % (2 * self.amplitude as u64 + 1)) as i32 - self.amplitude
+    }
+}
+
+impl Settings {
+    pub fn skill(&self) -> Skill {
+        Skill::new(self.skill_level, self.limit_strength, self.elo)
+    }
+}
+
+// Plays the engine with the settings against full strength searches of `depth` plies, alternating the colors
+// and the openings as in a match. `limits` are those of the weakened engine before the skill limits them
+pub fn calibrate(
+    settings: &Settings,
+    limits: &SearchLimits,
+    depth: u8,
+    games: usize,
+    openings: &[Game],
+    mut report: impl FnMut(&PlayedGame, &MatchScore),
+) -> MatchScore {
+    let quiet = || Engine::with_output(Arc::new(|_: Response| ()));
+    let mut tested = quiet();
+    tested.settings = settings.clone();
+    let mut opponent = quiet();
+    let fixed = SearchLimits {
+        depth: Some(depth),
+        ..SearchLimits::default()
+    };
+    let mut score = MatchScore::default();
+    for number in 0..games {
+        let opening = &openings[(number / 2) % openings.len()];
+        let white = number % 2;
+        let played = match white {
+            0 => play([&mut tested, &mut opponent], [limits, &fixed], opening),
+            _ => play([&mut opponent, &mut tested], [&fixed, limits], opening),
+        };
+        let played = PlayedGame { number, white, ..played };
+        score.add(&played);
+        report(&played, &score);
+    }
+    score
+}
+
+// A game between two engines in this process, `engines[0]` plays white
+fn play(engines: [&mut Engine; 2], limits: [&SearchLimits; 2], opening: &Game) -> PlayedGame {
+    let mut engines = engines;
+    for engine in engines.iter_mut() {
+        engine.shared.tt.clear();
+    }
+    let mut game = opening.clone();
+    loop {
+        let result = game.result();
+        if result.is_over() {
+            return PlayedGame {
+                number: 0,
+                white: 0,
+                game,
+                winner: result.winner(),
+                termination: result.to_string(),
+            };
+        }
+        let side = game.board().turn.index();
+        engines[side].game = game.clone();
+        let mv = engines[side].search(limits[side]).expect("Ongoing games have legal moves");
+        game.make_move(&mv).expect("The engine plays legal moves");
+    }
+}
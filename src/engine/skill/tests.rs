@@ -0,0 +1,132 @@
+#[cfg(test)]
+use crate::engine::output::CollectingOutput;
+#[cfg(test)]
+use crate::engine::skill::*;
+#[cfg(test)]
+use rand::rngs::StdRng;
+#[cfg(test)]
+use rand::SeedableRng;
+
+#[cfg(test)]
+fn line(notation: &str, score: i32) -> SearchResult {
+    let mv: Move = notation.parse().unwrap();
+    SearchResult {
+        mv,
+        score,
+        depth: 1,
+        pv: vec![mv],
+    }
+}
+
+#[test]
+fn levels_and_elo() {
+    assert!(!Skill::new(20, false, 600).is_limited());
+    assert_eq!(None, Skill::new(20, false, 600).max_depth());
+    assert_eq!(Skill { level: 0.0 }, Skill::new(20, true, 600));
+    assert_eq!(Skill { level: 10.0 }, Skill::new(20, true, 1600));
+    assert!(!Skill::new(0, true, 2600).is_limited());
+
+    let weakest = Skill::new(0, false, 0);
+    assert_eq!(Some(1), weakest.max_depth());
+    assert_eq!(Some(500), weakest.max_nodes());
+    assert_eq!(4, weakest.multi_pv(1));
+    assert_eq!(1, Skill::new(20, false, 0).multi_pv(1));
+    let limits = Skill::new(6, false, 0).limit(&SearchLimits {
+        depth: Some(2),
+        nodes: Some(1_000_000),
+        ..SearchLimits::default()
+    });
+    assert_eq!((Some(2), Some(4000)), (limits.depth, limits.nodes));
+}
+
+#[test]
+fn weaker_moves() {
+    let lines = [
+        line("e2e4", 50),
+        line("d2d4", 40),
+        line("g1f3", 20),
+        line("a2a3", -30),
+        line("h2h4", -60),
+    ];
+    let mut rng = StdRng::seed_from_u64(7);
+    assert_eq!(lines[0].mv, Skill::new(20, false, 0).choose(&lines, &mut rng).unwrap().mv);
+    assert!(Skill::new(0, false, 0).choose(&[], &mut rng).is_none());
+
+    let mut picked = [0; 5];
+    for _ in 0..1000 {
+        let chosen = Skill::new(0, false, 0).choose(&lines, &mut rng).unwrap();
+        picked[lines.iter().position(|line| line.mv == chosen.mv).unwrap()] += 1;
+    }
+    // only the candidates are played, the best one still most often
+    assert_eq!(0, picked[4]);
+    assert!(picked[1] > 0 && picked[3] > 0);
+    assert!(picked[0] > picked[3], "{picked:?}");
+
+    let mut stronger = 0;
+    for _ in 0..1000 {
+        stronger += (Skill::new(18, false, 0).choose(&lines, &mut rng).unwrap().mv == lines[0].mv) as u32;
+    }
+    assert!(stronger > picked[0], "{stronger} {picked:?}");
+}
+
+#[test]
+fn evaluation_noise() {
+    assert!(Skill::new(20, false, 0).eval_noise().is_none());
+    let noise = Skill::new(10, false, 0).eval_noise().unwrap();
+    assert_eq!(100, noise.amplitude);
+    let hashes = [1u64, 2, 3, 0xdead_beef, u64::MAX];
+    let noisy: Vec<i32> = hashes.iter().map(|&hash| noise.apply(0, hash)).collect();
+    assert!(noisy.iter().all(|eval| eval.abs() <= 100));
+    assert!(noisy.iter().any(|&eval| eval != noisy[0]));
+    assert_eq!(noisy, hashes.iter().map(|&hash| noise.apply(0, hash)).collect::<Vec<i32>>());
+    assert_eq!(MATE_BOUND - 5, noise.apply(MATE_BOUND - 5, 1));
+    assert_eq!(-MATE_BOUND + 5, noise.apply(-MATE_BOUND + 5, 1));
+}
+
+#[test]
+fn limited_strength_options() {
+    let output = Arc::new(CollectingOutput::default());
+    let mut engine = Engine::with_output(output.clone());
+    for line in [
+        "setoption name UCI_LimitStrength value true",
+        "setoption name UCI_Elo value 800",
+        "go depth 6",
+    ] {
+        assert!(engine.execute_line(line));
+    }
+    engine.wait_for_search();
+    assert_eq!(Skill { level: 2.0 }, engine.settings.skill());
+    let lines = output.lines();
+    assert!(lines.iter().all(|line| !line.starts_with("info depth 3")), "{lines:?}");
+    assert!(lines.iter().any(|line| line.starts_with("info depth 2")));
+    // the candidates of a weaker move are searched but only the line asked for is sent, and none reach the hash table
+    assert!(lines.iter().all(|line| !line.contains("multipv 2")), "{lines:?}");
+    assert_eq!(0, engine.shared.tt.hashfull());
+    assert!(engine.shared.tt.probe(engine.board().hash).is_none());
+    assert!(lines.last().unwrap().starts_with("bestmove"));
+    assert!(engine.execute_line("setoption name Skill Level value 21"));
+    assert!(output.lines().last().unwrap().contains("0..20"));
+}
+
+#[test]
+fn calibration_games() {
+    // white mates in one, whichever engine has white
+    let openings = [Game::from_fen("k7/6R1/8/8/8/8/8/6KR w - - 0 1").unwrap()];
+    let limits = SearchLimits {
+        depth: Some(2),
+        ..SearchLimits::default()
+    };
+    let mut reported = Vec::new();
+    let score = calibrate(&Settings::default(), &limits, 2, 2, &openings, |played, _| {
+        reported.push((played.white, played.result(), played.game.moves().len()));
+    });
+    assert_eq!(vec![(0, "1-0", 1), (1, "1-0", 1)], reported);
+    assert_eq!(
+        MatchScore {
+            wins: 1,
+            draws: 0,
+            losses: 1
+        },
+        score
+    );
+}
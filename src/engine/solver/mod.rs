@@ -0,0 +1,145 @@
+use crate::engine::output::Score;
+use crate::engine::searcher::SearchLimits;
+use crate::engine::Engine;
+use crate::model::epd::EpdPosition;
+use crate::model::util::errors;
+use crate::model::*;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, SystemTime};
+
+mod tests;
+
+// How the engine did on one position of a suite. Positions with STS points score the points of the move found
+#[derive(Clone, Debug)]
+pub struct Solution {
+    pub mv: Move,
+    pub san: String,
+    pub score: Score,
+    pub depth: u8,
+    pub nodes: u64,
+    pub time: Duration,
+    pub solved: bool,
+    pub points: u32,
+    pub max_points: u32,
+}
+
+// Totals of a suite, positions that couldn't be solved count as errors
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct SuiteScore {
+    pub positions: usize,
+    pub solved: usize,
+    pub points: u32,
+    pub max_points: u32,
+    pub errors: usize,
+}
+
+impl SuiteScore {
+    pub fn add(&mut self, solution: &Solution) {
+        self.positions += 1;
+        self.solved += solution.solved as usize;
+        self.points += solution.points;
+        self.max_points += solution.max_points;
+    }
+
+    pub fn add_error(&mut self) {
+        self.positions += 1;
+        self.errors += 1;
+    }
+}
+
+// 'solved 250/300, 2410/3000 points, 2 errors', the points only for STS suites
+impl Display for SuiteScore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "solved {}/{}", self.solved, self.positions)?;
+        if self.max_points > 0 {
+            write!(f, ", {}/{} points", self.points, self.max_points)?;
+        }
+        if self.errors > 0 {
+            write!(f, ", {} errors", self.errors)?;
+        }
+        Ok(())
+    }
+}
+
+impl Solution {
+    // The line of the position in a suite report, `number` counts from 0 and names positions without an id
+    pub fn report(&self, position: &EpdPosition, number: usize) -> String {
+        let id = position_id(position, number);
+        let expected: Vec<String> = ["bm", "am", "dm"]
+            .iter()
+            .filter_map(|opcode| Some(format!("{opcode} {}", position.operation(opcode)?.join(" "))))
+            .collect();
+        let points = match self.max_points {
+            0 => String::new(),
+            max => format!(" {}/{max} points", self.points),
+        };
+        format!(
+            "{id}: {} {} ({}){points}, score {} depth {} nodes {} in {}ms",
+            if self.solved { "solved" } else { "FAILED" },
+            self.san,
+            expected.join(", "),
+            self.score,
+            self.depth,
+            self.nodes,
+            self.time.as_millis()
+        )
+    }
+}
+
+// The line of a position the engine couldn't solve
+pub fn error_report(position: &EpdPosition, number: usize, error: &std::io::Error) -> String {
+    format!("{}: ERROR {error}", position_id(position, number))
+}
+
+fn position_id(position: &EpdPosition, number: usize) -> String {
+    position.id().map_or(format!("#{}", number + 1), String::from)
+}
+
+impl Engine {
+    // Searches the position from an empty hash table and checks the move found against 'bm', 'am', 'dm' and the STS points
+    pub fn solve(&mut self, position: &EpdPosition, limits: &SearchLimits) -> std::io::Result<Solution> {
+        let (best, avoid, mate, points) = (
+            position.san_moves("bm")?,
+            position.san_moves("am")?,
+            position.direct_mate()?,
+            position.points()?,
+        );
+        if best.is_empty() && avoid.is_empty() && mate.is_none() && points.is_empty() {
+            return Err(errors::invalid_input(format!("No bm, am, dm or points in '{position}'")));
+        }
+        self.wait_for_search();
+        self.game = Game::from(position.board);
+        self.game.set_chess960(position.board.chess960 || self.settings.chess960);
+        self.shared.tt.clear();
+
+        let start = SystemTime::now();
+        let lines = self.search_lines(limits);
+        let time = start.elapsed().unwrap();
+        let board = self.board();
+        let line = lines
+            .first()
+            .ok_or(errors::invalid_input(format!("No legal moves in '{position}'")))?;
+        let score = Score::from_search(line.score);
+        let found_mate = match (score, mate) {
+            (Score::Mate(moves), Some(mate)) => moves > 0 && moves <= mate,
+            (_, mate) => mate.is_none(),
+        };
+        let points_of = |mv: Move| points.iter().find(|(scored, _)| *scored == mv).map_or(0, |&(_, points)| points);
+        let max_points = points.iter().map(|&(_, points)| points).max().unwrap_or(0);
+        let solved = match best.is_empty() && avoid.is_empty() && mate.is_none() {
+            true => points_of(line.mv) == max_points,
+            false => (best.is_empty() || best.contains(&line.mv)) && !avoid.contains(&line.mv) && found_mate,
+        };
+        Ok(Solution {
+            mv: line.mv,
+            san: board.to_san(&line.mv),
+            score,
+            depth: line.depth,
+            nodes: self.searched_nodes(),
+            time,
+            solved,
+            points: points_of(line.mv),
+            max_points,
+        })
+    }
+}
@@ -0,0 +1,70 @@
+#[cfg(test)]
+use crate::engine::output::CollectingOutput;
+#[cfg(test)]
+use crate::engine::solver::*;
+#[cfg(test)]
+use std::sync::Arc;
+
+#[cfg(test)]
+const SCHOLAR: &str = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq -";
+
+#[cfg(test)]
+fn solve(epd: &str, depth: u8) -> std::io::Result<Solution> {
+    let mut engine = Engine::with_output(Arc::new(CollectingOutput::default()));
+    let limits = SearchLimits {
+        depth: Some(depth),
+        ..SearchLimits::default()
+    };
+    engine.solve(&epd.parse()?, &limits)
+}
+
+#[test]
+fn best_and_avoided_moves() {
+    let solution = solve(&format!("{SCHOLAR} bm Qxf7#; id \"scholar\";"), 3).unwrap();
+    assert!(solution.solved);
+    assert_eq!("Qxf7#", solution.san);
+    assert_eq!(Score::Mate(1), solution.score);
+    assert!(!solve(&format!("{SCHOLAR} bm Bxf7+;"), 3).unwrap().solved);
+    assert!(solve("k7/8/2p5/3p4/8/8/8/K2R4 w - - am Rxd5;", 4).unwrap().solved);
+    assert!(solve("k7/8/8/3q4/8/8/8/K2R4 w - - am Rxd5;", 4).is_ok_and(|solution| !solution.solved));
+    assert!(solve(&format!("{SCHOLAR} id \"nothing to check\";"), 1).is_err());
+}
+
+#[test]
+fn direct_mates() {
+    assert!(solve(&format!("{SCHOLAR} dm 1;"), 3).unwrap().solved);
+    // mate in two with a rook lift, the mate in one isn't there
+    let ladder = "7k/8/8/8/8/8/R7/1R5K w - - dm 2;";
+    assert!(solve(ladder, 4).unwrap().solved);
+    assert!(!solve("7k/8/8/8/8/8/R7/1R5K w - - dm 1;", 4).unwrap().solved);
+}
+
+#[test]
+fn sts_points() {
+    let solution = solve(&format!("{SCHOLAR} c0 \"Qxf7#=10, Bxf7+=3, Nc3=1\";"), 3).unwrap();
+    assert_eq!((true, 10, 10), (solution.solved, solution.points, solution.max_points));
+    let solution = solve(&format!("{SCHOLAR} bm Nc3; c8 \"10 3 1\"; c9 \"c3 c4f7 h5f7\";"), 3);
+    assert!(solution.is_err());
+    let solution = solve(&format!("{SCHOLAR} bm Nc3; c8 \"1 3 10\"; c9 \"b1c3 c4f7 h5f7\";"), 3).unwrap();
+    assert_eq!((false, 10, 10), (solution.solved, solution.points, solution.max_points));
+
+    let mut total = SuiteScore::default();
+    total.add(&solution);
+    total.add(&solve(&format!("{SCHOLAR} bm Qxf7#; c0 \"Qxf7#=10, Bxf7+=3\";"), 3).unwrap());
+    assert_eq!((2, 1, 20, 20), (total.positions, total.solved, total.points, total.max_points));
+}
+
+#[test]
+fn unsolvable_positions() {
+    let position: EpdPosition = format!("{SCHOLAR} id \"nothing to check\";").parse().unwrap();
+    let error = solve(&position.to_string(), 1).unwrap_err();
+    assert!(error_report(&position, 4, &error).starts_with("nothing to check: ERROR No bm, am, dm or points"));
+    let unnamed: EpdPosition = "k7/8/8/8/8/8/8/K7 w - - bm Kb1;".parse().unwrap();
+    assert!(error_report(&unnamed, 4, &error).starts_with("#5: ERROR"));
+
+    let mut total = SuiteScore::default();
+    total.add(&solve(&format!("{SCHOLAR} bm Qxf7#;"), 3).unwrap());
+    total.add_error();
+    assert_eq!((2, 1, 1), (total.positions, total.solved, total.errors));
+    assert_eq!("solved 1/2, 1 errors", total.to_string());
+}
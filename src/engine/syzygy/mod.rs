@@ -0,0 +1,409 @@
+use crate::model::util::errors;
+use crate::model::*;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::ops::Neg;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+mod table;
+mod tests;
+
+use table::{Metric, Table};
+
+// Search score of a tablebase win, far above any evaluation and below the mate scores
+pub const TB_WIN_SCORE: i32 = 1_000_000;
+
+// Win, draw or loss for the side to move. Cursed wins and blessed losses are only drawn by the fifty move rule
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+// Pieces of both sides as counts by color and PieceKind, tables are named after it like 'KRPvKR'
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Material {
+    counts: [[u8; 6]; 2],
+}
+
+// A root move with the result it keeps and the distance to zeroing of the position after it, see `Tablebase::rank_moves`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RankedMove {
+    pub mv: Move,
+    // from the view of the side playing the move, with the fifty move rule applied
+    pub wdl: Wdl,
+    // dtz of the position after the move, from the opponent's view
+    pub dtz: i32,
+}
+
+// Tables are read into memory when they are probed for the first time
+#[derive(Debug)]
+struct LazyTable {
+    path: PathBuf,
+    table: OnceLock<Option<Table>>,
+}
+
+#[derive(Debug, Default)]
+pub struct Tablebase {
+    wdl: HashMap<Material, LazyTable>,
+    dtz: HashMap<Material, LazyTable>,
+    max_pieces: usize,
+}
+
+// How the position got its value, decides how DTZ is found
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Resolved {
+    Table,
+    // a capture or en passant is the best move, DTZ is 1
+    ZeroingBestMove,
+}
+
+impl Wdl {
+    // Plies to zeroing when the best move zeroes the clock
+    fn zeroing_dtz(self) -> i32 {
+        match self {
+            Wdl::Loss => -1,
+            Wdl::BlessedLoss => -101,
+            Wdl::Draw => 0,
+            Wdl::CursedWin => 101,
+            Wdl::Win => 1,
+        }
+    }
+
+    // Score for the search, wins are preferred the sooner they are found
+    pub fn score(self, ply: u32) -> i32 {
+        match self {
+            Wdl::Win => TB_WIN_SCORE - ply as i32,
+            Wdl::CursedWin => 1,
+            Wdl::Draw => 0,
+            Wdl::BlessedLoss => -1,
+            Wdl::Loss => -TB_WIN_SCORE + ply as i32,
+        }
+    }
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        match self {
+            Wdl::Loss => Wdl::Win,
+            Wdl::BlessedLoss => Wdl::CursedWin,
+            Wdl::Draw => Wdl::Draw,
+            Wdl::CursedWin => Wdl::BlessedLoss,
+            Wdl::Win => Wdl::Loss,
+        }
+    }
+}
+
+fn add_plies(dtz: i32, plies: i32) -> i32 {
+    match dtz {
+        0 => 0,
+        dtz if dtz > 0 => dtz + plies,
+        dtz => dtz - plies,
+    }
+}
+
+impl Material {
+    pub fn from_board(board: &Board) -> Material {
+        Material::from_pieces(&board.squares.into_iter().flatten().collect::<Vec<_>>())
+    }
+
+    fn from_pieces(pieces: &[Piece]) -> Material {
+        let mut counts = [[0; 6]; 2];
+        for piece in pieces {
+            counts[piece.color.index()][piece.kind.index()] += 1;
+        }
+        Material { counts }
+    }
+
+    pub fn count(&self) -> usize {
+        self.counts.iter().flatten().map(|&count| count as usize).sum()
+    }
+
+    pub fn flipped(&self) -> Material {
+        Material {
+            counts: [self.counts[1], self.counts[0]],
+        }
+    }
+
+    fn side_has_pawns(&self, color: Color) -> bool {
+        self.counts[color.index()][PieceKind::Pawn.index()] > 0
+    }
+
+    fn has_pawns(&self) -> bool {
+        self.side_has_pawns(Color::White) || self.side_has_pawns(Color::Black)
+    }
+
+    fn is_symmetric(&self) -> bool {
+        self.counts[0] == self.counts[1]
+    }
+
+    // piece kinds of a side that occur exactly once, the kings always do
+    fn unique_pieces(&self) -> usize {
+        self.counts.iter().flatten().filter(|&&count| count == 1).count()
+    }
+}
+
+impl std::str::FromStr for Material {
+    type Err = std::io::Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let unexpected = || errors::invalid_input(format!("Unexpected tablebase name: '{name}'"));
+        let (white, black) = name.split_once('v').ok_or_else(unexpected)?;
+        let mut counts = [[0; 6]; 2];
+        for (side, pieces) in [white, black].into_iter().enumerate() {
+            for symbol in pieces.chars() {
+                let kind = PieceKind::from_char(symbol)
+                    .filter(|_| symbol.is_ascii_uppercase())
+                    .ok_or_else(unexpected)?;
+                counts[side][kind.index()] += 1;
+            }
+            if counts[side][PieceKind::King.index()] != 1 {
+                return Err(unexpected());
+            }
+        }
+        Ok(Material { counts })
+    }
+}
+
+impl Display for Material {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (side, counts) in self.counts.iter().enumerate() {
+            if side == 1 {
+                f.write_str("v")?;
+            }
+            for kind in PieceKind::ALL.into_iter().rev() {
+                let symbol = kind.char().to_ascii_uppercase().to_string();
+                f.write_str(&symbol.repeat(counts[kind.index()] as usize))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LazyTable {
+    fn get(&self, material: &Material, metric: Metric) -> Option<&Table> {
+        self.table
+            .get_or_init(|| std::fs::read(&self.path).and_then(|bytes| Table::new(bytes, material, metric)).ok())
+            .as_ref()
+    }
+}
+
+fn is_zeroing(board: &Board, mv: &Move) -> bool {
+    mv.is_capture() || board.piece_at(mv.from()).is_some_and(|piece| piece.kind == PieceKind::Pawn)
+}
+
+fn is_checkmate(board: &Board) -> bool {
+    board.is_check() && board.gen_moves(false).is_empty()
+}
+
+impl Tablebase {
+    pub fn new() -> Tablebase {
+        Tablebase::default()
+    }
+
+    // Adds the tables of all directories in `paths`, separated like PATH ('dir1:dir2' or 'dir1;dir2' on Windows)
+    pub fn open(paths: &str) -> std::io::Result<Tablebase> {
+        let mut tablebase = Tablebase::new();
+        for directory in std::env::split_paths(paths).filter(|path| !path.as_os_str().is_empty()) {
+            tablebase.add_directory(&directory)?;
+        }
+        Ok(tablebase)
+    }
+
+    // Adds every .rtbw and .rtbz file of the directory, returns how many there were
+    pub fn add_directory(&mut self, directory: &Path) -> std::io::Result<usize> {
+        let mut added = 0;
+        for entry in std::fs::read_dir(directory)? {
+            let path = entry?.path();
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let Ok(material) = name.parse::<Material>() else {
+                continue;
+            };
+            let tables = match path.extension().and_then(|extension| extension.to_str()) {
+                Some("rtbw") => &mut self.wdl,
+                Some("rtbz") => &mut self.dtz,
+                _ => continue,
+            };
+            self.max_pieces = self.max_pieces.max(material.count());
+            tables.insert(
+                material,
+                LazyTable {
+                    path,
+                    table: OnceLock::new(),
+                },
+            );
+            added += 1;
+        }
+        Ok(added)
+    }
+
+    // Number of WDL tables
+    pub fn len(&self) -> usize {
+        self.wdl.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.wdl.is_empty()
+    }
+
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    // Whether the position can be probed at all, tables don't know castling
+    pub fn covers(&self, board: &Board) -> bool {
+        board.castling.is_empty() && board.squares.iter().flatten().count() <= self.max_pieces
+    }
+
+    // The result with best play, ignoring the fifty move rule counter of the position
+    pub fn probe_wdl(&self, board: &Board) -> Option<Wdl> {
+        self.probe(board).map(|(wdl, _)| wdl)
+    }
+
+    // Plies to the next capture or pawn move with best play, positive when the side to move wins.
+    // Values past the fifty move rule are 100 plies larger than the real distance, and tables
+    // that store moves instead of plies can be one ply off
+    pub fn probe_dtz(&self, board: &Board) -> Option<i32> {
+        let (wdl, resolved) = self.probe(board)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if resolved == Resolved::ZeroingBestMove {
+            return Some(wdl.zeroing_dtz());
+        }
+        let moves = board.gen_moves(false);
+        if wdl > Wdl::Draw {
+            // a winning pawn push is zeroing too, captures were already looked at
+            for mv in moves.iter().filter(|mv| !mv.is_capture() && is_zeroing(board, mv)) {
+                if self.probe_wdl(&board.make_move(mv)).map(Wdl::neg) == Some(wdl) {
+                    return Some(wdl.zeroing_dtz());
+                }
+            }
+        }
+        let table = self.table(&self.dtz, board, Metric::Dtz)?;
+        if let Some(plies) = table.probe_dtz(board, wdl).ok()? {
+            return Some(add_plies(wdl.zeroing_dtz(), plies as i32));
+        }
+
+        // the table only has the other side to move, so search one ply
+        let mut best = if wdl > Wdl::Draw { None } else { Some(wdl.zeroing_dtz()) };
+        for mv in moves.iter().filter(|mv| !is_zeroing(board, mv)) {
+            let after = board.make_move(mv);
+            let dtz = -self.probe_dtz(&after)?;
+            if dtz == 1 && is_checkmate(&after) {
+                best = Some(1);
+            } else if dtz.signum() == wdl.zeroing_dtz().signum() {
+                let dtz = add_plies(dtz, 1);
+                best = Some(best.map_or(dtz, |best: i32| best.min(dtz)));
+            }
+        }
+        best
+    }
+
+    // Every legal move with the result it keeps, the best first: mates, then the best result and
+    // the fastest win or slowest loss by DTZ. None if a table is missing
+    pub fn rank_moves(&self, board: &Board, halfmove_clock: u32) -> Option<Vec<RankedMove>> {
+        let mut ranked = Vec::new();
+        for mv in board.gen_moves(false) {
+            let after = board.make_move(&mv);
+            let dtz = self.probe_dtz(&after)?;
+            let mut wdl = -self.probe_wdl(&after)?;
+            let clock = if is_zeroing(board, &mv) { 0 } else { halfmove_clock + 1 };
+            if dtz.unsigned_abs() + clock > 100 {
+                wdl = match wdl {
+                    Wdl::Win => Wdl::CursedWin,
+                    Wdl::Loss => Wdl::BlessedLoss,
+                    other => other,
+                };
+            }
+            ranked.push(RankedMove { mv, wdl, dtz });
+        }
+        ranked.sort_by_key(|ranked| {
+            let mates = ranked.dtz == -1 && is_checkmate(&board.make_move(&ranked.mv));
+            // zeroing is good when winning and bad when losing
+            let zeroing = is_zeroing(board, &ranked.mv) ^ (ranked.dtz < 0);
+            (Reverse(mates), Reverse(ranked.wdl), zeroing, Reverse(ranked.dtz))
+        });
+        Some(ranked)
+    }
+
+    fn table<'a>(&self, tables: &'a HashMap<Material, LazyTable>, board: &Board, metric: Metric) -> Option<&'a Table> {
+        let material = Material::from_board(board);
+        match tables.get(&material) {
+            Some(table) => table.get(&material, metric),
+            None => {
+                let flipped = material.flipped();
+                tables.get(&flipped)?.get(&flipped, metric)
+            }
+        }
+    }
+
+    fn probe_table(&self, board: &Board) -> Option<Wdl> {
+        if board.squares.iter().flatten().count() == 2 {
+            return Some(Wdl::Draw);
+        }
+        self.table(&self.wdl, board, Metric::Wdl)?.probe_wdl(board).ok()
+    }
+
+    // The tables may store any value where a capture is at least as good, so captures are
+    // searched first. En passant isn't in the tables at all.
+    fn probe(&self, board: &Board) -> Option<(Wdl, Resolved)> {
+        if !self.covers(board) {
+            return None;
+        }
+        let mut best_capture = Wdl::Loss;
+        let mut best_en_passant = Wdl::Loss;
+        let moves = board.gen_moves(false);
+        for mv in moves.iter().filter(|mv| mv.is_capture()) {
+            let wdl = -self.probe_captures(&board.make_move(mv), Wdl::Loss, -best_capture)?;
+            if wdl == Wdl::Win {
+                return Some((wdl, Resolved::ZeroingBestMove));
+            }
+            if mv.is_en_passant() {
+                best_en_passant = best_en_passant.max(wdl);
+            } else {
+                best_capture = best_capture.max(wdl);
+            }
+        }
+
+        let stored = self.probe_table(board)?;
+        if best_en_passant > stored.max(best_capture) {
+            return Some((best_en_passant, Resolved::ZeroingBestMove));
+        }
+        let best_capture = best_capture.max(best_en_passant);
+        if best_capture >= stored {
+            let resolved = if best_capture > Wdl::Draw {
+                Resolved::ZeroingBestMove
+            } else {
+                Resolved::Table
+            };
+            return Some((best_capture, resolved));
+        }
+        // stalemate unless en passant is played
+        if stored == Wdl::Draw && !moves.is_empty() && moves.iter().all(|mv| mv.is_en_passant()) {
+            return Some((best_en_passant, Resolved::ZeroingBestMove));
+        }
+        Some((stored, Resolved::Table))
+    }
+
+    // Alpha-beta over captures only, after a capture there is no en passant to care about
+    fn probe_captures(&self, board: &Board, mut alpha: Wdl, beta: Wdl) -> Option<Wdl> {
+        for mv in board.gen_moves(true) {
+            let wdl = -self.probe_captures(&board.make_move(&mv), -beta, -alpha)?;
+            if wdl >= beta {
+                return Some(wdl);
+            }
+            alpha = alpha.max(wdl);
+        }
+        Some(alpha.max(self.probe_table(board)?))
+    }
+}
@@ -0,0 +1,779 @@
+use crate::engine::syzygy::{Material, Wdl};
+use crate::model::util::errors;
+use crate::model::{Board, Color, Piece, PieceKind, Square};
+
+// Decoding of single .rtbw and .rtbz files. Squares are numbered like in the tables,
+// 0 is a1 and 63 is h8, see `tb_square`.
+
+pub(super) const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+pub(super) const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+// layout flags of the file header
+pub(super) const SPLIT: u8 = 1;
+pub(super) const HAS_PAWNS: u8 = 2;
+
+// flags of every subtable
+pub(super) const STM: u8 = 1;
+const MAPPED: u8 = 2;
+pub(super) const WIN_PLIES: u8 = 4;
+pub(super) const LOSS_PLIES: u8 = 8;
+const WIDE_DTZ: u8 = 16;
+pub(super) const SINGLE_VALUE: u8 = 128;
+
+const MAX_BLOCK_SIZE: usize = 1024;
+const NOT_LEGAL: u64 = u64::MAX;
+
+// The a1-d1-d4 triangle the leading piece is moved into, b1..d3 below the diagonal come first
+#[rustfmt::skip]
+const TRIANGLE: [u64; 64] = [
+    6, 0, 1, 2, 2, 1, 0, 6,
+    0, 7, 3, 4, 4, 3, 7, 0,
+    1, 3, 8, 5, 5, 8, 3, 1,
+    2, 4, 5, 9, 9, 5, 4, 2,
+    2, 4, 5, 9, 9, 5, 4, 2,
+    1, 3, 8, 5, 5, 8, 3, 1,
+    0, 7, 3, 4, 4, 3, 7, 0,
+    6, 0, 1, 2, 2, 1, 0, 6,
+];
+
+const INV_TRIANGLE: [usize; 10] = [1, 2, 3, 10, 11, 19, 0, 9, 18, 27];
+
+// Squares below the a1-h8 diagonal, the diagonal itself is 28..35
+#[rustfmt::skip]
+const LOWER: [u64; 64] = [
+    28,  0,  1,  2,  3,  4,  5,  6,
+     0, 29,  7,  8,  9, 10, 11, 12,
+     1,  7, 30, 13, 14, 15, 16, 17,
+     2,  8, 13, 31, 18, 19, 20, 21,
+     3,  9, 14, 18, 32, 22, 23, 24,
+     4, 10, 15, 19, 22, 33, 25, 26,
+     5, 11, 16, 20, 23, 25, 34, 27,
+     6, 12, 17, 21, 24, 26, 27, 35,
+];
+
+const KK_INDEX: [[u64; 64]; 10] = kk_index();
+const PAWNS: PawnTables = PawnTables::new();
+
+const fn binomial(mut n: u64, k: u64) -> u64 {
+    if k > n {
+        return 0;
+    }
+    if k > n - k {
+        return binomial(n, n - k);
+    }
+    let (mut result, mut d) = (1, 1);
+    while d <= k {
+        result = result * n / d;
+        n -= 1;
+        d += 1;
+    }
+    result
+}
+
+const fn diagonal_offset(square: usize) -> i32 {
+    (square / 8) as i32 - (square % 8) as i32
+}
+
+// Index of the two kings when the white one is in the triangle: 462 legal pairs,
+// the ones with both kings on the diagonal come last
+const fn kk_index() -> [[u64; 64]; 10] {
+    let mut table = [[NOT_LEGAL; 64]; 10];
+    let mut code = 0;
+    let mut both_on_diagonal = 0;
+    while both_on_diagonal < 2 {
+        let mut index = 0;
+        while index < 10 {
+            let first = INV_TRIANGLE[index];
+            let mut second = 0;
+            while second < 64 {
+                let adjacent = (first % 8).abs_diff(second % 8) <= 1 && (first / 8).abs_diff(second / 8) <= 1;
+                let above = diagonal_offset(first) == 0 && diagonal_offset(second) > 0;
+                let on_diagonal = diagonal_offset(first) == 0 && diagonal_offset(second) == 0;
+                if !adjacent && !above && on_diagonal == (both_on_diagonal == 1) {
+                    table[index][second] = code;
+                    code += 1;
+                }
+                second += 1;
+            }
+            index += 1;
+        }
+        both_on_diagonal += 1;
+    }
+    table
+}
+
+// Pawns are indexed on a2..h7, the leading pawn is kept on files a..d
+struct PawnTables {
+    map: [u64; 64],
+    lead_index: [[u64; 64]; 6],
+    lead_size: [[u64; 4]; 6],
+}
+
+impl PawnTables {
+    const fn new() -> PawnTables {
+        let mut map = [0; 64];
+        let mut lead_index = [[0; 64]; 6];
+        let mut lead_size = [[0; 4]; 6];
+        let mut available = 48;
+        let mut count = 1;
+        while count <= 5 {
+            let mut file = 0;
+            while file < 4 {
+                let mut index = 0;
+                let mut rank = 1;
+                while rank < 7 {
+                    let square = file + 8 * rank;
+                    if count == 1 {
+                        available -= 1;
+                        map[square] = available;
+                        available -= 1;
+                        map[square ^ 7] = available;
+                    }
+                    lead_index[count][square] = index;
+                    index += binomial(map[square], count as u64 - 1);
+                    rank += 1;
+                }
+                lead_size[count][file] = index;
+                file += 1;
+            }
+            count += 1;
+        }
+        PawnTables {
+            map,
+            lead_index,
+            lead_size,
+        }
+    }
+}
+
+fn flip_vertical(square: u8) -> u8 {
+    square ^ 56
+}
+
+fn flip_horizontal(square: u8) -> u8 {
+    square ^ 7
+}
+
+fn flip_diagonal(square: u8) -> u8 {
+    ((square >> 3) | (square << 3)) & 63
+}
+
+fn file(square: u8) -> u8 {
+    square & 7
+}
+
+fn rank(square: u8) -> u8 {
+    square >> 3
+}
+
+fn off_diagonal(square: u8) -> bool {
+    file(square) != rank(square)
+}
+
+pub(super) fn tb_square(square: Square) -> u8 {
+    square.file() + 8 * square.rank()
+}
+
+fn corrupted() -> std::io::Error {
+    errors::invalid_input(String::from("Corrupted tablebase file"))
+}
+
+// Bounds checked little endian reads from the table file
+struct Bytes(Vec<u8>);
+
+impl std::fmt::Debug for Bytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} bytes", self.0.len())
+    }
+}
+
+impl Bytes {
+    fn u8(&self, at: u64) -> std::io::Result<u8> {
+        self.0.get(at as usize).copied().ok_or_else(corrupted)
+    }
+
+    fn u16(&self, at: u64) -> std::io::Result<u16> {
+        Ok(u16::from_le_bytes([self.u8(at)?, self.u8(at + 1)?]))
+    }
+
+    fn u32(&self, at: u64) -> std::io::Result<u32> {
+        Ok(u32::from_le_bytes([
+            self.u8(at)?,
+            self.u8(at + 1)?,
+            self.u8(at + 2)?,
+            self.u8(at + 3)?,
+        ]))
+    }
+
+    // left and right child of a symbol, 12 bits each
+    fn children(&self, at: u64) -> std::io::Result<(u16, u16)> {
+        let (first, second, third) = (self.u8(at)? as u16, self.u8(at + 1)? as u16, self.u8(at + 2)? as u16);
+        Ok(((second & 0xf) << 8 | first, third << 4 | second >> 4))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum Metric {
+    Wdl,
+    Dtz,
+}
+
+// The pieces of one subtable in encoding order, with the group sizes and the index factor of each group
+#[derive(Debug)]
+struct Groups {
+    pieces: Vec<Piece>,
+    lens: Vec<usize>,
+    factors: Vec<u64>,
+}
+
+#[derive(Debug)]
+struct DtzMap {
+    wide: bool,
+    start: u64,
+    // offsets of the win, loss, cursed win and blessed loss maps
+    offsets: [u64; 4],
+}
+
+// One compressed subtable: a side to move of a file of the leading pawn
+#[derive(Debug)]
+struct PairsData {
+    flags: u8,
+    groups: Groups,
+    block_size: u32,
+    span: u32,
+    blocks: u32,
+    btree: u64,
+    min_symlen: u8,
+    lowest_sym: u64,
+    base: Vec<u64>,
+    symlen: Vec<u8>,
+    sparse_index: u64,
+    sparse_index_size: u64,
+    block_lengths: u64,
+    block_lengths_size: u64,
+    data: u64,
+    dtz_map: Option<DtzMap>,
+}
+
+#[derive(Debug)]
+pub(super) struct Table {
+    bytes: Bytes,
+    metric: Metric,
+    material: Material,
+    // one entry per file of the leading pawn (a..d), a single one without pawns
+    files: Vec<Vec<PairsData>>,
+}
+
+fn nibble_to_piece(nibble: u8) -> Option<Piece> {
+    let color = if nibble & 8 == 0 { Color::White } else { Color::Black };
+    let kind = match nibble & 7 {
+        1 => PieceKind::Pawn,
+        2 => PieceKind::Knight,
+        3 => PieceKind::Bishop,
+        4 => PieceKind::Rook,
+        5 => PieceKind::Queen,
+        6 => PieceKind::King,
+        _ => return None,
+    };
+    Some(Piece::new(kind, color))
+}
+
+impl Groups {
+    fn new(pieces: Vec<Piece>, order: [u8; 2], file: usize) -> std::io::Result<Groups> {
+        let material = Material::from_pieces(&pieces);
+        // without pawns the three unique pieces or the two kings lead
+        let first_len = match material.has_pawns() {
+            true => 0,
+            false if material.unique_pieces() >= 3 => 3,
+            false => 2,
+        };
+        let mut lens = Vec::new();
+        if first_len > 0 {
+            lens.push(first_len);
+        }
+        let mut rest = &pieces[first_len..];
+        while let Some(&piece) = rest.first() {
+            let len = rest.iter().take_while(|&&other| other == piece).count();
+            lens.push(len);
+            rest = &rest[len..];
+        }
+        if lens.is_empty() || lens.iter().sum::<usize>() != pieces.len() {
+            return Err(corrupted());
+        }
+
+        let both_pawns = material.side_has_pawns(Color::White) && material.side_has_pawns(Color::Black);
+        let mut factors = vec![0; lens.len() + 1];
+        let mut free_squares = 64 - lens[0] - if both_pawns { lens[1] } else { 0 };
+        let mut next = if both_pawns { 2 } else { 1 };
+        let mut index = 1;
+        let mut k = 0;
+        while next < lens.len() || k == order[0] || k == order[1] {
+            if k == order[0] {
+                factors[0] = index;
+                index *= match material.has_pawns() {
+                    true => PAWNS.lead_size[lens[0]][file],
+                    false if material.unique_pieces() >= 3 => 31_332,
+                    false => 462,
+                };
+            } else if k == order[1] {
+                factors[1] = index;
+                index *= binomial(48 - lens[0] as u64, lens[1] as u64);
+            } else {
+                factors[next] = index;
+                index *= binomial(free_squares as u64, lens[next] as u64);
+                free_squares -= lens[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        factors[lens.len()] = index;
+        Ok(Groups { pieces, lens, factors })
+    }
+
+    fn size(&self) -> u64 {
+        self.factors[self.lens.len()]
+    }
+}
+
+impl PairsData {
+    // Reads the header of a subtable, returns it with the position right after it
+    fn parse(bytes: &Bytes, mut at: u64, groups: Groups, metric: Metric) -> std::io::Result<(PairsData, u64)> {
+        let flags = bytes.u8(at)?;
+        let mut data = PairsData {
+            flags,
+            groups,
+            block_size: 0,
+            span: 0,
+            blocks: 0,
+            btree: 0,
+            min_symlen: 0,
+            lowest_sym: 0,
+            base: Vec::new(),
+            symlen: Vec::new(),
+            sparse_index: 0,
+            sparse_index_size: 0,
+            block_lengths: 0,
+            block_lengths_size: 0,
+            data: 0,
+            dtz_map: None,
+        };
+        if flags & SINGLE_VALUE != 0 {
+            // the value goes where the shortest symbol length would be, DTZ tables store 0
+            data.min_symlen = if metric == Metric::Wdl { bytes.u8(at + 1)? } else { 0 };
+            return Ok((data, at + 2));
+        }
+
+        let shift = |bits: u8| 1u32.checked_shl(bits as u32).ok_or_else(corrupted);
+        data.block_size = shift(bytes.u8(at + 1)?)?;
+        data.span = shift(bytes.u8(at + 2)?)?;
+        if data.block_size as usize > MAX_BLOCK_SIZE {
+            return Err(corrupted());
+        }
+        data.sparse_index_size = data.groups.size().div_ceil(data.span as u64);
+        data.blocks = bytes.u32(at + 4)?;
+        data.block_lengths_size = data.blocks as u64 + bytes.u8(at + 3)? as u64;
+        let (max_symlen, min_symlen) = (bytes.u8(at + 8)?, bytes.u8(at + 9)?);
+        if max_symlen > 32 || min_symlen > max_symlen {
+            return Err(corrupted());
+        }
+        data.min_symlen = min_symlen;
+        data.lowest_sym = at + 10;
+
+        // canonical Huffman code: the smallest code of every length
+        let lengths = (max_symlen - min_symlen + 1) as usize;
+        let mut base = vec![0u64; lengths];
+        for i in (0..lengths - 1).rev() {
+            let position = data.lowest_sym + 2 * i as u64;
+            let sum = base[i + 1] + bytes.u16(position)? as u64;
+            base[i] = sum.checked_sub(bytes.u16(position + 2)? as u64).ok_or_else(corrupted)? / 2;
+            if base[i] * 2 < base[i + 1] {
+                return Err(corrupted());
+            }
+        }
+        for (i, value) in base.iter_mut().enumerate() {
+            let bits = min_symlen as u32 + i as u32;
+            *value = value.checked_shl(64 - bits).ok_or_else(corrupted)?;
+        }
+        data.base = base;
+
+        at += 10 + 2 * lengths as u64;
+        let symbols = bytes.u16(at)? as usize;
+        at += 2;
+        data.btree = at;
+        data.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for symbol in 0..symbols as u16 {
+            read_symlen(bytes, data.btree, &mut data.symlen, &mut visited, symbol, 16)?;
+        }
+        at += 3 * symbols as u64 + (symbols as u64 & 1);
+        Ok((data, at))
+    }
+}
+
+// Every symbol is a leaf or a pair of two shorter symbols, symlen is the number of values it expands to minus one
+fn read_symlen(bytes: &Bytes, btree: u64, symlen: &mut [u8], visited: &mut [bool], symbol: u16, depth: u8) -> std::io::Result<()> {
+    if *visited.get(symbol as usize).ok_or_else(corrupted)? {
+        return Ok(());
+    }
+    let (left, right) = bytes.children(btree + 3 * symbol as u64)?;
+    if right == 0xfff {
+        symlen[symbol as usize] = 0;
+    } else {
+        let depth = depth.checked_sub(1).ok_or_else(corrupted)?;
+        read_symlen(bytes, btree, symlen, visited, left, depth)?;
+        read_symlen(bytes, btree, symlen, visited, right, depth)?;
+        let len = symlen[left as usize] as u16 + symlen[right as usize] as u16 + 1;
+        symlen[symbol as usize] = u8::try_from(len).map_err(|_| corrupted())?;
+    }
+    visited[symbol as usize] = true;
+    Ok(())
+}
+
+impl Table {
+    // `material` is the one from the file name, the tables store white as the stronger side
+    pub(super) fn new(raw: Vec<u8>, material: &Material, metric: Metric) -> std::io::Result<Table> {
+        let bytes = Bytes(raw);
+        let magic = match metric {
+            Metric::Wdl => WDL_MAGIC,
+            Metric::Dtz => DTZ_MAGIC,
+        };
+        if bytes.0.len() % 64 != 16 || bytes.0[..4] != magic {
+            return Err(errors::invalid_input(format!("Not a Syzygy {metric:?} table for {material}")));
+        }
+        let layout = bytes.u8(4)?;
+        let has_pawns = layout & HAS_PAWNS != 0;
+        if has_pawns != material.has_pawns() || (layout & SPLIT != 0) == material.is_symmetric() {
+            return Err(corrupted());
+        }
+
+        let both_pawns = material.side_has_pawns(Color::White) && material.side_has_pawns(Color::Black);
+        let files = if has_pawns { 4 } else { 1 };
+        let sides = if metric == Metric::Wdl && !material.is_symmetric() { 2 } else { 1 };
+        let count = material.count() as u64;
+        let mut at = 5;
+        let mut groups = Vec::new();
+        for file in 0..files {
+            let first = bytes.u8(at)?;
+            let second = if both_pawns { bytes.u8(at + 1)? } else { 0xff };
+            let orders = [[first & 0xf, second & 0xf], [first >> 4, second >> 4]];
+            at += 1 + both_pawns as u64;
+            let mut file_groups = Vec::new();
+            for (side, order) in orders.into_iter().enumerate().take(sides) {
+                let mut pieces = Vec::new();
+                for offset in 0..count {
+                    let byte = bytes.u8(at + offset)?;
+                    let nibble = if side == 0 { byte & 0xf } else { byte >> 4 };
+                    pieces.push(nibble_to_piece(nibble).ok_or_else(corrupted)?);
+                }
+                let key = Material::from_pieces(&pieces);
+                if key != *material && key.flipped() != *material {
+                    return Err(corrupted());
+                }
+                file_groups.push(Groups::new(pieces, order, file)?);
+            }
+            at += count;
+            groups.push(file_groups);
+        }
+        at += at & 1;
+        if (groups[0][0].pieces[0].kind == PieceKind::Pawn) != has_pawns {
+            return Err(corrupted());
+        }
+
+        let mut tables: Vec<Vec<PairsData>> = Vec::new();
+        for file_groups in groups {
+            let mut sides = Vec::new();
+            for side in file_groups {
+                let (data, next) = PairsData::parse(&bytes, at, side, metric)?;
+                sides.push(data);
+                at = next;
+            }
+            tables.push(sides);
+        }
+
+        if metric == Metric::Dtz {
+            let start = at;
+            for sides in &mut tables {
+                let side = &mut sides[0];
+                if side.flags & MAPPED == 0 {
+                    continue;
+                }
+                let wide = side.flags & WIDE_DTZ != 0;
+                let mut offsets = [0; 4];
+                for offset in &mut offsets {
+                    if wide {
+                        *offset = (at - start + 2) / 2;
+                        at += 2 * bytes.u16(at)? as u64 + 2;
+                    } else {
+                        *offset = at - start + 1;
+                        at += bytes.u8(at)? as u64 + 1;
+                    }
+                }
+                side.dtz_map = Some(DtzMap { wide, start, offsets });
+            }
+            at += at & 1;
+        }
+
+        for side in tables.iter_mut().flatten() {
+            side.sparse_index = at;
+            at += 6 * side.sparse_index_size;
+        }
+        for side in tables.iter_mut().flatten() {
+            side.block_lengths = at;
+            at += 2 * side.block_lengths_size;
+        }
+        for side in tables.iter_mut().flatten() {
+            // blocks start 64 byte aligned
+            at = (at + 0x3f) & !0x3f;
+            side.data = at;
+            at += side.blocks as u64 * side.block_size as u64;
+        }
+
+        Ok(Table {
+            bytes,
+            metric,
+            material: material.clone(),
+            files: tables,
+        })
+    }
+
+    // The stored WDL value, captures and en passant are not resolved here
+    pub(super) fn probe_wdl(&self, board: &Board) -> std::io::Result<Wdl> {
+        let (file, side, index) = self.encode(board)?.ok_or_else(corrupted)?;
+        match self.decompress(&self.files[file][side], index)? {
+            0 => Ok(Wdl::Loss),
+            1 => Ok(Wdl::BlessedLoss),
+            2 => Ok(Wdl::Draw),
+            3 => Ok(Wdl::CursedWin),
+            4 => Ok(Wdl::Win),
+            _ => Err(corrupted()),
+        }
+    }
+
+    // Plies to the next capture or pawn move for a decisive `wdl`, rounded up to an even number when the
+    // table doesn't store plies. None if the table only has the other side to move
+    pub(super) fn probe_dtz(&self, board: &Board, wdl: Wdl) -> std::io::Result<Option<u32>> {
+        let Some((file, side, index)) = self.encode(board)? else {
+            return Ok(None);
+        };
+        let side = &self.files[file][side];
+        let mut value = self.decompress(side, index)? as u64;
+        let map = match wdl {
+            Wdl::Win => 0,
+            Wdl::Loss => 1,
+            Wdl::CursedWin => 2,
+            _ => 3,
+        };
+        if let Some(dtz_map) = &side.dtz_map {
+            let offset = dtz_map.offsets[map] + value;
+            value = match dtz_map.wide {
+                true => self.bytes.u16(dtz_map.start + 2 * offset)? as u64,
+                false => self.bytes.u8(dtz_map.start + offset)? as u64,
+            };
+        }
+        let plies = match wdl {
+            Wdl::Win => side.flags & WIN_PLIES != 0,
+            Wdl::Loss => side.flags & LOSS_PLIES != 0,
+            _ => false,
+        };
+        Ok(Some(if plies { value as u32 } else { 2 * value as u32 }))
+    }
+
+    fn decompress(&self, data: &PairsData, index: u64) -> std::io::Result<u16> {
+        if data.flags & SINGLE_VALUE != 0 {
+            return Ok(data.min_symlen as u16);
+        }
+        let bytes = &self.bytes;
+
+        // the sparse index points close to the block holding `index`
+        let main = index / data.span as u64;
+        let mut block = bytes.u32(data.sparse_index + 6 * main)?;
+        let offset = bytes.u16(data.sparse_index + 6 * main + 4)? as i64;
+        let mut literal = (index % data.span as u64) as i64 - data.span as i64 / 2 + offset;
+        let block_length = |block: u32| Ok::<i64, std::io::Error>(bytes.u16(data.block_lengths + 2 * block as u64)? as i64 + 1);
+        while literal < 0 {
+            block = block.checked_sub(1).ok_or_else(corrupted)?;
+            literal += block_length(block)?;
+        }
+        while literal >= block_length(block)? {
+            literal -= block_length(block)?;
+            block += 1;
+        }
+
+        let start = (data.data + block as u64 * data.block_size as u64) as usize;
+        let end = start + data.block_size as usize;
+        let mut buffer = bytes.0.get(start..end).ok_or_else(corrupted)?.to_vec();
+        // room for the last refill
+        buffer.extend_from_slice(&[0; 4]);
+        let mut cursor = 8;
+        let mut bits = u64::from_be_bytes(buffer[..8].try_into().unwrap());
+        let mut available = 64;
+
+        let symlen = |symbol: u16| data.symlen.get(symbol as usize).map(|&len| len as i64 + 1).ok_or_else(corrupted);
+        let mut symbol;
+        loop {
+            let mut len = 0;
+            while bits < *data.base.get(len).ok_or_else(corrupted)? {
+                len += 1;
+            }
+            symbol = ((bits - data.base[len]) >> (64 - len - data.min_symlen as usize)) as u16;
+            symbol = symbol.wrapping_add(bytes.u16(data.lowest_sym + 2 * len as u64)?);
+            if literal < symlen(symbol)? {
+                break;
+            }
+            literal -= symlen(symbol)?;
+            len += data.min_symlen as usize;
+            bits <<= len;
+            available -= len;
+            if available <= 32 {
+                let refill = buffer.get(cursor..cursor + 4).ok_or_else(corrupted)?;
+                available += 32;
+                bits |= (u32::from_be_bytes(refill.try_into().unwrap()) as u64) << (64 - available);
+                cursor += 4;
+            }
+        }
+
+        // walk down the pairs to the value
+        while data.symlen[symbol as usize] != 0 {
+            let (left, right) = bytes.children(data.btree + 3 * symbol as u64)?;
+            if literal < symlen(left)? {
+                symbol = left;
+            } else {
+                literal -= symlen(left)?;
+                symbol = right;
+            }
+        }
+        let leaf = data.btree + 3 * symbol as u64;
+        match self.metric {
+            Metric::Wdl => Ok(bytes.u8(leaf)? as u16),
+            Metric::Dtz => Ok(bytes.u16(leaf)? & 0xfff),
+        }
+    }
+
+    // The subtable as the file of the leading pawn and the side to move, and the index of the position in it
+    pub(super) fn encode(&self, board: &Board) -> std::io::Result<Option<(usize, usize, u64)>> {
+        let key = Material::from_board(board);
+        let material = &self.material;
+        let black_to_move = board.turn == Color::Black;
+        // tables are stored with the stronger side as white, mirrored positions are looked up flipped
+        let flip = (material.is_symmetric() && black_to_move) || key != *material;
+        let black_side = black_to_move ^ flip;
+        let color = |color: Color| if flip { color.flip() } else { color };
+        let square = |square: u8| if flip { flip_vertical(square) } else { square };
+
+        let mut pieces: [Option<Piece>; 64] = [None; 64];
+        for board_square in Square::all() {
+            pieces[tb_square(board_square) as usize] = board.piece_at(board_square);
+        }
+        let mut used = [false; 64];
+        let mut squares: Vec<u8> = Vec::with_capacity(material.count());
+
+        let file_index = if material.has_pawns() {
+            let lead = self.files[0][0].groups.pieces[0];
+            let pawn = Some(Piece::new(PieceKind::Pawn, color(lead.color)));
+            for tb in 0..64u8 {
+                if pieces[tb as usize] == pawn {
+                    used[tb as usize] = true;
+                    squares.push(square(tb));
+                }
+            }
+            // the leading pawn is the one furthest along the pawn order
+            for i in 1..squares.len() {
+                if PAWNS.map[squares[0] as usize] < PAWNS.map[squares[i] as usize] {
+                    squares.swap(0, i);
+                }
+            }
+            let lead_file = file(squares[0]);
+            if lead_file >= 4 {
+                7 - lead_file as usize
+            } else {
+                lead_file as usize
+            }
+        } else {
+            0
+        };
+        let sides = &self.files[file_index];
+        let side_index = if black_side { sides.len() - 1 } else { 0 };
+        let side = &sides[side_index];
+
+        // DTZ tables keep one side to move, the caller has to search the other one
+        if self.metric == Metric::Dtz && (side.flags & STM != 0) != black_side && (!material.is_symmetric() || material.has_pawns()) {
+            return Ok(None);
+        }
+
+        let lead_pawns = squares.len();
+        for piece in &side.groups.pieces[lead_pawns..] {
+            let wanted = Some(Piece::new(piece.kind, color(piece.color)));
+            let tb = (0..64u8)
+                .find(|&tb| !used[tb as usize] && pieces[tb as usize] == wanted)
+                .ok_or_else(corrupted)?;
+            used[tb as usize] = true;
+            squares.push(square(tb));
+        }
+
+        if file(squares[0]) >= 4 {
+            squares.iter_mut().for_each(|square| *square = flip_horizontal(*square));
+        }
+
+        let mut index = if material.has_pawns() {
+            let mut index = PAWNS.lead_index[lead_pawns][squares[0] as usize];
+            squares[1..lead_pawns].sort_unstable_by_key(|&square| PAWNS.map[square as usize]);
+            for (i, &square) in squares.iter().enumerate().take(lead_pawns).skip(1) {
+                index += binomial(PAWNS.map[square as usize], i as u64);
+            }
+            index
+        } else {
+            if rank(squares[0]) >= 4 {
+                squares.iter_mut().for_each(|square| *square = flip_vertical(*square));
+            }
+            // the first leading piece off the a1-h8 diagonal goes below it
+            for i in 0..side.groups.lens[0] {
+                if !off_diagonal(squares[i]) {
+                    continue;
+                }
+                if rank(squares[i]) > file(squares[i]) {
+                    squares[i..].iter_mut().for_each(|square| *square = flip_diagonal(*square));
+                }
+                break;
+            }
+            let s: Vec<u64> = squares.iter().map(|&square| square as u64).collect();
+            if side.groups.lens[0] == 3 {
+                let adjust1 = (s[1] > s[0]) as u64;
+                let adjust2 = (s[2] > s[0]) as u64 + (s[2] > s[1]) as u64;
+                let r = |i: usize| rank(squares[i]) as u64;
+                if off_diagonal(squares[0]) {
+                    TRIANGLE[s[0] as usize] * 63 * 62 + (s[1] - adjust1) * 62 + (s[2] - adjust2)
+                } else if off_diagonal(squares[1]) {
+                    6 * 63 * 62 + r(0) * 28 * 62 + LOWER[s[1] as usize] * 62 + s[2] - adjust2
+                } else if off_diagonal(squares[2]) {
+                    6 * 63 * 62 + 4 * 28 * 62 + r(0) * 7 * 28 + (r(1) - adjust1) * 28 + LOWER[s[2] as usize]
+                } else {
+                    6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + r(0) * 7 * 6 + (r(1) - adjust1) * 6 + (r(2) - adjust2)
+                }
+            } else {
+                KK_INDEX[TRIANGLE[s[0] as usize] as usize][s[1] as usize]
+            }
+        };
+        if index == NOT_LEGAL {
+            return Err(corrupted());
+        }
+        index *= side.groups.factors[0];
+
+        // the other groups are combinations of the squares left over
+        let mut remaining_pawns = material.side_has_pawns(Color::White) && material.side_has_pawns(Color::Black);
+        let mut start = side.groups.lens[0];
+        for (group, &len) in side.groups.lens.iter().enumerate().skip(1) {
+            let (previous, rest) = squares.split_at_mut(start);
+            let group_squares = &mut rest[..len];
+            group_squares.sort_unstable();
+            let mut n = 0;
+            for (i, &square) in group_squares.iter().enumerate() {
+                let adjust = previous.iter().filter(|&&other| square > other).count() as u64;
+                n += binomial(square as u64 - adjust - if remaining_pawns { 8 } else { 0 }, i as u64 + 1);
+            }
+            remaining_pawns = false;
+            index += n * side.groups.factors[group];
+            start += len;
+        }
+        Ok(Some((file_index, side_index, index)))
+    }
+}
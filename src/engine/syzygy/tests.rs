@@ -0,0 +1,613 @@
+#[cfg(test)]
+use crate::engine::syzygy::table::*;
+#[cfg(test)]
+use crate::engine::syzygy::*;
+#[cfg(test)]
+use crate::engine::tablebase::{Dtm, Tables};
+#[cfg(test)]
+use std::collections::{BTreeSet, BinaryHeap};
+
+// Syzygy files of the 3 piece endings and KPvKP, written by `write_test_tables` from the engine's own DTM tables
+#[cfg(test)]
+const TEST_TABLES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/syzygy");
+#[cfg(test)]
+const TEST_MATERIAL: [&str; 5] = ["KQvK", "KRvK", "KBvK", "KNvK", "KPvK"];
+
+// The official files are checked by the ignored tests, point SYZYGY_PATH to the 3 and 4 piece tables
+#[cfg(test)]
+fn local_tables() -> Option<Tablebase> {
+    let tablebase = Tablebase::open(&std::env::var("SYZYGY_PATH").ok()?).unwrap();
+    Some(tablebase).filter(|tablebase| tablebase.max_pieces() >= 4)
+}
+
+#[cfg(test)]
+fn board(fen: &str) -> Board {
+    Board::from_fen(String::from(fen)).unwrap()
+}
+
+#[cfg(test)]
+fn dtm_tables(signature: &str) -> Tables {
+    let mut tables = Tables::new();
+    tables.generate(&signature.parse().unwrap());
+    tables
+}
+
+// The pieces in the order of the table, the leading ones first and the kings last
+#[cfg(test)]
+fn table_pieces(name: &str) -> Vec<Piece> {
+    let (white, black) = name.split_once('v').unwrap();
+    let side = |pieces: &str, color: Color| -> Vec<Piece> {
+        let kinds = pieces
+            .chars()
+            .skip(1)
+            .map(|symbol| PieceKind::from_char(symbol.to_ascii_lowercase()).unwrap());
+        kinds.map(|kind| Piece::new(kind, color)).collect()
+    };
+    let mut pieces = side(white, Color::White);
+    pieces.extend(side(black, Color::Black));
+    pieces.extend([Piece::new(PieceKind::King, Color::White), Piece::new(PieceKind::King, Color::Black)]);
+    pieces
+}
+
+// The position as the DTM tables see it, they leave en passant to the search
+#[cfg(test)]
+fn without_en_passant(board: &Board) -> Board {
+    let fen = board.to_fen();
+    let mut fields: Vec<&str> = fen.split(' ').collect();
+    fields[3] = "-";
+    Board::from_fen(fields.join(" ")).unwrap()
+}
+
+// The squares of the pieces as a FEN placement, None if two share a square or a pawn is on the first or last rank
+#[cfg(test)]
+fn placement(pieces: &[Piece], code: usize) -> Option<String> {
+    let squares: Vec<usize> = (0..pieces.len()).map(|i| code / 64usize.pow(i as u32) % 64).collect();
+    let pawn_on_back_rank = pieces
+        .iter()
+        .zip(&squares)
+        .any(|(piece, &square)| piece.kind == PieceKind::Pawn && !(8..56).contains(&square));
+    if (1..squares.len()).any(|i| squares[..i].contains(&squares[i])) || pawn_on_back_rank {
+        return None;
+    }
+    // squares count like in the tables, 0 is a1
+    let mut placement = String::new();
+    for rank in (0..8).rev() {
+        let mut empty = 0;
+        for file in 0..8 {
+            match squares.iter().position(|&square| square == file + 8 * rank) {
+                Some(i) => {
+                    if empty > 0 {
+                        placement.push_str(&empty.to_string());
+                    }
+                    empty = 0;
+                    let symbol = pieces[i].kind.char();
+                    placement.push(if pieces[i].color == Color::White {
+                        symbol.to_ascii_uppercase()
+                    } else {
+                        symbol
+                    });
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            placement.push_str(&empty.to_string());
+        }
+        if rank > 0 {
+            placement.push('/');
+        }
+    }
+    Some(placement)
+}
+
+// Every legal position of the pieces with either side to move, visiting one of every `step` placements
+#[cfg(test)]
+fn legal_positions(pieces: &[Piece], step: usize) -> impl Iterator<Item = Board> + '_ {
+    let placements = (0..64usize.pow(pieces.len() as u32))
+        .step_by(step)
+        .filter_map(|code| placement(pieces, code));
+    placements.flat_map(|placement| {
+        let [white, black] = ["w", "b"].map(|turn| board(&format!("{placement} {turn} - - 0 1")));
+        // the side that just moved can't be in check
+        [(white, black.is_check()), (black, white.is_check())]
+            .into_iter()
+            .filter(|(_, illegal)| !illegal)
+            .map(|(board, _)| board)
+    })
+}
+
+#[cfg(test)]
+fn dtm_wdl(dtm: Dtm) -> Wdl {
+    match dtm {
+        Dtm::Win(_) => Wdl::Win,
+        Dtm::Loss(_) => Wdl::Loss,
+        Dtm::Draw => Wdl::Draw,
+    }
+}
+
+// WDL of every position from the DTM tables and DTZ counted back from the positions where the winner zeroes the clock
+// or mates. A mated side has DTZ -1. Like the DTM tables this leaves en passant out, so a few positions before a double
+// pawn push can differ from the official tables
+#[cfg(test)]
+fn solve(positions: &[Board], dtm: &Tables) -> Vec<(Wdl, i32)> {
+    let index: HashMap<u64, usize> = positions.iter().enumerate().map(|(i, board)| (board.hash, i)).collect();
+    assert_eq!(positions.len(), index.len());
+    let wdl: Vec<Wdl> = positions.iter().map(|board| dtm_wdl(dtm.probe(board).unwrap())).collect();
+    // the position after every move unless the move zeroes or mates, with the result for the side playing it
+    let moves: Vec<Vec<(Option<usize>, Wdl)>> = positions
+        .iter()
+        .map(|board| {
+            let moves = board.gen_moves(false);
+            let successors = moves.iter().map(|mv| {
+                let after = board.make_move(mv);
+                let next = match is_zeroing(board, mv) || is_checkmate(&after) {
+                    true => None,
+                    false => Some(index[&after.hash]),
+                };
+                (next, -dtm_wdl(dtm.probe(&without_en_passant(&after)).unwrap()))
+            });
+            successors.collect()
+        })
+        .collect();
+
+    let mut dtz: Vec<Option<i32>> = (0..positions.len())
+        .map(|i| match wdl[i] {
+            Wdl::Draw => Some(0),
+            Wdl::Loss if moves[i].is_empty() => Some(-1),
+            Wdl::Win if moves[i].iter().any(|&(next, wdl)| next.is_none() && wdl == Wdl::Win) => Some(1),
+            _ => None,
+        })
+        .collect();
+    // one ply further on every pass, so wins are found with their shortest and losses with their longest DTZ
+    loop {
+        let known = dtz.clone();
+        for i in (0..positions.len()).filter(|&i| known[i].is_none()) {
+            let distances = moves[i].iter().map(|&(next, _)| match next {
+                Some(next) => known[next].map(i32::abs),
+                None => Some(0),
+            });
+            dtz[i] = match wdl[i] {
+                Wdl::Win => moves[i]
+                    .iter()
+                    .filter(|(_, wdl)| *wdl == Wdl::Win)
+                    .filter_map(|&(next, _)| known[next?])
+                    .map(|dtz| 1 - dtz)
+                    .min(),
+                _ => distances
+                    .collect::<Option<Vec<i32>>>()
+                    .map(|distances| -1 - distances.into_iter().max().unwrap_or(0)),
+            };
+        }
+        if dtz == known {
+            break;
+        }
+    }
+    let solved: Vec<(Wdl, i32)> = wdl
+        .into_iter()
+        .zip(dtz)
+        .map(|(wdl, dtz)| (wdl, dtz.expect("Every position has a DTZ")))
+        .collect();
+    // past 100 plies the tables would need cursed wins and blessed losses
+    assert!(solved.iter().all(|(_, dtz)| dtz.abs() <= 100));
+    solved
+}
+
+// One subtable to write, indexes without a legal position have no value
+#[cfg(test)]
+struct Subtable {
+    flags: u8,
+    values: Vec<Option<u16>>,
+}
+
+// The parts of a subtable that go to different places of the file
+#[cfg(test)]
+#[derive(Default)]
+struct Compressed {
+    header: Vec<u8>,
+    sparse_index: Vec<u8>,
+    block_lengths: Vec<u8>,
+    blocks: Vec<u8>,
+}
+
+#[cfg(test)]
+const TEST_MAX_RUN: usize = 256;
+
+// Values in a subtable, without pawns the three leading pieces are in the a1-d1-d4 triangle
+#[cfg(test)]
+fn subtable_size(material: &Material) -> usize {
+    match material.to_string().as_str() {
+        "KPvKP" => 6 * 47 * 62 * 61,
+        _ if material.has_pawns() => 6 * 63 * 62,
+        _ => 31_332,
+    }
+}
+
+// Huffman code lengths of the symbols by how often they are written
+#[cfg(test)]
+fn code_lengths(frequencies: &[u64]) -> Vec<u32> {
+    if frequencies.len() == 1 {
+        return vec![1];
+    }
+    let mut parents = vec![usize::MAX; frequencies.len()];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = frequencies.iter().enumerate().map(|(i, &count)| Reverse((count, i))).collect();
+    while let (Some(Reverse((first, a))), Some(Reverse((second, b)))) = (heap.pop(), heap.pop()) {
+        parents.push(usize::MAX);
+        let node = parents.len() - 1;
+        (parents[a], parents[b]) = (node, node);
+        heap.push(Reverse((first + second, node)));
+    }
+    let depth = |mut node: usize| {
+        let mut depth = 0;
+        while parents[node] != usize::MAX {
+            node = parents[node];
+            depth += 1;
+        }
+        depth
+    };
+    (0..frequencies.len()).map(depth).collect()
+}
+
+// Runs of equal values become pairs of symbols, runs of 2^n values one symbol each, written with a canonical Huffman code
+#[cfg(test)]
+fn compress(subtable: &Subtable, metric: Metric, size: usize) -> Compressed {
+    // small blocks for the 3 piece tables, probing them in debug builds decodes less
+    let (block_log, span_log) = if size > 1 << 16 { (10u8, 10u8) } else { (6, 6) };
+    let known: Vec<u16> = subtable.values.iter().flatten().copied().collect();
+    let first = known.first().copied().unwrap_or(0);
+    if known.iter().all(|&value| value == first) && (metric == Metric::Wdl || first == 0) {
+        return Compressed {
+            header: vec![subtable.flags | SINGLE_VALUE, first as u8],
+            ..Compressed::default()
+        };
+    }
+    // the value of an index without a position doesn't matter, the one before makes the longest runs
+    let mut last = first;
+    let values: Vec<u16> = (0..size)
+        .map(|i| {
+            last = subtable.values.get(i).copied().flatten().unwrap_or(last);
+            last
+        })
+        .collect();
+
+    let mut tokens: Vec<(u16, usize)> = Vec::new();
+    let mut i = 0;
+    while i < values.len() {
+        let run = values[i..]
+            .iter()
+            .take(TEST_MAX_RUN)
+            .take_while(|&&value| value == values[i])
+            .count();
+        tokens.push((values[i], 1 << run.ilog2()));
+        i += 1 << run.ilog2();
+    }
+    let written: BTreeSet<(u16, usize)> = tokens.iter().copied().collect();
+    let mut symbols: BTreeSet<(u16, usize)> = BTreeSet::new();
+    for &(value, mut len) in &written {
+        while len >= 1 {
+            symbols.insert((value, len));
+            len /= 2;
+        }
+    }
+    let written: Vec<(u16, usize)> = written.into_iter().collect();
+    let mut counts: HashMap<(u16, usize), u64> = HashMap::new();
+    for &token in &tokens {
+        *counts.entry(token).or_default() += 1;
+    }
+    let frequencies: Vec<u64> = written.iter().map(|symbol| counts[symbol]).collect();
+    let lengths: HashMap<(u16, usize), u32> = written.iter().copied().zip(code_lengths(&frequencies)).collect();
+    let (min, max) = (*lengths.values().min().unwrap(), *lengths.values().max().unwrap());
+    assert!(max <= 32);
+
+    // longer codes get the lower symbol numbers, symbols only found inside pairs come last
+    let mut numbered = written.clone();
+    numbered.sort_by_key(|symbol| Reverse(lengths[symbol]));
+    numbered.extend(symbols.iter().filter(|symbol| !lengths.contains_key(symbol)));
+    let number: HashMap<(u16, usize), u64> = numbered.iter().enumerate().map(|(i, &symbol)| (symbol, i as u64)).collect();
+    let count = |len: u32| lengths.values().filter(|&&other| other == len).count() as u64;
+    let offset = |len: u32| lengths.values().filter(|&&other| other > len).count() as u64;
+    let mut base = vec![0u64; max as usize + 1];
+    for len in (min..max).rev() {
+        base[len as usize] = (base[len as usize + 1] + count(len + 1)) / 2;
+    }
+
+    // blocks of whole symbols, bits written from the top
+    let block_bits = 8usize << block_log;
+    let mut blocks: Vec<(Vec<bool>, usize)> = vec![(Vec::new(), 0)];
+    for symbol in &tokens {
+        let len = lengths[symbol];
+        let (bits, values) = blocks.last().unwrap();
+        if bits.len() + len as usize > block_bits || values + symbol.1 > 1 << 15 {
+            blocks.push((Vec::new(), 0));
+        }
+        let code = base[len as usize] + number[symbol] - offset(len);
+        let (bits, values) = blocks.last_mut().unwrap();
+        bits.extend((0..len).rev().map(|bit| code >> bit & 1 == 1));
+        *values += symbol.1;
+    }
+
+    let mut compressed = Compressed::default();
+    let starts: Vec<usize> = blocks
+        .iter()
+        .scan(0, |start, (_, values)| {
+            *start += values;
+            Some(*start - values)
+        })
+        .collect();
+    let span = 1usize << span_log;
+    for chunk in 0..size.div_ceil(span) {
+        let reference = chunk * span + span / 2;
+        let block = starts.iter().rposition(|&start| start <= reference).unwrap();
+        compressed.sparse_index.extend((block as u32).to_le_bytes());
+        compressed.sparse_index.extend(((reference - starts[block]) as u16).to_le_bytes());
+    }
+    for (bits, values) in &blocks {
+        compressed.block_lengths.extend((*values as u16 - 1).to_le_bytes());
+        let mut bytes: Vec<u8> = bits
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0, |acc, &bit| acc << 1 | bit as u8) << (8 - byte.len()))
+            .collect();
+        bytes.resize(block_bits / 8, 0);
+        compressed.blocks.extend(bytes);
+    }
+
+    let header = &mut compressed.header;
+    header.extend([subtable.flags, block_log, span_log, 0]);
+    header.extend((blocks.len() as u32).to_le_bytes());
+    header.extend([max as u8, min as u8]);
+    for len in min..=max {
+        header.extend((offset(len) as u16).to_le_bytes());
+    }
+    header.extend((numbered.len() as u16).to_le_bytes());
+    for &(value, len) in &numbered {
+        let (left, right) = match len {
+            1 => (value as u64, 0xfff),
+            len => (number[&(value, len / 2)], number[&(value, len / 2)]),
+        };
+        header.extend([left as u8, (left >> 8) as u8 & 0xf | (right as u8 & 0xf) << 4, (right >> 4) as u8]);
+    }
+    if numbered.len() % 2 == 1 {
+        header.push(0);
+    }
+    compressed
+}
+
+// A whole file: one subtable for every file of the leading pawn and side to move
+#[cfg(test)]
+fn write_table(metric: Metric, pieces: &[Piece], subtables: &[Vec<Subtable>]) -> Vec<u8> {
+    let material = Material::from_pieces(pieces);
+    let size = subtable_size(&material);
+    let both_pawns = material.side_has_pawns(Color::White) && material.side_has_pawns(Color::Black);
+    let mut bytes = match metric {
+        Metric::Wdl => WDL_MAGIC.to_vec(),
+        Metric::Dtz => DTZ_MAGIC.to_vec(),
+    };
+    bytes.push(if material.is_symmetric() { 0 } else { SPLIT } | if material.has_pawns() { HAS_PAWNS } else { 0 });
+    for _ in subtables {
+        // both sides index the leading pieces first, then the pawns of the other side
+        bytes.push(0);
+        if both_pawns {
+            bytes.push(0x11);
+        }
+        for piece in pieces {
+            let kind = [
+                PieceKind::Pawn,
+                PieceKind::Knight,
+                PieceKind::Bishop,
+                PieceKind::Rook,
+                PieceKind::Queen,
+                PieceKind::King,
+            ];
+            let nibble = kind.iter().position(|&kind| kind == piece.kind).unwrap() as u8 + 1 + 8 * piece.color.index() as u8;
+            bytes.push(nibble | nibble << 4);
+        }
+    }
+    bytes.resize(bytes.len().next_multiple_of(2), 0);
+    let compressed: Vec<Compressed> = subtables
+        .iter()
+        .flatten()
+        .map(|subtable| compress(subtable, metric, size))
+        .collect();
+    for subtable in &compressed {
+        bytes.extend(&subtable.header);
+    }
+    if metric == Metric::Dtz {
+        bytes.resize(bytes.len().next_multiple_of(2), 0);
+    }
+    for subtable in &compressed {
+        bytes.extend(&subtable.sparse_index);
+    }
+    for subtable in &compressed {
+        bytes.extend(&subtable.block_lengths);
+    }
+    for subtable in &compressed {
+        bytes.resize(bytes.len().next_multiple_of(64), 0);
+        bytes.extend(&subtable.blocks);
+    }
+    bytes.resize(bytes.len().next_multiple_of(64) + 16, 0);
+    bytes
+}
+
+// The WDL table stores both sides to move unless the material is symmetric, the DTZ table only white in plies
+#[cfg(test)]
+fn test_table(name: &str, metric: Metric, positions: impl Iterator<Item = (Board, (Wdl, i32))>) -> Vec<u8> {
+    let pieces = table_pieces(name);
+    let material: Material = name.parse().unwrap();
+    let files = if material.has_pawns() { 4 } else { 1 };
+    let (sides, flags) = match metric {
+        Metric::Wdl if material.is_symmetric() => (1, 0),
+        Metric::Wdl => (2, 0),
+        Metric::Dtz => (1, WIN_PLIES | LOSS_PLIES),
+    };
+    let empty = || {
+        (0..files)
+            .map(|_| (0..sides).map(|_| Subtable { flags, values: Vec::new() }).collect())
+            .collect()
+    };
+    let mut subtables: Vec<Vec<Subtable>> = empty();
+    // a table without values tells the index of every position
+    let layout = Table::new(write_table(metric, &pieces, &subtables), &material, metric).unwrap();
+    for (board, (wdl, dtz)) in positions {
+        let Some((file, side, index)) = layout.encode(&board).unwrap() else {
+            continue;
+        };
+        let value = match metric {
+            Metric::Wdl => wdl as u16,
+            Metric::Dtz if wdl == Wdl::Draw => continue,
+            Metric::Dtz => dtz.unsigned_abs() as u16 - 1,
+        };
+        let values = &mut subtables[file][side].values;
+        values.resize(values.len().max(index as usize + 1), None);
+        assert!(values[index as usize].is_none_or(|stored| stored == value), "{}", board.to_fen());
+        values[index as usize] = Some(value);
+    }
+    write_table(metric, &pieces, &subtables)
+}
+
+// KPvKP only has the WDL file, it is there for en passant which DTZ resolves as a capture
+#[test]
+#[ignore = "writes the tables in tests/data/syzygy, run it in release"]
+fn write_test_tables() {
+    let dtm = dtm_tables("KPKP");
+    std::fs::create_dir_all(TEST_TABLES).unwrap();
+    let write = |name: &str, extension: &str, bytes: Vec<u8>| std::fs::write(format!("{TEST_TABLES}/{name}.{extension}"), bytes).unwrap();
+    for name in TEST_MATERIAL {
+        let positions: Vec<Board> = legal_positions(&table_pieces(name), 1).collect();
+        let solved = solve(&positions, &dtm);
+        for (metric, extension) in [(Metric::Wdl, "rtbw"), (Metric::Dtz, "rtbz")] {
+            write(
+                name,
+                extension,
+                test_table(name, metric, positions.iter().copied().zip(solved.iter().copied())),
+            );
+        }
+    }
+    let pieces = table_pieces("KPvKP");
+    let positions = legal_positions(&pieces, 1).map(|board| (board, (dtm_wdl(dtm.probe(&board).unwrap()), 0)));
+    write("KPvKP", "rtbw", test_table("KPvKP", Metric::Wdl, positions));
+}
+
+#[test]
+fn material_names() {
+    let material: Material = "KRPvKR".parse().unwrap();
+    assert_eq!("KRPvKR", material.to_string());
+    assert_eq!("KRvKRP", material.flipped().to_string());
+    assert_eq!(5, material.count());
+    assert_eq!(material, Material::from_board(&board("8/8/4k3/3r4/8/4PR2/8/4K3 w - - 0 1")));
+    assert!("KQvQ".parse::<Material>().is_err());
+    assert!("KQK".parse::<Material>().is_err());
+    assert!("kqvk".parse::<Material>().is_err());
+}
+
+#[test]
+fn wdl_order() {
+    assert!(Wdl::Win > Wdl::CursedWin && Wdl::CursedWin > Wdl::Draw && Wdl::BlessedLoss > Wdl::Loss);
+    assert_eq!(Wdl::BlessedLoss, -Wdl::CursedWin);
+    assert!(Wdl::Win.score(3) > Wdl::Win.score(5));
+    assert_eq!(-Wdl::Win.score(4), Wdl::Loss.score(4));
+}
+
+#[test]
+fn missing_and_broken_tables() {
+    let directory = std::env::temp_dir().join(format!("crust-syzygy-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("KQvK.rtbw"), [0u8; 80]).unwrap();
+    std::fs::write(directory.join("notes.txt"), "not a table").unwrap();
+    let tablebase = Tablebase::open(directory.to_str().unwrap()).unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(1, tablebase.len());
+    assert_eq!(3, tablebase.max_pieces());
+    let kqk = board("8/8/4k3/8/8/8/8/3QK3 w - - 0 1");
+    assert!(tablebase.covers(&kqk));
+    assert_eq!(None, tablebase.probe_wdl(&kqk));
+    // bare kings need no table
+    assert_eq!(Some(Wdl::Draw), tablebase.probe_wdl(&board("8/8/4k3/8/8/8/8/4K3 w - - 0 1")));
+    assert!(!tablebase.covers(&board("r3k3/8/8/8/8/8/8/4K3 b q - 0 1")));
+    assert!(Tablebase::open("/nonexistent/syzygy").is_err());
+}
+
+#[test]
+fn probe_test_tables() {
+    let tablebase = Tablebase::open(TEST_TABLES).unwrap();
+    assert_eq!((6, 4), (tablebase.len(), tablebase.max_pieces()));
+    let kqk = board("8/8/4k3/8/8/8/8/3QK3 w - - 0 1");
+    assert_eq!(Some(Wdl::Win), tablebase.probe_wdl(&kqk));
+    assert_eq!(Some(Wdl::Loss), tablebase.probe_wdl(&board("8/8/4k3/8/8/8/8/3QK3 b - - 0 1")));
+    // the queen hangs, and a lone minor piece never wins
+    assert_eq!(Some(Wdl::Draw), tablebase.probe_wdl(&board("8/8/8/8/8/2k5/3Q4/7K b - - 0 1")));
+    assert_eq!(Some(Wdl::Draw), tablebase.probe_wdl(&board("8/8/8/8/8/1k6/8/KB6 w - - 0 1")));
+    // the king stops the rook pawn, or doesn't get in front of the pawn in time
+    assert_eq!(Some(Wdl::Draw), tablebase.probe_wdl(&board("k7/8/8/8/8/8/P7/K7 w - - 0 1")));
+    assert_eq!(Some(Wdl::Win), tablebase.probe_wdl(&board("8/8/8/8/8/8/4P3/k3K3 w - - 0 1")));
+
+    // white to move is stored, black to move comes from a search of one ply
+    assert_eq!(Some(1), tablebase.probe_dtz(&board("k7/8/1K6/8/8/8/8/7R w - - 0 1")));
+    assert_eq!(Some(-1), tablebase.probe_dtz(&board("R6k/8/6K1/8/8/8/8/8 b - - 0 1")));
+    assert_eq!(Some(-2), tablebase.probe_dtz(&board("k7/8/1K6/8/8/8/8/6R1 b - - 0 1")));
+    assert_eq!(Some(0), tablebase.probe_dtz(&board("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1")));
+    // pawn moves zero the clock
+    assert_eq!(Some(1), tablebase.probe_dtz(&board("8/8/8/8/8/8/4P3/k3K3 w - - 0 1")));
+    assert!(tablebase
+        .probe_dtz(&board("8/8/8/8/8/8/4P3/k3K3 b - - 0 1"))
+        .is_some_and(|dtz| dtz < 0));
+
+    let ranked = tablebase.rank_moves(&board("k7/8/1K6/8/8/8/8/7R w - - 0 1"), 0).unwrap();
+    assert_eq!(("h1h8", -1), (ranked[0].mv.to_string().as_str(), ranked[0].dtz));
+    assert!(ranked.iter().all(|line| line.wdl == Wdl::Win || line.mv.to_string() != "h1h8"));
+    assert!(ranked.windows(2).all(|pair| pair[0].wdl >= pair[1].wdl));
+    // only promoting to a queen or a rook keeps the win
+    let ranked = tablebase.rank_moves(&board("8/4P3/8/8/8/8/k7/4K3 w - - 0 1"), 0).unwrap();
+    let promotions: Vec<(String, Wdl)> = ranked
+        .iter()
+        .filter(|line| line.mv.promote_to().is_some())
+        .map(|line| (line.mv.to_string(), line.wdl))
+        .collect();
+    assert_eq!(4, promotions.len());
+    assert!(promotions
+        .iter()
+        .all(|(mv, wdl)| (*wdl == Wdl::Win) == (mv.ends_with('q') || mv.ends_with('r'))));
+}
+
+#[test]
+fn en_passant_with_test_tables() {
+    let tablebase = Tablebase::open(TEST_TABLES).unwrap();
+    // the pawns race to a draw, taking en passant leaves the black king too far away
+    assert_eq!(Some(Wdl::Draw), tablebase.probe_wdl(&board("7k/8/8/3pP3/8/8/8/K7 w - - 0 1")));
+    let en_passant = board("7k/8/8/3pP3/8/8/8/K7 w - d6 0 1");
+    assert_eq!(Some(Wdl::Win), tablebase.probe_wdl(&en_passant));
+    assert_eq!(Some(1), tablebase.probe_dtz(&en_passant));
+    // black is lost unless it takes en passant, KPvKP has no DTZ file
+    assert_eq!(Some(Wdl::Loss), tablebase.probe_wdl(&board("8/8/8/8/2pP4/8/2K5/k7 b - - 0 1")));
+    assert_eq!(None, tablebase.probe_dtz(&board("8/8/8/8/2pP4/8/2K5/k7 b - - 0 1")));
+    let en_passant = board("8/8/8/8/2pP4/8/2K5/k7 b - d3 0 1");
+    assert_eq!(Some(Wdl::Draw), tablebase.probe_wdl(&en_passant));
+    assert_eq!(Some(0), tablebase.probe_dtz(&en_passant));
+}
+
+// Without pawns only mates zero the clock, so DTZ is the distance to mate of the DTM tables
+#[test]
+fn test_tables_agree_with_dtm() {
+    let tablebase = Tablebase::open(TEST_TABLES).unwrap();
+    let dtm = dtm_tables("KRK");
+    for board in legal_positions(&table_pieces("KRvK"), 23) {
+        let expected = dtm.probe(&board).unwrap();
+        assert_eq!(Some(dtm_wdl(expected)), tablebase.probe_wdl(&board), "{}", board.to_fen());
+        let dtz = match expected {
+            Dtm::Win(plies) => plies as i32,
+            Dtm::Loss(plies) => -(plies.max(1) as i32),
+            Dtm::Draw => 0,
+        };
+        assert_eq!(Some(dtz), tablebase.probe_dtz(&board), "{}", board.to_fen());
+    }
+}
+
+#[test]
+#[ignore = "needs SYZYGY_PATH"]
+fn probe_local_tables() {
+    let tablebase = local_tables().expect("SYZYGY_PATH with the 3 and 4 piece tables");
+    let kqk = board("8/8/4k3/8/8/8/8/3QK3 w - - 0 1");
+    assert_eq!(Some(Wdl::Win), tablebase.probe_wdl(&kqk));
+    assert!(tablebase.probe_dtz(&kqk).is_some_and(|dtz| dtz > 0));
+    // en passant draws, the table alone would say black wins
+    assert_eq!(Some(Wdl::Draw), tablebase.probe_wdl(&board("8/8/8/8/2pP4/8/2K5/k7 b - d3 0 1")));
+}
@@ -0,0 +1,183 @@
+use crate::engine::tablebase::*;
+
+const KING_STEPS: [(i8, i8); 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+const KNIGHT_JUMPS: [(i8, i8); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+
+// Retrograde analysis: every mate is found first, then positions are resolved ply by ply walking the moves backwards.
+// A position is won once one move reaches a lost position and lost once every move reaches a won one.
+// Captures and promotions leave the table, their results come from the smaller tables which must be generated already.
+// En passant is left out, the tables are probed only when no en passant capture is possible
+pub(super) fn generate(signature: &Signature, tables: &Tables) -> Table {
+    let indexer = Indexer::new(signature);
+    let size = indexer.size();
+    let mut values = vec![NO_POSITION; size];
+    let mut resolved = vec![false; size];
+    // moves staying in the table that don't reach a won position yet
+    let mut unknown = vec![0u8; size];
+    // the slowest loss through captures and promotions, and whether one of them holds a draw
+    let mut loss_floor = vec![0u8; size];
+    let mut holds = vec![false; size];
+    let mut plies: Vec<Vec<u32>> = vec![Vec::new()];
+
+    for index in 0..size {
+        let Some(board) = indexer.board(index) else {
+            continue;
+        };
+        values[index] = Dtm::Draw.encode();
+        let moves = board.gen_moves(false);
+        if moves.is_empty() {
+            match board.is_check() {
+                true => plies[0].push(index as u32),
+                false => resolved[index] = true,
+            }
+            continue;
+        }
+        let mut children = Vec::new();
+        let mut fastest_win: Option<u8> = None;
+        for mv in &moves {
+            let child = board.make_move(mv);
+            if !mv.is_capture() && mv.promote_to().is_none() {
+                children.push(indexer.index(&child).unwrap());
+                continue;
+            }
+            let missing = format!(
+                "the {} table is needed before {signature}",
+                Signature::from_board(&child).canonical().0
+            );
+            match tables.probe_position(&child).expect(&missing) {
+                Dtm::Loss(depth) => fastest_win = Some(fastest_win.map_or(depth + 1, |win| win.min(depth + 1))),
+                Dtm::Draw => holds[index] = true,
+                Dtm::Win(depth) => loss_floor[index] = loss_floor[index].max(depth + 1),
+            }
+        }
+        children.sort_unstable();
+        children.dedup();
+        unknown[index] = children.len() as u8;
+        if let Some(win) = fastest_win {
+            holds[index] = true;
+            push(&mut plies, win, index);
+        }
+        if children.is_empty() && !holds[index] {
+            push(&mut plies, loss_floor[index], index);
+        }
+    }
+
+    let mut depth = 0;
+    while depth < plies.len() {
+        for index in std::mem::take(&mut plies[depth]) {
+            let index = index as usize;
+            if resolved[index] {
+                continue;
+            }
+            resolved[index] = true;
+            let won = depth % 2 == 1;
+            values[index] = if won { Dtm::Win(depth as u8) } else { Dtm::Loss(depth as u8) }.encode();
+            let board = indexer.board(index).unwrap();
+            for parent in predecessors(&indexer, &board) {
+                if resolved[parent] {
+                    continue;
+                }
+                if !won {
+                    push(&mut plies, depth as u8 + 1, parent);
+                    continue;
+                }
+                unknown[parent] -= 1;
+                if unknown[parent] == 0 && !holds[parent] {
+                    push(&mut plies, (depth as u8 + 1).max(loss_floor[parent]), parent);
+                }
+            }
+        }
+        depth += 1;
+    }
+    // indexes that are no position take the value before them, which keeps the runs in the files long
+    let mut last = Dtm::Draw.encode();
+    for value in values.iter_mut() {
+        if *value == NO_POSITION {
+            *value = last;
+        }
+        last = *value;
+    }
+    Table {
+        signature: signature.clone(),
+        values,
+    }
+}
+
+fn push(plies: &mut Vec<Vec<u32>>, depth: u8, index: usize) {
+    assert!(
+        depth < NO_POSITION - 2,
+        "mates longer than {} plies don't fit the table",
+        NO_POSITION - 3
+    );
+    if plies.len() <= depth as usize {
+        plies.resize(depth as usize + 1, Vec::new());
+    }
+    plies[depth as usize].push(index as u32);
+}
+
+// Indexes of the positions leading to the board by a move that is not a capture or a promotion
+fn predecessors(indexer: &Indexer, board: &Board) -> Vec<usize> {
+    let mover = board.turn.flip();
+    let mut parents = Vec::new();
+    for to in Square::all() {
+        let Some(piece) = board.piece_at(to).filter(|piece| piece.color == mover) else {
+            continue;
+        };
+        for from in origins(board, piece, to) {
+            let mut parent = *board;
+            parent.squares[to.index()] = None;
+            parent.squares[from.index()] = Some(piece);
+            parent.turn = mover;
+            // the side that didn't move can't have been in check
+            if parent.is_attacked(parent.king_square(board.turn).unwrap(), mover) {
+                continue;
+            }
+            parents.push(indexer.index(&parent).unwrap());
+        }
+    }
+    parents.sort_unstable();
+    parents.dedup();
+    parents
+}
+
+// Empty squares `piece` could have come from to reach `to`
+fn origins(board: &Board, piece: Piece, to: Square) -> Vec<Square> {
+    let step = |square: Square, (file, rank): (i8, i8)| {
+        let (file, rank) = (square.file() as i8 + file, square.rank() as i8 + rank);
+        ((0..8).contains(&file) && (0..8).contains(&rank)).then(|| Square::new(file as u8, rank as u8))
+    };
+    let empty = |square: &Square| board.piece_at(*square).is_none();
+    let slide = |directions: &[(i8, i8)]| {
+        let mut squares = Vec::new();
+        for &direction in directions {
+            let mut square = to;
+            while let Some(next) = step(square, direction).filter(empty) {
+                squares.push(next);
+                square = next;
+            }
+        }
+        squares
+    };
+    match piece.kind {
+        PieceKind::King => KING_STEPS.iter().filter_map(|&offset| step(to, offset)).filter(empty).collect(),
+        PieceKind::Knight => KNIGHT_JUMPS.iter().filter_map(|&offset| step(to, offset)).filter(empty).collect(),
+        PieceKind::Bishop => slide(&KING_STEPS[4..]),
+        PieceKind::Rook => slide(&KING_STEPS[..4]),
+        PieceKind::Queen => slide(&KING_STEPS),
+        PieceKind::Pawn => {
+            let (back, start) = if piece.color == Color::White { (-1, 1) } else { (1, 6) };
+            let mut squares = Vec::new();
+            // pawns never stand on the back rank
+            if let Some(single) = step(to, (0, back))
+                .filter(empty)
+                .filter(|square| square.rank() != 0 && square.rank() != 7)
+            {
+                squares.push(single);
+                if let Some(double) = step(single, (0, back)).filter(empty).filter(|square| square.rank() == start) {
+                    squares.push(double);
+                }
+            }
+            squares
+        }
+    }
+}
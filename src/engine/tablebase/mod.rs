@@ -0,0 +1,528 @@
+use crate::engine::evaluation::{EvalParams, MATE_SCORE};
+use crate::model::util::errors;
+use crate::model::*;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+
+mod generator;
+mod tests;
+
+// Tables are generated for up to this many pieces, kings included
+pub const MAX_PIECES: usize = 4;
+const MAGIC: &[u8; 4] = b"CTB1";
+const EXTENSION: &str = "ctb";
+// stored for indexes that are not a legal position
+const NO_POSITION: u8 = 255;
+
+// Distance to mate in plies for the side to move
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Dtm {
+    Win(u8),
+    Loss(u8),
+    Draw,
+}
+
+// The pieces of a table: the white king and pieces, then the black king and pieces, like 'KRKP'
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Signature {
+    pieces: Vec<Piece>,
+}
+
+// Distance to mate of every position of one signature with either side to move
+#[derive(Clone, Debug)]
+pub struct Table {
+    signature: Signature,
+    // one byte per index, see `Dtm::encode`
+    values: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Tables {
+    tables: HashMap<Signature, Table>,
+}
+
+impl Dtm {
+    // Draws are 0, wins the odd number of plies and losses the even number of plies plus 2
+    fn encode(self) -> u8 {
+        match self {
+            Dtm::Draw => 0,
+            Dtm::Win(plies) => plies,
+            Dtm::Loss(plies) => plies + 2,
+        }
+    }
+
+    fn decode(value: u8) -> Option<Dtm> {
+        match value {
+            NO_POSITION => None,
+            0 => Some(Dtm::Draw),
+            plies if plies % 2 == 1 => Some(Dtm::Win(plies)),
+            plies => Some(Dtm::Loss(plies - 2)),
+        }
+    }
+
+    // Mate score for the search when the position is `ply` plies from the root
+    pub fn score(self, ply: u32) -> i32 {
+        match self {
+            Dtm::Draw => 0,
+            Dtm::Win(plies) => -(MATE_SCORE + ply as i32 + plies as i32),
+            Dtm::Loss(plies) => MATE_SCORE + ply as i32 + plies as i32,
+        }
+    }
+}
+
+impl Signature {
+    pub fn from_board(board: &Board) -> Signature {
+        let mut pieces: Vec<Piece> = board.squares.into_iter().flatten().collect();
+        pieces.sort_by_key(|piece| Signature::order(*piece));
+        Signature { pieces }
+    }
+
+    // white first, the king first and then the most valuable pieces
+    fn order(piece: Piece) -> (usize, bool, std::cmp::Reverse<usize>) {
+        (
+            piece.color.index(),
+            piece.kind != PieceKind::King,
+            std::cmp::Reverse(piece.kind.index()),
+        )
+    }
+
+    fn new(mut pieces: Vec<Piece>) -> Signature {
+        pieces.sort_by_key(|piece| Signature::order(*piece));
+        Signature { pieces }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pieces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pieces.is_empty()
+    }
+
+    pub fn has_pawns(&self) -> bool {
+        self.pieces.iter().any(|piece| piece.kind == PieceKind::Pawn)
+    }
+
+    // Colors swapped, 'KPK' becomes 'KKP'
+    pub fn flipped(&self) -> Signature {
+        Signature::new(self.pieces.iter().map(|piece| Piece::new(piece.kind, piece.color.flip())).collect())
+    }
+
+    // Tables are kept with the stronger side as white, true if that needed swapping the colors
+    pub fn canonical(&self) -> (Signature, bool) {
+        let params = EvalParams::default();
+        let strength = |signature: &Signature, color: Color| {
+            let pieces = signature.pieces.iter().filter(|piece| piece.color == color);
+            let kinds: Vec<usize> = pieces.clone().map(|piece| piece.kind.index()).collect();
+            (pieces.map(|piece| params.piece_value(piece.kind)).sum::<i32>(), kinds)
+        };
+        match strength(self, Color::White) >= strength(self, Color::Black) {
+            true => (self.clone(), false),
+            false => (self.flipped(), true),
+        }
+    }
+
+    // Signatures one capture or promotion away, each in canonical form
+    fn successors(&self) -> Vec<Signature> {
+        let mut successors = Vec::new();
+        for (index, piece) in self.pieces.iter().enumerate() {
+            if piece.kind == PieceKind::King {
+                continue;
+            }
+            let mut captured = self.pieces.clone();
+            captured.remove(index);
+            successors.push(Signature::new(captured));
+            if piece.kind != PieceKind::Pawn {
+                continue;
+            }
+            for kind in PieceKind::PROMOTIONS {
+                let mut promoted = self.pieces.clone();
+                promoted[index] = Piece::new(kind, piece.color);
+                successors.push(Signature::new(promoted.clone()));
+                for (other, victim) in self.pieces.iter().enumerate() {
+                    if victim.color != piece.color && victim.kind != PieceKind::King {
+                        let mut capturing = promoted.clone();
+                        capturing.remove(other);
+                        successors.push(Signature::new(capturing));
+                    }
+                }
+            }
+        }
+        // bare kings need no table
+        successors.retain(|signature| signature.len() > 2);
+        let mut canonical: Vec<Signature> = successors.iter().map(|signature| signature.canonical().0).collect();
+        canonical.sort_by_key(|signature| signature.to_string());
+        canonical.dedup();
+        canonical
+    }
+}
+
+impl FromStr for Signature {
+    type Err = std::io::Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let unexpected = || errors::invalid_input(format!("Unexpected material signature: '{name}', expected one like 'KRKP'"));
+        let mut pieces = Vec::new();
+        let mut kings = 0;
+        for symbol in name.chars() {
+            let kind = PieceKind::from_char(symbol)
+                .filter(|_| symbol.is_ascii_uppercase())
+                .ok_or_else(unexpected)?;
+            if kind == PieceKind::King {
+                kings += 1;
+            }
+            let color = if kings > 1 { Color::Black } else { Color::White };
+            pieces.push(Piece::new(kind, color));
+        }
+        if kings != 2 || !name.starts_with('K') {
+            return Err(unexpected());
+        }
+        if pieces.len() > MAX_PIECES {
+            return Err(errors::invalid_input(format!(
+                "Tables are generated for up to {MAX_PIECES} pieces, '{name}' has more"
+            )));
+        }
+        Ok(Signature::new(pieces))
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.pieces
+            .iter()
+            .try_for_each(|piece| write!(f, "{}", piece.kind.char().to_ascii_uppercase()))
+    }
+}
+
+type Transform = fn(u8, u8) -> (u8, u8);
+
+// Symmetries of the board as (file, rank) maps. With pawns only mirroring the files keeps a position the same
+const TRANSFORMS: [Transform; 8] = [
+    |file, rank| (file, rank),
+    |file, rank| (7 - file, rank),
+    |file, rank| (file, 7 - rank),
+    |file, rank| (7 - file, 7 - rank),
+    |file, rank| (rank, file),
+    |file, rank| (7 - rank, file),
+    |file, rank| (rank, 7 - file),
+    |file, rank| (7 - rank, 7 - file),
+];
+
+// Squares the white king is moved to by the symmetries: files a..d with pawns, else the a1-d1-d4 triangle
+fn king_region(pawns: bool) -> Vec<Square> {
+    let mut region = Vec::new();
+    for rank in 0..8 {
+        for file in 0..4 {
+            if pawns || (rank <= file) {
+                region.push(Square::new(file, rank));
+            }
+        }
+    }
+    region
+}
+
+// Index of a position: the side to move, the white king's place in its region and then every other piece's square.
+// Identical pieces are ordered by square, so only one of their orders is a valid index
+struct Indexer {
+    pieces: Vec<Piece>,
+    transforms: usize,
+    kings: Vec<Square>,
+    // place of every square in `kings`
+    region: [Option<usize>; 64],
+}
+
+impl Indexer {
+    fn new(signature: &Signature) -> Indexer {
+        let pawns = signature.has_pawns();
+        let squares = king_region(pawns);
+        let mut region = [None; 64];
+        for (place, square) in squares.iter().enumerate() {
+            region[square.index()] = Some(place);
+        }
+        Indexer {
+            pieces: signature.pieces.clone(),
+            transforms: if pawns { 2 } else { 8 },
+            kings: squares,
+            region,
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.kings.len() * 64usize.pow(self.pieces.len() as u32 - 1) * 2
+    }
+
+    // The smallest index over all symmetries of the board, None if its material doesn't match
+    fn index(&self, board: &Board) -> Option<usize> {
+        let count = self.pieces.len();
+        let mut placed = [None; MAX_PIECES];
+        for square in Square::all() {
+            let Some(piece) = board.piece_at(square) else {
+                continue;
+            };
+            let slot = (0..count).find(|&slot| self.pieces[slot] == piece && placed[slot].is_none())?;
+            placed[slot] = Some(square);
+        }
+        if placed[..count].contains(&None) {
+            return None;
+        }
+        let positions = self.size() / 2;
+        let mut best: Option<usize> = None;
+        for transform in &TRANSFORMS[..self.transforms] {
+            let mut squares = [Square::A1; MAX_PIECES];
+            for (slot, square) in placed[..count].iter().flatten().enumerate() {
+                let (file, rank) = transform(square.file(), square.rank());
+                squares[slot] = Square::new(file, rank);
+            }
+            let Some(king) = self.region[squares[0].index()] else {
+                continue;
+            };
+            // identical pieces are kept in square order
+            for slot in 2..count {
+                let mut slot = slot;
+                while slot > 1 && self.pieces[slot - 1] == self.pieces[slot] && squares[slot - 1] > squares[slot] {
+                    squares.swap(slot - 1, slot);
+                    slot -= 1;
+                }
+            }
+            let index = squares[1..count].iter().fold(king, |index, square| index * 64 + square.index());
+            let index = board.turn.index() * positions + index;
+            best = Some(best.map_or(index, |best| best.min(index)));
+        }
+        best
+    }
+
+    // The position of an index, None unless it is legal and the canonical index of its symmetries
+    fn board(&self, index: usize) -> Option<Board> {
+        let positions = self.size() / 2;
+        let turn = if index < positions { Color::White } else { Color::Black };
+        let mut rest = index % positions;
+        let mut squares = [Square::A1; MAX_PIECES];
+        for slot in (1..self.pieces.len()).rev() {
+            squares[slot] = Square::from_index(rest % 64);
+            rest /= 64;
+        }
+        squares[0] = self.kings[rest];
+
+        let mut board = Board {
+            squares: [None; 64],
+            turn,
+            castling: Castling::default(),
+            en_passant: None,
+            hash: 0,
+            chess960: false,
+        };
+        for (slot, (&piece, &square)) in self.pieces.iter().zip(&squares).enumerate() {
+            let back_rank = square.rank() == 0 || square.rank() == 7;
+            let unordered = slot > 0 && self.pieces[slot - 1] == piece && squares[slot - 1] >= square;
+            if board.squares[square.index()].is_some() || (piece.kind == PieceKind::Pawn && back_rank) || unordered {
+                return None;
+            }
+            board.squares[square.index()] = Some(piece);
+        }
+        let waiting = turn.flip();
+        if board.is_attacked(board.king_square(waiting)?, turn) || self.index(&board) != Some(index) {
+            return None;
+        }
+        board.hash = board.compute_hash();
+        Some(board)
+    }
+}
+
+// The same position with the colors swapped and the board upside down
+fn mirrored(board: &Board) -> Board {
+    let mut mirror = Board {
+        squares: [None; 64],
+        turn: board.turn.flip(),
+        castling: Castling::default(),
+        en_passant: board.en_passant.map(Square::flip),
+        hash: 0,
+        chess960: board.chess960,
+    };
+    for square in Square::all() {
+        if let Some(piece) = board.piece_at(square) {
+            mirror.squares[square.flip().index()] = Some(Piece::new(piece.kind, piece.color.flip()));
+        }
+    }
+    mirror.hash = mirror.compute_hash();
+    mirror
+}
+
+impl Table {
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    // The longest win in the table, in plies
+    pub fn longest_mate(&self) -> u8 {
+        self.values
+            .iter()
+            .filter_map(|&value| Dtm::decode(value))
+            .filter_map(|dtm| match dtm {
+                Dtm::Win(plies) => Some(plies),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Only for boards of this signature with white as in the table, see `Tables::probe` for any board
+    pub fn probe(&self, board: &Board) -> Option<Dtm> {
+        let index = Indexer::new(&self.signature).index(board)?;
+        Dtm::decode(self.values[index])
+    }
+
+    // Magic, signature, number of positions and the values run length encoded as (value, LEB128 run) pairs
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let name = self.signature.to_string();
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(name.len() as u8);
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.extend_from_slice(&(self.values.len() as u32).to_le_bytes());
+        let mut values = self.values.iter().peekable();
+        while let Some(&value) = values.next() {
+            let mut run = 1u32;
+            while values.next_if_eq(&&value).is_some() {
+                run += 1;
+            }
+            bytes.push(value);
+            while run >= 0x80 {
+                bytes.push((run & 0x7f) as u8 | 0x80);
+                run >>= 7;
+            }
+            bytes.push(run as u8);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> std::io::Result<Table> {
+        let broken = || errors::invalid_input(String::from("Broken endgame table file"));
+        if bytes.len() < 5 || &bytes[..4] != MAGIC {
+            return Err(broken());
+        }
+        let name_end = 5 + bytes[4] as usize;
+        let name = std::str::from_utf8(bytes.get(5..name_end).ok_or_else(broken)?).map_err(|_| broken())?;
+        let signature: Signature = name.parse()?;
+        let count = u32::from_le_bytes(bytes.get(name_end..name_end + 4).ok_or_else(broken)?.try_into().unwrap()) as usize;
+        if count != Indexer::new(&signature).size() {
+            return Err(broken());
+        }
+        let mut values = Vec::with_capacity(count);
+        let mut rest = bytes[name_end + 4..].iter();
+        while let Some(&value) = rest.next() {
+            let (mut run, mut shift) = (0usize, 0);
+            loop {
+                let byte = *rest.next().ok_or_else(broken)?;
+                run |= ((byte & 0x7f) as usize) << shift;
+                shift += 7;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            if values.len() + run > count {
+                return Err(broken());
+            }
+            values.resize(values.len() + run, value);
+        }
+        if values.len() != count {
+            return Err(broken());
+        }
+        Ok(Table { signature, values })
+    }
+}
+
+impl Tables {
+    pub fn new() -> Tables {
+        Tables::default()
+    }
+
+    // Loads every .ctb file of the directory
+    pub fn open(directory: &Path) -> std::io::Result<Tables> {
+        let mut tables = Tables::new();
+        for entry in std::fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == EXTENSION) {
+                let table = Table::from_bytes(&std::fs::read(&path)?)?;
+                tables.tables.insert(table.signature.clone(), table);
+            }
+        }
+        Ok(tables)
+    }
+
+    // Writes every table as '<signature>.ctb'
+    pub fn save(&self, directory: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(directory)?;
+        for table in self.tables.values() {
+            std::fs::write(directory.join(format!("{}.{EXTENSION}", table.signature)), table.to_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    pub fn get(&self, signature: &Signature) -> Option<&Table> {
+        self.tables.get(&signature.canonical().0)
+    }
+
+    pub fn max_pieces(&self) -> usize {
+        self.tables.keys().map(Signature::len).max().unwrap_or(0)
+    }
+
+    // Generates the table and every smaller one it leads to by captures and promotions, unless they are loaded already.
+    // Returns the signatures generated, smallest first
+    pub fn generate(&mut self, signature: &Signature) -> Vec<Signature> {
+        let (signature, _) = signature.canonical();
+        let mut generated = Vec::new();
+        if signature.len() <= 2 || self.tables.contains_key(&signature) {
+            return generated;
+        }
+        for successor in signature.successors() {
+            generated.extend(self.generate(&successor));
+        }
+        let table = generator::generate(&signature, self);
+        self.tables.insert(signature.clone(), table);
+        generated.push(signature);
+        generated
+    }
+
+    // Tables don't know castling and en passant, such positions are left to the search
+    pub fn probe(&self, board: &Board) -> Option<Dtm> {
+        if !board.castling.is_empty() {
+            return None;
+        }
+        if board.en_passant.is_some() && board.gen_moves(true).iter().any(|mv| mv.is_en_passant()) {
+            return None;
+        }
+        self.probe_position(board)
+    }
+
+    fn probe_position(&self, board: &Board) -> Option<Dtm> {
+        match board.squares.iter().flatten().count() {
+            2 => return Some(Dtm::Draw),
+            count if count > self.max_pieces() => return None,
+            _ => (),
+        }
+        let signature = Signature::from_board(board);
+        let (canonical, flipped) = signature.canonical();
+        let table = self.tables.get(&canonical)?;
+        match flipped {
+            true => table.probe(&mirrored(board)),
+            false => table.probe(board),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+#[cfg(test)]
+use crate::engine::tablebase::*;
+
+#[cfg(test)]
+fn board(fen: &str) -> Board {
+    Board::from_fen(String::from(fen)).unwrap()
+}
+
+#[cfg(test)]
+use std::sync::OnceLock;
+
+// Generating is slow in debug builds, so the tests share one set of tables
+#[cfg(test)]
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let mut tables = Tables::new();
+        tables.generate(&"KKP".parse().unwrap());
+        tables
+    })
+}
+
+#[cfg(test)]
+fn table(name: &str) -> &'static Table {
+    tables().get(&name.parse().unwrap()).unwrap()
+}
+
+#[test]
+fn signature_names() {
+    let signature: Signature = "KRKP".parse().unwrap();
+    assert_eq!("KRKP", signature.to_string());
+    assert_eq!("KPKR", signature.flipped().to_string());
+    assert_eq!(("KRKP".parse().unwrap(), true), "KPKR".parse::<Signature>().unwrap().canonical());
+    assert_eq!("KBNK", "KNBK".parse::<Signature>().unwrap().to_string());
+    assert_eq!(signature, Signature::from_board(&board("8/8/4k3/8/3p4/8/8/2R1K3 w - - 0 1")));
+    for name in ["KQ", "QKK", "KQkK", "KQRBK", "KvK"] {
+        assert!(name.parse::<Signature>().is_err());
+    }
+    let successors: Vec<String> = "KKP"
+        .parse::<Signature>()
+        .unwrap()
+        .successors()
+        .iter()
+        .map(Signature::to_string)
+        .collect();
+    assert_eq!(vec!["KBK", "KNK", "KQK", "KRK"], successors);
+    assert_eq!(9, "KQKP".parse::<Signature>().unwrap().successors().len());
+}
+
+#[test]
+fn king_and_heavy_piece_mates() {
+    let tables = tables();
+    // mate in 10 and in 16 moves
+    assert_eq!(19, table("KQK").longest_mate());
+    assert_eq!(31, table("KRK").longest_mate());
+    assert_eq!(0, table("KNK").longest_mate());
+
+    assert_eq!(Some(Dtm::Win(1)), tables.probe(&board("k7/8/1K6/8/8/8/8/7R w - - 0 1")));
+    assert_eq!(Some(Dtm::Loss(0)), tables.probe(&board("R6k/8/6K1/8/8/8/8/8 b - - 0 1")));
+    assert_eq!(Some(Dtm::Draw), tables.probe(&board("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1")));
+    // the rook hangs
+    assert_eq!(Some(Dtm::Draw), tables.probe(&board("8/8/8/8/8/7k/1r6/2K5 w - - 0 1")));
+    // black with the rook is the same table upside down
+    assert_eq!(Some(Dtm::Win(1)), tables.probe(&board("K7/8/1k6/8/8/8/8/7r b - - 0 1")));
+    assert_eq!(None, tables.probe(&board("8/8/4k3/8/8/8/8/R3K3 w Q - 0 1")));
+    assert_eq!(None, tables.probe(&board("8/8/4k3/8/8/8/8/RB2K3 w - - 0 1")));
+}
+
+// Every win has a move to a loss one ply shorter and every loss only moves to wins
+#[test]
+fn distances_are_consistent() {
+    let tables = tables();
+    let table = table("KQK");
+    let indexer = Indexer::new(table.signature());
+    for index in 0..indexer.size() {
+        let Some(board) = indexer.board(index) else {
+            continue;
+        };
+        let children: Vec<Option<Dtm>> = board.gen_moves(false).iter().map(|mv| tables.probe(&board.make_move(mv))).collect();
+        match table.probe(&board).unwrap() {
+            Dtm::Win(plies) => {
+                assert!(children.contains(&Some(Dtm::Loss(plies - 1))));
+                assert!(children
+                    .iter()
+                    .all(|child| !matches!(child, Some(Dtm::Loss(shorter)) if *shorter < plies - 1)));
+            }
+            Dtm::Loss(0) => assert!(children.is_empty() && board.is_check()),
+            Dtm::Loss(plies) => {
+                assert!(children.contains(&Some(Dtm::Win(plies - 1))));
+                assert!(children
+                    .iter()
+                    .all(|child| matches!(child, Some(Dtm::Win(shorter)) if *shorter < plies)));
+            }
+            Dtm::Draw => assert!(children.iter().all(|child| !matches!(child, Some(Dtm::Loss(_))))),
+        }
+    }
+}
+
+#[test]
+fn pawn_endings() {
+    let tables = tables();
+    assert_eq!(5, tables.len());
+    assert!(Tables::new()
+        .generate(&"KBK".parse().unwrap())
+        .iter()
+        .map(Signature::to_string)
+        .eq(["KBK"]));
+    // the king is outside the square of the pawn
+    assert!(matches!(tables.probe(&board("7k/8/8/8/8/8/P7/K7 b - - 0 1")), Some(Dtm::Loss(_))));
+    assert_eq!(Some(Dtm::Draw), tables.probe(&board("8/8/8/8/8/k7/P7/K7 w - - 0 1")));
+    // opposition
+    assert_eq!(Some(Dtm::Draw), tables.probe(&board("8/4k3/8/4K3/4P3/8/8/8 w - - 0 1")));
+    assert!(matches!(
+        tables.probe(&board("8/4k3/8/4K3/4P3/8/8/8 b - - 0 1")),
+        Some(Dtm::Loss(_))
+    ));
+    assert_eq!(Some(Dtm::Draw), tables.probe(&board("8/8/8/4p3/4k3/8/4K3/8 b - - 0 1")));
+    assert!(matches!(tables.probe(&board("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1")), Some(Dtm::Win(_))));
+}
+
+#[test]
+fn table_files() {
+    let table = table("KRK");
+    let bytes = table.to_bytes();
+    assert!(bytes.len() < table.len());
+    let loaded = Table::from_bytes(&bytes).unwrap();
+    assert_eq!(table.values, loaded.values);
+    assert!(loaded.values.iter().all(|&value| value != NO_POSITION));
+    assert!(Table::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Table::from_bytes(b"CTB1\x03KRKjunk").is_err());
+
+    let directory = std::env::temp_dir().join(format!("crust-ctb-{}", std::process::id()));
+    tables().save(&directory).unwrap();
+    std::fs::write(directory.join("notes.txt"), "not a table").unwrap();
+    let opened = Tables::open(&directory).unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(5, opened.len());
+    assert_eq!(3, opened.max_pieces());
+    assert_eq!(Some(Dtm::Win(1)), opened.probe(&board("k7/8/1K6/8/8/8/8/7R w - - 0 1")));
+    assert!(Tables::open(Path::new("/nonexistent/tables")).is_err());
+}
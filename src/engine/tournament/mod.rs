@@ -0,0 +1,249 @@
+use crate::model::epd;
+use crate::model::pgn::{self, PgnGame};
+use crate::model::util::errors;
+use crate::model::*;
+use crate::uci::client::UciClient;
+use std::fmt::{Display, Formatter};
+use std::io::{self, ErrorKind};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub mod sprt;
+mod tests;
+
+pub use sprt::Sprt;
+
+// Moves may take this much longer than the clock allows before the game is lost on time
+const TIME_MARGIN: Duration = Duration::from_millis(100);
+// How long an engine that lost on time gets to answer 'stop' before it is restarted
+const STOP_TIMEOUT: Duration = Duration::from_secs(1);
+
+// 'base+increment' in seconds, e.g. '10+0.1'
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+pub struct MatchSettings {
+    // commands starting the two engines, the score is the one of the first
+    pub engines: [String; 2],
+    pub time_control: TimeControl,
+    // every opening is played twice in a row with the colors swapped
+    pub games: usize,
+    pub concurrency: usize,
+    pub sprt: Option<Sprt>,
+}
+
+// Games won, drawn and lost by the first engine
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+// A finished game, `white` is the index of the engine that played white
+#[derive(Clone, Debug)]
+pub struct PlayedGame {
+    pub number: usize,
+    pub white: usize,
+    pub game: Game,
+    pub winner: Option<Color>,
+    pub termination: String,
+}
+
+impl FromStr for TimeControl {
+    type Err = io::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (base, increment) = value.split_once('+').unwrap_or((value, "0"));
+        let seconds = |seconds: &str| {
+            seconds
+                .parse::<f64>()
+                .ok()
+                .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+                .map(Duration::from_secs_f64)
+                .ok_or(errors::invalid_input(format!(
+                    "Unexpected time control '{value}', expected seconds+increment"
+                )))
+        };
+        Ok(TimeControl {
+            base: seconds(base)?,
+            increment: seconds(increment)?,
+        })
+    }
+}
+
+impl MatchScore {
+    pub fn add(&mut self, played: &PlayedGame) {
+        match played.winner.map(|winner| (winner == Color::White) == (played.white == 0)) {
+            Some(true) => self.wins += 1,
+            Some(false) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+}
+
+// Wins, losses and draws with the score and the games, like '6 - 3 - 1 [0.650] 10'
+impl Display for MatchScore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} - {} - {} [{:.3}] {}", self.wins, self.losses, self.draws, self.score(), self.games())
+    }
+}
+
+impl PlayedGame {
+    // 'Game 3: first vs second: 1-0 {white mates}'
+    pub fn report(&self, names: &[String; 2]) -> String {
+        let (white, black) = (&names[self.white], &names[1 - self.white]);
+        format!("Game {}: {white} vs {black}: {} {{{}}}", self.number + 1, self.result(), self.termination)
+    }
+
+    pub fn result(&self) -> &'static str {
+        match self.winner {
+            Some(Color::White) => "1-0",
+            Some(Color::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+
+    pub fn to_pgn(&self, names: &[String; 2]) -> PgnGame {
+        let mut pgn = PgnGame::from(self.game.clone());
+        pgn.set_tag("Event", "crust match");
+        pgn.set_tag("Round", &(self.number + 1).to_string());
+        pgn.set_tag("White", &names[self.white]);
+        pgn.set_tag("Black", &names[1 - self.white]);
+        pgn.set_tag("Result", self.result());
+        pgn.set_tag("Termination", &self.termination);
+        pgn
+    }
+}
+
+// Openings from a PGN file or an EPD file with a position per line, the start position without any
+pub fn load_openings(text: &str, is_pgn: bool) -> io::Result<Vec<Game>> {
+    let openings: Vec<Game> = match is_pgn {
+        true => pgn::parse_games(text)?.into_iter().map(|pgn| pgn.game).collect(),
+        false => epd::parse_epd(text)?
+            .into_iter()
+            .map(|position| Game::from(position.board))
+            .collect(),
+    };
+    match openings.is_empty() {
+        true => Ok(vec![Game::new()]),
+        false => Ok(openings),
+    }
+}
+
+// Plays the game on from the opening with `engines[0]` as white. Games end by the rules of `Game`,
+// on time or with an illegal move
+pub fn play_game(engines: [&mut UciClient; 2], opening: &Game, time_control: TimeControl) -> io::Result<PlayedGame> {
+    let mut engines = engines;
+    for engine in engines.iter_mut() {
+        engine.new_game()?;
+    }
+    let mut game = opening.clone();
+    let mut clocks = [time_control.base; 2];
+    let finish = |game: Game, winner: Option<Color>, termination: String| PlayedGame {
+        number: 0,
+        white: 0,
+        game,
+        winner,
+        termination,
+    };
+    loop {
+        let result = game.result();
+        if result.is_over() {
+            return Ok(finish(game, result.winner(), result.to_string()));
+        }
+        let turn = game.board().turn;
+        let side = |color: Color| if color == Color::White { "white" } else { "black" };
+        let engine = &mut engines[turn.index()];
+        let go = format!(
+            "wtime {} btime {} winc {} binc {}",
+            clocks[0].as_millis(),
+            clocks[1].as_millis(),
+            time_control.increment.as_millis(),
+            time_control.increment.as_millis()
+        );
+        let start = Instant::now();
+        let best = match engine.best_move(&game, &go, Some(clocks[turn.index()] + TIME_MARGIN)) {
+            Err(err) if err.kind() == ErrorKind::TimedOut => {
+                // the late bestmove must not be taken for the answer in the next game
+                engine.send("stop")?;
+                if engine.read_until("bestmove", Some(STOP_TIMEOUT)).is_err() {
+                    engine.restart()?;
+                }
+                return Ok(finish(game, Some(turn.flip()), format!("{} loses on time", side(turn))));
+            }
+            best => best?,
+        };
+        let elapsed = start.elapsed();
+        if elapsed > clocks[turn.index()] + TIME_MARGIN {
+            return Ok(finish(game, Some(turn.flip()), format!("{} loses on time", side(turn))));
+        }
+        clocks[turn.index()] = clocks[turn.index()].saturating_sub(elapsed) + time_control.increment;
+        if best.parse::<Move>().and_then(|mv| game.make_move(&mv)).is_err() {
+            return Ok(finish(
+                game,
+                Some(turn.flip()),
+                format!("{} plays the illegal move {best}", side(turn)),
+            ));
+        }
+    }
+}
+
+// Plays the games on `concurrency` pairs of engine processes, `report` gets every game as it finishes with the score so far.
+// The match stops early once the SPRT accepts one of its hypotheses
+pub fn run_match(
+    settings: &MatchSettings,
+    openings: &[Game],
+    mut report: impl FnMut(&PlayedGame, &MatchScore, &[String; 2]),
+) -> io::Result<MatchScore> {
+    let mut pairs = Vec::new();
+    for _ in 0..settings.concurrency.max(1) {
+        pairs.push([UciClient::start(&settings.engines[0])?, UciClient::start(&settings.engines[1])?]);
+    }
+    let mut names = [pairs[0][0].name.clone(), pairs[0][1].name.clone()];
+    if names[0] == names[1] {
+        names = settings.engines.clone();
+    }
+    let (next, stop) = (&AtomicUsize::new(0), &AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for mut pair in pairs {
+            let sender = sender.clone();
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let number = next.fetch_add(1, Ordering::Relaxed);
+                    if number >= settings.games {
+                        break;
+                    }
+                    let opening = &openings[(number / 2) % openings.len()];
+                    let white = number % 2;
+                    let [first, second] = &mut pair;
+                    let engines = if white == 0 { [first, second] } else { [second, first] };
+                    let played = play_game(engines, opening, settings.time_control).map(|played| PlayedGame { number, white, ..played });
+                    let failed = played.is_err();
+                    if sender.send(played).is_err() || failed {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        let mut score = MatchScore::default();
+        for played in receiver {
+            let played = played.inspect_err(|_| stop.store(true, Ordering::Relaxed))?;
+            score.add(&played);
+            report(&played, &score, &names);
+            if settings.sprt.is_some_and(|sprt| sprt.decision(&score).is_some()) {
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+        Ok(score)
+    })
+}
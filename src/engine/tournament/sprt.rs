@@ -0,0 +1,107 @@
+use crate::engine::tournament::MatchScore;
+
+// Sequential probability ratio test of H0 'the Elo difference is elo0' against H1 'it is elo1',
+// with the normal approximation of the game scores as used by fishtest
+#[derive(Clone, Copy, Debug)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    // false positive and false negative rates
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+// 95% of the normal distribution
+const Z_95: f64 = 1.959964;
+
+// Expected score of the stronger side
+pub fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+pub fn elo(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+impl MatchScore {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games().max(1) as f64
+    }
+
+    // Variance of a single game's score
+    fn variance(&self) -> f64 {
+        let score = self.score();
+        let deviation = |result: f64, count: u32| count as f64 * (result - score).powi(2);
+        (deviation(1.0, self.wins) + deviation(0.5, self.draws) + deviation(0.0, self.losses)) / self.games().max(1) as f64
+    }
+
+    // The Elo difference and the half width of its 95% confidence interval, infinite after only wins or only losses
+    pub fn elo(&self) -> (f64, f64) {
+        let error = Z_95 * (self.variance() / self.games().max(1) as f64).sqrt();
+        let (low, high) = (elo((self.score() - error).max(0.0)), elo((self.score() + error).min(1.0)));
+        match self.score() {
+            score if score <= 0.0 || score >= 1.0 => (elo(score), f64::INFINITY),
+            score => (elo(score), (high - low) / 2.0),
+        }
+    }
+
+    // 'Elo difference: 35.2 +/- 60.1'
+    pub fn elo_difference(&self) -> String {
+        let (elo, error) = self.elo();
+        format!("Elo difference: {elo:.1} +/- {error:.1}")
+    }
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64) -> Sprt {
+        Sprt {
+            elo0,
+            elo1,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+
+    // Log likelihood ratio of H1 against H0
+    pub fn llr(&self, score: &MatchScore) -> f64 {
+        let variance = score.variance();
+        if score.games() == 0 || variance == 0.0 {
+            return 0.0;
+        }
+        let (score0, score1) = (expected_score(self.elo0), expected_score(self.elo1));
+        score.games() as f64 * (score1 - score0) * (2.0 * score.score() - score0 - score1) / (2.0 * variance)
+    }
+
+    // H0 is accepted below the lower bound and H1 above the upper one
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    // 'LLR: 1.25 (-2.94, 2.94) [0, 5]'
+    pub fn report(&self, score: &MatchScore) -> String {
+        let (lower, upper) = self.bounds();
+        format!("LLR: {:.2} ({lower:.2}, {upper:.2}) [{}, {}]", self.llr(score), self.elo0, self.elo1)
+    }
+
+    pub fn verdict(&self, score: &MatchScore) -> &'static str {
+        match self.decision(score) {
+            Some(true) => "SPRT: H1 accepted",
+            Some(false) => "SPRT: H0 accepted",
+            None => "SPRT: no decision",
+        }
+    }
+
+    // Some(true) once H1 is accepted, Some(false) for H0
+    pub fn decision(&self, score: &MatchScore) -> Option<bool> {
+        let (lower, upper) = self.bounds();
+        match self.llr(score) {
+            llr if llr >= upper => Some(true),
+            llr if llr <= lower => Some(false),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,109 @@
+#[cfg(test)]
+use crate::engine::tournament::sprt::{self, *};
+#[cfg(test)]
+use crate::engine::tournament::*;
+
+#[test]
+fn time_control_parsing() {
+    let tc: TimeControl = "10+0.1".parse().unwrap();
+    assert_eq!(Duration::from_secs(10), tc.base);
+    assert_eq!(Duration::from_millis(100), tc.increment);
+    assert_eq!(Duration::ZERO, "60".parse::<TimeControl>().unwrap().increment);
+    assert!("10+".parse::<TimeControl>().is_err());
+    assert!("-1+0".parse::<TimeControl>().is_err());
+}
+
+#[test]
+fn elo_and_error() {
+    assert!((sprt::elo(sprt::expected_score(100.0)) - 100.0).abs() < 1e-9);
+    let even = MatchScore {
+        wins: 10,
+        draws: 20,
+        losses: 10,
+    };
+    assert_eq!(40, even.games());
+    assert_eq!(0.5, even.score());
+    let (elo, error) = even.elo();
+    assert!(elo.abs() < 1e-9);
+    assert!(error > 50.0 && error < 100.0, "{error}");
+    let better = MatchScore {
+        wins: 300,
+        draws: 400,
+        losses: 200,
+    };
+    let (elo, error) = better.elo();
+    assert!((elo - 38.8).abs() < 0.5, "{elo}");
+    assert!(error < 20.0, "{error}");
+    let lost = MatchScore {
+        wins: 0,
+        draws: 0,
+        losses: 4,
+    };
+    assert_eq!((f64::NEG_INFINITY, f64::INFINITY), lost.elo());
+}
+
+#[test]
+fn sprt_decisions() {
+    let sprt = Sprt::new(0.0, 10.0);
+    let (lower, upper) = sprt.bounds();
+    assert!((lower + 2.944).abs() < 1e-3 && (upper - 2.944).abs() < 1e-3);
+    assert_eq!(None, sprt.decision(&MatchScore::default()));
+    let even = |games: u32| MatchScore {
+        wins: games,
+        draws: 2 * games,
+        losses: games,
+    };
+    assert_eq!(None, sprt.decision(&even(10)));
+    assert_eq!(Some(false), sprt.decision(&even(2000)));
+    let better = MatchScore {
+        wins: 3000,
+        draws: 4000,
+        losses: 2000,
+    };
+    assert!(sprt.llr(&better) > upper);
+    assert_eq!(Some(true), sprt.decision(&better));
+}
+
+#[test]
+fn paired_scores() {
+    let played = |white: usize, winner: Option<Color>| PlayedGame {
+        number: 0,
+        white,
+        game: Game::new(),
+        winner,
+        termination: String::new(),
+    };
+    let mut score = MatchScore::default();
+    for game in [
+        played(0, Some(Color::White)),
+        played(1, Some(Color::White)),
+        played(1, Some(Color::Black)),
+        played(0, None),
+    ] {
+        score.add(&game);
+    }
+    assert_eq!(
+        MatchScore {
+            wins: 2,
+            draws: 1,
+            losses: 1
+        },
+        score
+    );
+    let pgn = played(1, Some(Color::Black)).to_pgn(&[String::from("new"), String::from("old")]);
+    assert_eq!(Some("old"), pgn.tag("White"));
+    assert_eq!(Some("new"), pgn.tag("Black"));
+    assert_eq!(Some("0-1"), pgn.tag("Result"));
+    assert!(pgn.to_string().ends_with(" 0-1\n") || pgn.to_string().ends_with("\n0-1\n"));
+}
+
+#[test]
+fn opening_files() {
+    let epd = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq -\n# comment\n\n";
+    let openings = load_openings(epd, false).unwrap();
+    assert_eq!(1, openings.len());
+    assert_eq!(Color::Black, openings[0].board().turn);
+    let openings = load_openings("[Event \"?\"]\n\n1. d4 d5 2. c4 *\n", true).unwrap();
+    assert_eq!(3, openings[0].moves().len());
+    assert_eq!(Game::new().to_fen(), load_openings("", false).unwrap()[0].to_fen());
+}
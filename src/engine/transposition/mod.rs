@@ -0,0 +1,138 @@
+mod tests;
+
+use crate::model::Move;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+pub const DEFAULT_HASH_MB: usize = 16;
+const GENERATIONS: u8 = 32;
+const HASHFULL_SAMPLE: usize = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Entry {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+}
+
+// Every slot holds `key ^ data` next to `data`. A reader that races with a writer
+// gets a pair that doesn't xor back to its key and treats the slot as empty,
+// so threads can share the table without locks.
+struct Slot {
+    check: AtomicU64,
+    data: AtomicU64,
+}
+
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    // bumped by every search, hashfull only counts entries of the current one
+    generation: AtomicU8,
+}
+
+impl TranspositionTable {
+    pub fn new(size_mb: usize) -> TranspositionTable {
+        let count = (size_mb.max(1) * 1024 * 1024 / std::mem::size_of::<Slot>()).max(1);
+        let slots = (0..count)
+            .map(|_| Slot {
+                check: AtomicU64::new(0),
+                data: AtomicU64::new(0),
+            })
+            .collect();
+        TranspositionTable {
+            slots,
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    pub fn new_search(&self) {
+        let next = (self.generation() + 1) % GENERATIONS;
+        self.generation.store(next, Ordering::Relaxed);
+    }
+
+    // Permille of the table filled by the current search, estimated from the first slots
+    pub fn hashfull(&self) -> u32 {
+        let sample = &self.slots[..self.slots.len().min(HASHFULL_SAMPLE)];
+        let generation = self.generation() as u64;
+        let used = sample
+            .iter()
+            .map(|slot| slot.data.load(Ordering::Relaxed))
+            .filter(|&data| data != 0 && (data >> 17) & 0b11111 == generation)
+            .count();
+        (used * 1000 / sample.len()) as u32
+    }
+
+    fn generation(&self) -> u8 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<Entry> {
+        let slot = &self.slots[self.index(hash)];
+        let data = slot.data.load(Ordering::Relaxed);
+        let check = slot.check.load(Ordering::Relaxed);
+        if data == 0 || check ^ data != hash {
+            return None;
+        }
+        Some(Entry::unpack(data))
+    }
+
+    pub fn store(&self, hash: u64, entry: Entry) {
+        let slot = &self.slots[self.index(hash)];
+        let data = entry.pack() | (self.generation() as u64) << 17;
+        slot.check.store(hash ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.check.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn index(&self, hash: u64) -> usize {
+        (hash % self.slots.len() as u64) as usize
+    }
+}
+
+// data layout: score (32) | depth (8) | bound (2) | generation (5) | move (16) | occupied (1)
+impl Entry {
+    fn pack(&self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 0u64,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+        let mv = self.best_move.map_or(0, |mv| pack_move(&mv));
+        ((self.score as u32 as u64) << 32) | ((self.depth as u64) << 24) | (bound << 22) | (mv << 1) | 1
+    }
+
+    fn unpack(data: u64) -> Entry {
+        let bound = match (data >> 22) & 0b11 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+        let mv = (data >> 1) & 0xFFFF;
+        Entry {
+            best_move: if mv == 0 { None } else { Some(unpack_move(mv)) },
+            score: (data >> 32) as u32 as i32,
+            depth: (data >> 24) as u8,
+            bound,
+        }
+    }
+}
+
+fn pack_move(mv: &Move) -> u64 {
+    mv.raw() as u64
+}
+
+fn unpack_move(data: u64) -> Move {
+    Move::from_raw(data as u16)
+}
@@ -0,0 +1,45 @@
+#[cfg(test)]
+use crate::engine::transposition::*;
+#[cfg(test)]
+use crate::model::{PieceKind, Square};
+
+#[test]
+fn store_and_probe() {
+    let table = TranspositionTable::new(1);
+    let entry = Entry {
+        best_move: Some(Move::promotion("e7".parse().unwrap(), Square::E8, PieceKind::Knight)),
+        score: -1234,
+        depth: 7,
+        bound: Bound::Lower,
+    };
+    table.store(0xDEAD_BEEF, entry);
+
+    let found = table.probe(0xDEAD_BEEF).unwrap();
+    assert_eq!(entry.best_move, found.best_move);
+    assert_eq!(-1234, found.score);
+    assert_eq!(7, found.depth);
+    assert_eq!(Bound::Lower, found.bound);
+    assert!(table.probe(0xDEAD_BEEE).is_none());
+}
+
+#[test]
+fn clear_table() {
+    let table = TranspositionTable::new(1);
+    table.store(42, Entry { best_move: None, score: i32::MIN + 20, depth: 1, bound: Bound::Exact });
+    assert_eq!(i32::MIN + 20, table.probe(42).unwrap().score);
+    table.clear();
+    assert!(table.probe(42).is_none());
+}
+
+#[test]
+fn hashfull_counts_current_search() {
+    let table = TranspositionTable::new(1);
+    assert_eq!(0, table.hashfull());
+    for hash in 0..100_000u64 {
+        table.store(hash, Entry { best_move: None, score: 0, depth: 1, bound: Bound::Exact });
+    }
+    assert!(table.hashfull() > 0);
+    table.new_search();
+    assert_eq!(0, table.hashfull());
+    assert!(table.probe(99_999).is_some());
+}
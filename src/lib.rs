@@ -0,0 +1,13 @@
+// crust as a library: board and move types with FEN/SAN, move generation, evaluation and the search
+// behind the UCI and xboard front ends and the subcommands of the binary in main.rs
+pub mod cecp;
+pub mod console;
+pub mod engine;
+pub mod model;
+pub mod uci;
+
+pub use engine::evaluation::EvalParams;
+pub use engine::output::{CollectingOutput, Info, Output, Response, Score, SearchStats, StdoutOutput};
+pub use engine::searcher::{SearchLimits, SearchResult};
+pub use engine::{Engine, Settings};
+pub use model::{Board, Color, Game, GameResult, Move, MoveList, Piece, PieceKind, Square};
@@ -1,26 +1,63 @@
-mod engine;
-mod model;
-mod uci;
-
+use crust::cecp::{self, Xboard};
+use crust::uci::Command;
+use crust::Engine;
 use std::io;
-use uci::*;
+
+mod cli;
 
 fn main() -> io::Result<()> {
-    let mut buffer = String::new();
-    let mut engine = engine::Engine::new();
-    loop {
-        io::stdin().read_line(&mut buffer)?;
-        if buffer.contains("quit") {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => protocol(),
+        Some("bench") => cli::bench(&args[1..]),
+        Some("book") => cli::build_book(&args[1..]),
+        Some("tablebase") => cli::generate_tables(&args[1..]),
+        Some("perft-suite") => cli::perft_suite(&args[1..]),
+        Some("solve-suite") => cli::solve_suite(&args[1..]),
+        Some("match") => cli::play_match(&args[1..]),
+        Some("calibrate") => cli::calibrate(&args[1..]),
+        Some("play") => cli::play(&args[1..]),
+        Some("analyze") => cli::analyze(&args[1..]),
+        Some(_) => Err(cli::usage()),
+    }
+}
+
+// UCI unless the first command is 'xboard'
+fn protocol() -> io::Result<()> {
+    let mut lines = io::stdin().lines();
+    let first = loop {
+        match lines.next() {
+            None => return Ok(()),
+            Some(line) if line.as_ref().is_ok_and(|line| line.trim().is_empty()) => continue,
+            Some(line) => break line?,
+        }
+    };
+    let lines = std::iter::once(Ok(first.clone())).chain(lines);
+    match first.trim() {
+        "xboard" => xboard(lines),
+        _ => uci(lines),
+    }
+}
+
+// `go` runs the search in the background, so 'stop', 'ponderhit' and 'isready'
+// are handled while the engine is thinking
+fn uci(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<()> {
+    let mut engine = Engine::new();
+    for line in lines {
+        if !engine.execute_line(&line?) {
             break;
         }
-        match Command::parse(&buffer.trim()) {
-            Ok(command) => {
-                engine.execute_uci(command)?;
-                buffer.clear();
-            }
-            Err(err) => println!("{}", err)
+    }
+    engine.execute_uci(Command::Stop)
+}
+
+// The engine's moves and thinking are written by the search thread, so '?' and 'ping' are answered while it thinks
+fn xboard(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<()> {
+    let mut xboard = Xboard::new(|line: &str| println!("{line}"));
+    for line in lines {
+        if !xboard.execute_line(&line?) {
+            return Ok(());
         }
-        buffer.clear();
     }
-    Ok(())
+    xboard.execute(cecp::Command::MoveNow)
 }
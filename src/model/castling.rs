@@ -0,0 +1,149 @@
+use crate::model::util::errors;
+use crate::model::{Board, Color, Piece, PieceKind, Square};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CastleSide {
+    King,
+    Queen,
+}
+
+// Files of the rooks that may still castle. Tying the right to a rook file
+// instead of a corner is what makes Chess960 castling work
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Castling {
+    rooks: [[Option<u8>; 2]; 2],
+}
+
+impl CastleSide {
+    pub const ALL: [CastleSide; 2] = [CastleSide::King, CastleSide::Queen];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    // Where king and rook end up, the same as in classical chess
+    pub fn king_file(self) -> u8 {
+        match self {
+            CastleSide::King => 6,
+            CastleSide::Queen => 2,
+        }
+    }
+
+    pub fn rook_file(self) -> u8 {
+        match self {
+            CastleSide::King => 5,
+            CastleSide::Queen => 3,
+        }
+    }
+}
+
+impl Castling {
+    pub fn rook_file(&self, color: Color, side: CastleSide) -> Option<u8> {
+        self.rooks[color.index()][side.index()]
+    }
+
+    pub fn set(&mut self, color: Color, side: CastleSide, file: Option<u8>) {
+        self.rooks[color.index()][side.index()] = file;
+    }
+
+    pub fn clear(&mut self, color: Color) {
+        self.rooks[color.index()] = [None, None];
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rooks.iter().flatten().all(Option::is_none)
+    }
+}
+
+fn back_rank(color: Color) -> u8 {
+    match color {
+        Color::White => 0,
+        Color::Black => 7,
+    }
+}
+
+impl Board {
+    pub fn king_square(&self, color: Color) -> Option<Square> {
+        let king = Some(Piece::new(PieceKind::King, color));
+        Square::all().find(|&square| self.piece_at(square) == king)
+    }
+
+    pub fn castling_rook(&self, color: Color, side: CastleSide) -> Option<Square> {
+        self.castling.rook_file(color, side).map(|file| Square::new(file, back_rank(color)))
+    }
+
+    // The rook of `color` on the back rank furthest from the king on `side`, what 'K' and 'Q' mean in X-FEN
+    fn outermost_rook(&self, color: Color, side: CastleSide) -> Option<u8> {
+        let king = self.king_square(color).filter(|king| king.rank() == back_rank(color))?;
+        let rook = Some(Piece::new(PieceKind::Rook, color));
+        let is_rook = |file: &u8| self.piece_at(Square::new(*file, back_rank(color))) == rook;
+        match side {
+            CastleSide::King => (king.file() + 1..8).rev().find(is_rook),
+            CastleSide::Queen => (0..king.file()).find(is_rook),
+        }
+    }
+
+    // Reads 'KQkq' as well as Shredder-FEN ('HAha') and X-FEN ('Kkc') castling fields
+    pub(crate) fn parse_castling(&mut self, field: &str) -> std::io::Result<()> {
+        self.castling = Castling::default();
+        if field == "-" {
+            return Ok(());
+        }
+        let mut shredder = false;
+        for symbol in field.chars() {
+            let color = if symbol.is_ascii_uppercase() { Color::White } else { Color::Black };
+            let king = self.king_square(color).filter(|king| king.rank() == back_rank(color));
+            let invalid = || errors::invalid_input(format!("Unexpected castling right '{symbol}' in fen string"));
+            let (side, file) = match symbol.to_ascii_lowercase() {
+                'k' => (CastleSide::King, self.outermost_rook(color, CastleSide::King).ok_or_else(invalid)?),
+                'q' => (CastleSide::Queen, self.outermost_rook(color, CastleSide::Queen).ok_or_else(invalid)?),
+                file @ 'a'..='h' => {
+                    shredder = true;
+                    let file = file as u8 - b'a';
+                    let king = king.ok_or_else(invalid)?;
+                    let side = if file > king.file() { CastleSide::King } else { CastleSide::Queen };
+                    (side, file)
+                }
+                _ => return Err(invalid()),
+            };
+            self.castling.set(color, side, Some(file));
+        }
+        self.chess960 = shredder || !self.has_classical_castling();
+        Ok(())
+    }
+
+    // Castling rights only for a king on the e-file and rooks in the corners, positions without them need Chess960 notation
+    pub fn has_classical_castling(&self) -> bool {
+        Color::ALL.iter().all(|&color| {
+            CastleSide::ALL.iter().all(|&side| match self.castling.rook_file(color, side) {
+                None => true,
+                Some(file) => self.king_square(color).is_some_and(|king| king.file() == 4) && file == if side == CastleSide::King { 7 } else { 0 },
+            })
+        })
+    }
+
+    // X-FEN unless `shredder`: 'K' and 'Q' for the outermost rooks, rook files otherwise
+    pub(crate) fn castling_fen(&self, shredder: bool) -> String {
+        let mut result = String::new();
+        for color in Color::ALL {
+            for side in CastleSide::ALL {
+                let Some(file) = self.castling.rook_file(color, side) else {
+                    continue;
+                };
+                let symbol = if !shredder && self.outermost_rook(color, side) == Some(file) {
+                    match side {
+                        CastleSide::King => 'k',
+                        CastleSide::Queen => 'q',
+                    }
+                } else {
+                    (b'a' + file) as char
+                };
+                result.push(if color == Color::White { symbol.to_ascii_uppercase() } else { symbol });
+            }
+        }
+        if result.is_empty() {
+            result.push('-');
+        }
+        result
+    }
+}
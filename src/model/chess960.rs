@@ -0,0 +1,48 @@
+use crate::model::util::errors;
+use crate::model::{Board, PieceKind};
+
+pub const STANDARD_POSITION: u16 = 518;
+
+// Files of the two knights among the five squares left after bishops and queen
+const KNIGHTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+// Back rank of the Chess960 start position `index` in Scharnagl numbering, 518 is the classical one
+pub fn back_rank(index: u16) -> std::io::Result<[PieceKind; 8]> {
+    if index >= 960 {
+        return Err(errors::invalid_input(format!("Chess960 position must be below 960, but got {index}")));
+    }
+    let mut rank: [Option<PieceKind>; 8] = [None; 8];
+    let mut n = index as usize;
+    rank[2 * (n % 4) + 1] = Some(PieceKind::Bishop);
+    n /= 4;
+    rank[2 * (n % 4)] = Some(PieceKind::Bishop);
+    n /= 4;
+    place(&mut rank, n % 6, PieceKind::Queen);
+    n /= 6;
+    let (first, second) = KNIGHTS[n];
+    // the second knight goes first so the index of the first one doesn't shift
+    place(&mut rank, second, PieceKind::Knight);
+    place(&mut rank, first, PieceKind::Knight);
+    for kind in [PieceKind::Rook, PieceKind::King, PieceKind::Rook] {
+        place(&mut rank, 0, kind);
+    }
+    Ok(rank.map(|kind| kind.expect("All back rank squares are filled")))
+}
+
+// Puts `kind` on the `empty`-th free square
+fn place(rank: &mut [Option<PieceKind>; 8], empty: usize, kind: PieceKind) {
+    let file = (0..8).filter(|&file| rank[file].is_none()).nth(empty).expect("Not enough empty squares");
+    rank[file] = Some(kind);
+}
+
+impl Board {
+    pub fn chess960(index: u16) -> std::io::Result<Board> {
+        let rank = back_rank(index)?;
+        let black: String = rank.iter().map(|kind| kind.char()).collect();
+        let rooks: String = (0..8u8).rev().filter(|&file| rank[file as usize] == PieceKind::Rook).map(|file| (b'a' + file) as char).collect();
+        let fen = format!("{black}/pppppppp/8/8/8/8/PPPPPPPP/{} w {}{rooks} - 0 1", black.to_ascii_uppercase(), rooks.to_ascii_uppercase());
+        let mut board = Board::from_fen(fen)?;
+        board.chess960 = true;
+        Ok(board)
+    }
+}
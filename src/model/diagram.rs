@@ -0,0 +1,43 @@
+use crate::model::{Board, Color, Piece, Square};
+
+const BORDER: &str = "  +---+---+---+---+---+---+---+---+";
+
+impl Board {
+    // The board with rank and file coordinates, white at the bottom unless `flipped`. Pieces are letters as in FEN
+    // or chess symbols with `unicode`
+    pub fn diagram(&self, unicode: bool, flipped: bool) -> String {
+        let order = |coordinates: [u8; 8]| match flipped {
+            true => coordinates.into_iter().rev().collect::<Vec<u8>>(),
+            false => coordinates.to_vec(),
+        };
+        let mut lines = vec![String::from(BORDER)];
+        for rank in order([7, 6, 5, 4, 3, 2, 1, 0]) {
+            let mut line = format!("{} |", rank + 1);
+            for file in order([0, 1, 2, 3, 4, 5, 6, 7]) {
+                let symbol = match self.piece_at(Square::new(file, rank)) {
+                    None => ' ',
+                    Some(piece) if unicode => piece_symbol(piece),
+                    Some(piece) => piece.char(),
+                };
+                line.push_str(&format!(" {symbol} |"));
+            }
+            lines.push(line);
+            lines.push(String::from(BORDER));
+        }
+        let files: Vec<String> = order([0, 1, 2, 3, 4, 5, 6, 7])
+            .iter()
+            .map(|&file| ((b'a' + file) as char).to_string())
+            .collect();
+        lines.push(format!("    {}", files.join("   ")));
+        lines.join("\n")
+    }
+}
+
+fn piece_symbol(piece: Piece) -> char {
+    let symbols = match piece.color {
+        // in the order of PieceKind
+        Color::White => ['♙', '♘', '♗', '♖', '♕', '♔'],
+        Color::Black => ['♟', '♞', '♝', '♜', '♛', '♚'],
+    };
+    symbols[piece.kind.index()]
+}
@@ -0,0 +1,152 @@
+use crate::model::util::errors;
+use crate::model::{Board, Move};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// A position of an EPD file: the first four FEN fields followed by operations such as 'bm Qg6; id "WAC.001";'
+#[derive(Clone, Debug)]
+pub struct EpdPosition {
+    pub board: Board,
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+// Operands of these opcodes are written in quotes
+const STRING_OPCODES: [&str; 11] = ["id", "c0", "c1", "c2", "c3", "c4", "c5", "c6", "c7", "c8", "c9"];
+
+// One position per line, empty lines and lines starting with '#' are skipped
+pub fn parse_epd(text: &str) -> std::io::Result<Vec<EpdPosition>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::parse)
+        .collect()
+}
+
+impl EpdPosition {
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.operation("id").and_then(|operands| operands.first()).map(String::as_str)
+    }
+
+    // The moves of an operation in SAN such as 'bm' or 'am', empty when the position doesn't have it
+    pub fn san_moves(&self, opcode: &str) -> std::io::Result<Vec<Move>> {
+        self.operation(opcode)
+            .unwrap_or_default()
+            .iter()
+            .map(|san| self.board.parse_san(san))
+            .collect()
+    }
+
+    // Mate in moves from 'dm'
+    pub fn direct_mate(&self) -> std::io::Result<Option<i32>> {
+        match self.operation("dm").and_then(|operands| operands.first()) {
+            None => Ok(None),
+            Some(moves) => moves
+                .parse()
+                .map(Some)
+                .map_err(|_| errors::invalid_input(format!("Unexpected dm operand '{moves}'"))),
+        }
+    }
+
+    // Points of the moves in STS suites, from 'c0 "f5=10, Be5+=2"' or from the moves of 'c9' in UCI with the points of 'c8'
+    pub fn points(&self) -> std::io::Result<Vec<(Move, u32)>> {
+        let invalid = |operand: &str| errors::invalid_input(format!("Unexpected points '{operand}'"));
+        if let Some(comment) = self
+            .operation("c0")
+            .and_then(|operands| operands.first())
+            .filter(|comment| comment.contains('='))
+        {
+            let mut points = Vec::new();
+            for entry in comment.split(',').map(str::trim) {
+                let (san, value) = entry.rsplit_once('=').ok_or_else(|| invalid(entry))?;
+                points.push((self.board.parse_san(san)?, value.parse().map_err(|_| invalid(entry))?));
+            }
+            return Ok(points);
+        }
+        let (Some(values), Some(moves)) = (self.operation("c8"), self.operation("c9")) else {
+            return Ok(Vec::new());
+        };
+        let values: Vec<&str> = values.iter().flat_map(|operand| operand.split_whitespace()).collect();
+        let moves: Vec<&str> = moves.iter().flat_map(|operand| operand.split_whitespace()).collect();
+        if values.len() != moves.len() {
+            return Err(invalid(&values.join(" ")));
+        }
+        let mut points = Vec::new();
+        for (mv, value) in moves.into_iter().zip(values) {
+            let found = self.board.find_move(&mv.parse()?).ok_or_else(|| invalid(mv))?;
+            points.push((found, value.parse().map_err(|_| invalid(value))?));
+        }
+        Ok(points)
+    }
+}
+
+impl FromStr for EpdPosition {
+    type Err = std::io::Error;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut rest = line.trim();
+        let mut fen = Vec::new();
+        for _ in 0..4 {
+            let (field, tail) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            fen.push(field);
+            rest = tail.trim_start();
+        }
+        let board = Board::from_fen(fen.join(" "))?;
+
+        let mut operations = Vec::new();
+        let mut tokens: Vec<String> = Vec::new();
+        let mut chars = rest.chars().peekable();
+        while let Some(symbol) = chars.next() {
+            match symbol {
+                ';' => {
+                    if let Some((opcode, operands)) = tokens.split_first() {
+                        operations.push((opcode.clone(), operands.to_vec()));
+                    }
+                    tokens.clear();
+                }
+                '"' => {
+                    let string: String = chars.by_ref().take_while(|&symbol| symbol != '"').collect();
+                    tokens.push(string);
+                }
+                symbol if symbol.is_whitespace() => (),
+                symbol => {
+                    let mut token = String::from(symbol);
+                    while let Some(&next) = chars.peek().filter(|&&next| !next.is_whitespace() && next != ';') {
+                        token.push(next);
+                        chars.next();
+                    }
+                    tokens.push(token);
+                }
+            }
+        }
+        if !tokens.is_empty() {
+            return Err(errors::invalid_input(format!("Expected ';' after '{}'", tokens.join(" "))));
+        }
+        Ok(EpdPosition { board, operations })
+    }
+}
+
+impl Display for EpdPosition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let fen = self.board.to_fen();
+        let fields: Vec<&str> = fen.split(' ').take(4).collect();
+        write!(f, "{}", fields.join(" "))?;
+        for (opcode, operands) in &self.operations {
+            write!(f, " {opcode}")?;
+            for operand in operands {
+                match STRING_OPCODES.contains(&opcode.as_str()) || operand.contains(char::is_whitespace) {
+                    true => write!(f, " \"{operand}\"")?,
+                    false => write!(f, " {operand}")?,
+                }
+            }
+            write!(f, ";")?;
+        }
+        Ok(())
+    }
+}
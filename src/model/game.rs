@@ -0,0 +1,251 @@
+use crate::model::util::errors;
+use crate::model::{Board, Color, Move, PieceKind, Square};
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    Ongoing,
+    Checkmate { winner: Color },
+    Stalemate,
+    Repetition,
+    FiftyMoves,
+    InsufficientMaterial,
+}
+
+// A game from its start position: the played moves with the boards after them and the clocks
+#[derive(Clone, Debug)]
+pub struct Game {
+    // boards[0] is the start position, boards[i + 1] the position after moves[i]
+    boards: Vec<Board>,
+    moves: Vec<Move>,
+    // plies since the last capture or pawn move, one per board
+    halfmove_clocks: Vec<u32>,
+    start_fullmove: u32,
+}
+
+impl GameResult {
+    pub fn is_over(self) -> bool {
+        self != GameResult::Ongoing
+    }
+
+    pub fn winner(self) -> Option<Color> {
+        match self {
+            GameResult::Checkmate { winner } => Some(winner),
+            _ => None,
+        }
+    }
+
+    // Result tag as used in PGN
+    pub fn score(self) -> &'static str {
+        match self {
+            GameResult::Ongoing => "*",
+            GameResult::Checkmate { winner: Color::White } => "1-0",
+            GameResult::Checkmate { winner: Color::Black } => "0-1",
+            _ => "1/2-1/2",
+        }
+    }
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameResult::Ongoing => f.write_str("ongoing"),
+            GameResult::Checkmate { winner: Color::White } => f.write_str("white mates"),
+            GameResult::Checkmate { winner: Color::Black } => f.write_str("black mates"),
+            GameResult::Stalemate => f.write_str("stalemate"),
+            GameResult::Repetition => f.write_str("threefold repetition"),
+            GameResult::FiftyMoves => f.write_str("fifty move rule"),
+            GameResult::InsufficientMaterial => f.write_str("insufficient material"),
+        }
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
+}
+
+impl From<Board> for Game {
+    fn from(board: Board) -> Self {
+        Game {
+            boards: vec![board],
+            moves: Vec::new(),
+            halfmove_clocks: vec![0],
+            start_fullmove: 1,
+        }
+    }
+}
+
+impl Game {
+    pub fn new() -> Game {
+        Game::from(Board::new())
+    }
+
+    // Unlike Board::from_fen this keeps the halfmove clock and the move number
+    pub fn from_fen(fen: &str) -> std::io::Result<Game> {
+        let mut game = Game::from(Board::from_fen(String::from(fen))?);
+        let mut clocks = fen.split_whitespace().skip(4);
+        let mut clock = |name: &str, default: u32| match clocks.next() {
+            None => Ok(default),
+            Some(value) => value.parse().map_err(|_| errors::invalid_input(format!("Unexpected {name} '{value}' in fen string"))),
+        };
+        game.halfmove_clocks[0] = clock("halfmove clock", 0)?;
+        game.start_fullmove = clock("move number", 1)?.max(1);
+        Ok(game)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let fen = self.board().to_fen();
+        let position: Vec<&str> = fen.split(' ').take(4).collect();
+        format!("{} {} {}", position.join(" "), self.halfmove_clock(), self.fullmove_number())
+    }
+
+    pub fn start_fen(&self) -> String {
+        let fen = self.start().to_fen();
+        let position: Vec<&str> = fen.split(' ').take(4).collect();
+        format!("{} {} {}", position.join(" "), self.halfmove_clocks[0], self.start_fullmove)
+    }
+
+    pub fn start(&self) -> &Board {
+        &self.boards[0]
+    }
+
+    pub fn board(&self) -> &Board {
+        self.boards.last().expect("A game always has its start position")
+    }
+
+    // The start position and the board after every move
+    pub fn boards(&self) -> &[Board] {
+        &self.boards
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    // Hashes of all positions of the game, the current one last
+    pub fn hashes(&self) -> impl DoubleEndedIterator<Item = u64> + '_ {
+        self.boards.iter().map(|board| board.hash)
+    }
+
+    pub fn halfmove_clock(&self) -> u32 {
+        *self.halfmove_clocks.last().expect("A game always has its start position")
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        let black_started = self.start().turn == Color::Black;
+        self.start_fullmove + (self.moves.len() as u32 + black_started as u32) / 2
+    }
+
+    pub fn set_chess960(&mut self, chess960: bool) {
+        for board in &mut self.boards {
+            board.chess960 = chess960;
+        }
+    }
+
+    // Plays a legal move, `mv` only needs the squares and promotion as parsed from UCI
+    pub fn make_move(&mut self, mv: &Move) -> std::io::Result<Move> {
+        let board = *self.board();
+        let legal = board.find_move(mv).ok_or(errors::invalid_input(format!("Illegal move: '{mv}'")))?;
+        self.push(board, legal);
+        Ok(legal)
+    }
+
+    pub fn make_san_move(&mut self, san: &str) -> std::io::Result<Move> {
+        let board = *self.board();
+        let legal = board.parse_san(san)?;
+        self.push(board, legal);
+        Ok(legal)
+    }
+
+    fn push(&mut self, board: Board, mv: Move) {
+        let irreversible = mv.is_capture() || board.piece_at(mv.from()).is_some_and(|piece| piece.kind == PieceKind::Pawn);
+        let clock = if irreversible { 0 } else { self.halfmove_clock() + 1 };
+        self.boards.push(board.make_move(&mv));
+        self.moves.push(mv);
+        self.halfmove_clocks.push(clock);
+    }
+
+    // Takes the last move back, None at the start position
+    pub fn takeback(&mut self) -> Option<Move> {
+        let mv = self.moves.pop()?;
+        self.boards.pop();
+        self.halfmove_clocks.pop();
+        Some(mv)
+    }
+
+    // How often the current position occurred, only positions since the last capture or pawn move can repeat
+    pub fn repetitions(&self) -> usize {
+        let hash = self.board().hash;
+        let reversible = self.halfmove_clock() as usize + 1;
+        self.hashes().rev().take(reversible).step_by(2).filter(|&other| other == hash).count()
+    }
+
+    pub fn result(&self) -> GameResult {
+        let board = self.board();
+        if board.gen_moves(false).is_empty() {
+            return match board.is_check() {
+                true => GameResult::Checkmate { winner: board.inactive_color() },
+                false => GameResult::Stalemate,
+            };
+        }
+        if board.is_insufficient_material() {
+            GameResult::InsufficientMaterial
+        } else if self.halfmove_clock() >= 100 {
+            GameResult::FiftyMoves
+        } else if self.repetitions() >= 3 {
+            GameResult::Repetition
+        } else {
+            GameResult::Ongoing
+        }
+    }
+
+    pub fn san_moves(&self) -> Vec<String> {
+        self.moves.iter().zip(&self.boards).map(|(mv, board)| board.to_san(mv)).collect()
+    }
+
+    // PGN movetext like '1. e4 e5 2. Nf3', games starting with black get '1... e5'
+    pub fn movetext(&self) -> String {
+        let mut result = String::new();
+        let mut number = self.start_fullmove;
+        for (index, (san, board)) in self.san_moves().iter().zip(&self.boards).enumerate() {
+            if index > 0 {
+                result.push(' ');
+            }
+            match board.turn {
+                Color::White => result.push_str(&format!("{number}. ")),
+                Color::Black if index == 0 => result.push_str(&format!("{number}... ")),
+                Color::Black => (),
+            }
+            if board.turn == Color::Black {
+                number += 1;
+            }
+            result.push_str(san);
+        }
+        result
+    }
+}
+
+impl Board {
+    // Neither side can mate: bare kings, a single minor piece, or only bishops all on one square color
+    pub fn is_insufficient_material(&self) -> bool {
+        let (mut minors, mut knights) = (0, 0);
+        let mut bishop_colors = [false; 2];
+        for square in Square::all() {
+            match self.piece_at(square).map(|piece| piece.kind) {
+                None | Some(PieceKind::King) => (),
+                Some(PieceKind::Knight) => {
+                    minors += 1;
+                    knights += 1;
+                }
+                Some(PieceKind::Bishop) => {
+                    minors += 1;
+                    bishop_colors[((square.file() + square.rank()) % 2) as usize] = true;
+                }
+                Some(_) => return false,
+            }
+        }
+        minors <= 1 || (knights == 0 && !(bishop_colors[0] && bishop_colors[1]))
+    }
+}
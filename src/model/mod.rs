@@ -1,89 +1,189 @@
+pub mod castling;
+pub mod epd;
+pub mod chess960;
+pub mod diagram;
+pub mod game;
+pub mod move_list;
+pub mod pgn;
 pub mod pieces;
+pub mod san;
+pub mod square;
 mod tests;
 pub mod util;
+pub mod zobrist;
 
-use std::fmt::Debug;
+use std::fmt::{Debug, Display, Formatter};
+use std::str::FromStr;
 
-use pieces::*;
+pub use castling::{CastleSide, Castling};
+pub use game::{Game, GameResult};
+pub use move_list::MoveList;
+pub use pieces::{Color, Piece, PieceKind};
+pub use square::Square;
 use util::*;
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub struct Move {
-    pub from: usize,
-    pub to: usize,
-    pub promote_to: Option<u8>,
-    pub castle: bool,
-}
+// from (6) | to (6) | flags (4). The flags are a capture bit and a promotion bit,
+// the two low bits hold the promotion kind or tell double pushes, castles and en passant apart
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Move(u16);
 
 #[derive(Clone, Copy, Debug)]
 pub struct Board {
-    pub squares: [u8; 64],
-    pub white_is_active: bool,
-    pub castle_white_king: bool,
-    pub castle_white_queen: bool,
-    pub castle_black_king: bool,
-    pub castle_black_queen: bool,
-    pub en_passant: Option<usize>,
+    pub squares: [Option<Piece>; 64],
+    pub turn: Color,
+    pub castling: Castling,
+    pub en_passant: Option<Square>,
+    pub hash: u64,
+    // Castles are written king-takes-rook in UCI, e.g. 'e1h1'
+    pub chess960: bool,
 }
 
 impl Move {
-    pub fn to_notation(&self) -> String {
-        let from = index_to_square_notation(self.from).expect("Unable to convert 'from' to notation");
-        let to = index_to_square_notation(self.to).expect("Unable to convert 'to' to notation");
-        let promotion = match self.promote_to {
-            Some(QUEEN) => "q",
-            Some(ROOK) => "r",
-            Some(KNIGHT) => "k",
-            Some(BISHOP) => "b",
-            _ => "",
-        };
-        format!("{from}{to}{promotion}")
+    pub const QUIET: u16 = 0;
+    pub const DOUBLE_PUSH: u16 = 1;
+    pub const CASTLE: u16 = 2;
+    pub const CASTLE_960: u16 = 3;
+    pub const CAPTURE: u16 = 4;
+    pub const EN_PASSANT: u16 = 5;
+    pub const PROMOTION: u16 = 8;
+    const PROMOTIONS: [PieceKind; 4] = [PieceKind::Knight, PieceKind::Bishop, PieceKind::Rook, PieceKind::Queen];
+
+    pub const NULL: Move = Move(0);
+
+    pub fn new(from: Square, to: Square, flags: u16) -> Move {
+        Move(from.index() as u16 | (to.index() as u16) << 6 | flags << 12)
+    }
+
+    pub fn regular(from: Square, to: Square) -> Move {
+        Move::new(from, to, Move::QUIET)
+    }
+
+    pub fn capture(from: Square, to: Square) -> Move {
+        Move::new(from, to, Move::CAPTURE)
+    }
+
+    // From the king to the castling rook, the same in classical chess and Chess960
+    pub fn castle(king: Square, rook: Square) -> Move {
+        Move::new(king, rook, Move::CASTLE)
+    }
+
+    pub fn promotion(from: Square, to: Square, kind: PieceKind) -> Move {
+        let index = Move::PROMOTIONS.iter().position(|&promotion| promotion == kind).expect("Can't promote to pawn or king");
+        Move::new(from, to, Move::PROMOTION | index as u16)
+    }
+
+    pub fn with_capture(self) -> Move {
+        Move(self.0 | Move::CAPTURE << 12)
     }
 
-    pub fn regular(from: usize, to: usize) -> Move {
-        Move {
-            from,
-            to,
-            promote_to: None,
-            castle: false,
+    pub fn from_raw(raw: u16) -> Move {
+        Move(raw)
+    }
+
+    pub fn raw(self) -> u16 {
+        self.0
+    }
+
+    pub fn from(self) -> Square {
+        Square::from_index((self.0 & 0b111111) as usize)
+    }
+
+    pub fn to(self) -> Square {
+        Square::from_index((self.0 >> 6 & 0b111111) as usize)
+    }
+
+    pub fn flags(self) -> u16 {
+        self.0 >> 12
+    }
+
+    pub fn promote_to(self) -> Option<PieceKind> {
+        match self.flags() & Move::PROMOTION {
+            0 => None,
+            _ => Some(Move::PROMOTIONS[(self.flags() & 0b11) as usize]),
         }
     }
 
-    pub fn castle(from: usize, to: usize) -> Move {
-        Move {
-            from,
-            to,
-            promote_to: None,
-            castle: true,
+    pub fn is_capture(self) -> bool {
+        self.flags() & Move::CAPTURE != 0
+    }
+
+    pub fn is_castle(self) -> bool {
+        self.flags() == Move::CASTLE || self.flags() == Move::CASTLE_960
+    }
+
+    pub fn is_en_passant(self) -> bool {
+        self.flags() == Move::EN_PASSANT
+    }
+
+    pub fn is_double_push(self) -> bool {
+        self.flags() == Move::DOUBLE_PUSH
+    }
+
+    pub fn is_null(self) -> bool {
+        self == Move::NULL
+    }
+
+    pub fn to_notation(self) -> String {
+        self.to_string()
+    }
+
+    // Target square in UCI notation, classical castles go to the king destination instead of the rook
+    pub fn uci_to(self) -> Square {
+        match self.flags() {
+            Move::CASTLE => {
+                let side = if self.to().file() > self.from().file() { CastleSide::King } else { CastleSide::Queen };
+                Square::new(side.king_file(), self.from().rank())
+            }
+            _ => self.to(),
         }
     }
 
-    pub fn promotion(from: usize, to: usize, piece: u8) -> Move {
-        Move {
-            from,
-            to,
-            promote_to: Some(piece),
-            castle: false,
+    // Same UCI notation, the flags of a parsed UCI move don't have to match
+    pub fn same_squares(self, other: Move) -> bool {
+        self.from() == other.from() && self.uci_to() == other.uci_to() && self.promote_to() == other.promote_to()
+    }
+}
+
+// Long algebraic notation as used by UCI, 'e2e4' or 'a7a8q'
+impl Display for Move {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.from(), self.uci_to())?;
+        match self.promote_to() {
+            Some(kind) => write!(f, "{kind}"),
+            None => Ok(()),
         }
     }
-    #[allow(dead_code)]
-    pub const NULL: Move = {
-        Move {
-            from: 0,
-            to: 0,
-            promote_to: None,
-            castle: false,
+}
+
+// Parses the UCI notation. Captures, castles and the other flags depend on the position,
+// Board::find_move gives the move with them
+impl FromStr for Move {
+    type Err = std::io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.is_ascii() || s.len() < 4 || s.len() > 5 {
+            return Err(errors::invalid_input(format!("Unexpected move notation: '{s}'")));
+        }
+        let (from, to) = (s[0..2].parse()?, s[2..4].parse()?);
+        match &s[4..] {
+            "" => Ok(Move::regular(from, to)),
+            kind => match kind.parse()? {
+                PieceKind::Pawn | PieceKind::King => Err(errors::invalid_input(format!("Unexpected promotion: '{kind}'"))),
+                kind => Ok(Move::promotion(from, to, kind)),
+            },
         }
-    };
-    #[allow(dead_code)]
-    pub fn is_null(&self) -> bool {
-        self.eq(&Move::NULL)
     }
 }
 
 impl Debug for Move {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(self.to_notation().as_str())
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new()
     }
 }
 
@@ -94,132 +194,88 @@ impl Board {
 
     pub fn from_fen(fen: String) -> std::io::Result<Board> {
         let mut fen = fen.chars();
-        let mut squares: [u8; 64] = [0; 64];
+        let mut squares: [Option<Piece>; 64] = [None; 64];
         let mut current = 0;
         for symbol in &mut fen {
             match symbol {
-                'r' => {
-                    squares[current] = rook(BLACK);
-                    current += 1;
-                }
-                'n' => {
-                    squares[current] = knight(BLACK);
-                    current += 1;
-                }
-                'b' => {
-                    squares[current] = bishop(BLACK);
-                    current += 1;
-                }
-                'q' => {
-                    squares[current] = quieen(BLACK);
-                    current += 1;
-                }
-                'k' => {
-                    squares[current] = king(BLACK);
-                    current += 1;
-                }
-                'p' => {
-                    squares[current] = pawn(BLACK);
-                    current += 1;
-                }
-                'R' => {
-                    squares[current] = rook(WHITE);
-                    current += 1;
-                }
-                'N' => {
-                    squares[current] = knight(WHITE);
-                    current += 1;
-                }
-                'B' => {
-                    squares[current] = bishop(WHITE);
-                    current += 1;
-                }
-                'Q' => {
-                    squares[current] = quieen(WHITE);
-                    current += 1;
-                }
-                'K' => {
-                    squares[current] = king(WHITE);
-                    current += 1;
-                }
-                'P' => {
-                    squares[current] = pawn(WHITE);
-                    current += 1;
-                }
                 '/' => (),
-                number if number.is_digit(10) => {
-                    let empty_count = usize::try_from(number.to_digit(10).unwrap()).expect("Unable to convert char to usize???");
-                    current += empty_count;
-                }
-                _parse_start_fenother => todo!("Error"),
+                number if number.is_ascii_digit() => current += number.to_digit(10).unwrap() as usize,
+                other => match Piece::from_char(other) {
+                    Some(piece) if current < 64 => {
+                        squares[current] = Some(piece);
+                        current += 1;
+                    }
+                    _ => return Err(errors::invalid_input(format!("Unexpected symbol '{other}' in fen string"))),
+                },
+            }
+            if current > 64 {
+                return Err(errors::invalid_input(String::from("Too many squares in fen string")));
             }
-            if current >= 64 {
+            if current == 64 {
                 break;
             }
         }
 
-        let space = (&mut fen).next();
+        let space = fen.next();
         if space != Some(' ') {
-            return Result::Err(errors::invalid_input(format!("Expected space after pieces string")));
+            return Result::Err(errors::invalid_input(String::from("Expected space after pieces string")));
         }
-        let white_is_active = match (&mut fen).next() {
-            Some('w') => true,
-            Some('b') => false,
-            _ => return Result::Err(errors::invalid_input(format!("Expected active color after fen string"))),
+        let turn = match fen.next() {
+            Some('w') => Color::White,
+            Some('b') => Color::Black,
+            _ => return Result::Err(errors::invalid_input(String::from("Expected active color after fen string"))),
         };
-        let space = (&mut fen).next();
+        let space = fen.next();
         if space != Some(' ') {
-            return Result::Err(errors::invalid_input(format!("Expected space after pieces string")));
+            return Result::Err(errors::invalid_input(String::from("Expected space after pieces string")));
         }
-        let mut castle_white_king = false;
-        let mut castle_white_queen = false;
-        let mut castle_black_king = false;
-        let mut castle_black_queen = false;
-        #[allow(irrefutable_let_patterns)]
-        while let next = (&mut fen).next() {
-            match next {
-                Some('K') => castle_white_king = true,
-                Some('k') => castle_black_king = true,
-                Some('Q') => castle_white_queen = true,
-                Some('q') => castle_black_queen = true,
-                Some('-') => (),
-                Some(' ') => break,
-                _ => return Result::Err(errors::invalid_input(format!("Expected castle state in fen string"))),
-            }
-        }
-        let en_passant = match (&mut fen).next() {
+        let castling: String = fen.by_ref().take_while(|&symbol| symbol != ' ').collect();
+        let en_passant = match fen.next() {
             Some('-') => None,
-            Some(file) => match (&mut fen).next() {
-                Some(rank) => Some(square_notation_to_index(format!("{file}{rank}").as_str())?),
-                None => return Result::Err(errors::invalid_input(format!("Expected en_passant rank in fen string"))),
+            Some(file) => match fen.next() {
+                Some(rank) => Some(format!("{file}{rank}").parse()?),
+                None => return Result::Err(errors::invalid_input(String::from("Expected en_passant rank in fen string"))),
             },
-            None => return Result::Err(errors::invalid_input(format!("Expected en_passant  in fen string"))),
+            None => return Result::Err(errors::invalid_input(String::from("Expected en_passant  in fen string"))),
         };
 
-        Ok(Board {
+        let mut board = Board {
             squares,
-            white_is_active,
-            castle_white_king,
-            castle_white_queen,
-            castle_black_king,
-            castle_black_queen,
+            turn,
+            castling: Castling::default(),
             en_passant,
-        })
+            hash: 0,
+            chess960: false,
+        };
+        board.parse_castling(&castling)?;
+        board.hash = board.compute_hash();
+        Ok(board)
+    }
+
+    // X-FEN, which is plain 'KQkq' for classical positions
+    pub fn to_fen(self) -> String {
+        self.fen_with_castling(false)
+    }
+
+    // Rook files for all castling rights, 'HAha' for the start position
+    pub fn to_shredder_fen(self) -> String {
+        self.fen_with_castling(true)
     }
-    pub fn to_fen(&self) -> String {
+
+    fn fen_with_castling(self, shredder: bool) -> String {
         let mut result = String::with_capacity(64);
         let mut empty_count = 0;
         for rank in 0..8 {
             for file in 0..8 {
-                let piece = self.squares[rank * 8 + file];
-                if piece == NONE {
-                    empty_count += 1;
-                } else {
-                    if empty_count > 0 {
-                        result.push_str(empty_count.to_string().as_str());
-                        empty_count = 0;
+                match self.squares[rank * 8 + file] {
+                    None => empty_count += 1,
+                    Some(piece) => {
+                        if empty_count > 0 {
+                            result.push_str(empty_count.to_string().as_str());
+                            empty_count = 0;
+                        }
+                        result.push(piece.char());
                     }
-                    result.push(piece.notation());
                 }
             }
             if empty_count > 0 {
@@ -231,121 +287,112 @@ impl Board {
             }
         }
         result.push(' ');
-        if self.white_is_active {
-            result.push('w');
-        } else {
-            result.push('b');
-        }
+        result.push_str(&self.turn.to_string());
         result.push(' ');
-        if !self.castle_white_king && !self.castle_white_queen && !self.castle_black_king && !self.castle_black_queen {
-            result.push('-');
-        } else {
-            if self.castle_white_king {
-                result.push('K');
-            }
-            if self.castle_white_queen {
-                result.push('Q');
-            }
-            if self.castle_black_king {
-                result.push('k');
-            }
-            if self.castle_black_queen {
-                result.push('q');
-            }
-        }
+        result.push_str(&self.castling_fen(shredder));
         result.push(' ');
         match self.en_passant {
-            Some(square) => result.push_str(index_to_square_notation(square).unwrap().as_str()),
+            Some(square) => result.push_str(&square.to_string()),
             None => result.push('-'),
         }
         result.push_str(" 0 0");
         result
     }
 
-    pub fn active_color(&self) -> u8 {
-        if self.white_is_active {
-            WHITE
-        } else {
-            BLACK
+    pub fn active_color(&self) -> Color {
+        self.turn
+    }
+
+    pub fn inactive_color(&self) -> Color {
+        self.turn.flip()
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.squares[square.index()]
+    }
+
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = self.castle_hash() ^ self.en_passant_hash();
+        for (square, piece) in self.squares.iter().enumerate() {
+            if let Some(piece) = piece {
+                hash ^= zobrist::KEYS.pieces[piece.index()][square];
+            }
+        }
+        if self.turn == Color::Black {
+            hash ^= zobrist::KEYS.black_to_move;
         }
+        hash
     }
-    pub fn inactive_color(&self) -> u8 {
-        if self.white_is_active {
-            BLACK
-        } else {
-            WHITE
+
+    fn castle_hash(&self) -> u64 {
+        let keys = &zobrist::KEYS.castle;
+        let rights = Color::ALL.into_iter().flat_map(|color| CastleSide::ALL.map(|side| self.castling.rook_file(color, side).is_some()));
+        rights.zip(keys).filter(|&(right, _)| right).fold(0, |acc, (_, key)| acc ^ key)
+    }
+
+    fn en_passant_hash(&self) -> u64 {
+        match self.en_passant {
+            Some(square) => zobrist::KEYS.en_passant[square.file() as usize],
+            None => 0,
         }
     }
 
+    fn set_square(&mut self, square: Square, piece: Option<Piece>) {
+        if let Some(old) = self.squares[square.index()] {
+            self.hash ^= zobrist::KEYS.pieces[old.index()][square.index()];
+        }
+        if let Some(piece) = piece {
+            self.hash ^= zobrist::KEYS.pieces[piece.index()][square.index()];
+        }
+        self.squares[square.index()] = piece;
+    }
+
     pub fn make_move(&self, mv: &Move) -> Board {
-        let mut result = self.clone();
-        let moved_piece = self.squares[mv.from];
+        let mut result = *self;
+        let (from, to) = (mv.from(), mv.to());
+        let moved_piece = self.piece_at(from).expect("No piece to move");
+        result.hash ^= self.castle_hash() ^ self.en_passant_hash();
 
         // Disable castling
-        {
-            if mv.from == 60 {
-                result.castle_white_king = false;
-                result.castle_white_queen = false;
-            }
-            if mv.from == 63 || mv.to == 63 {
-                result.castle_white_king = false;
-            }
-            if mv.from == 56 || mv.to == 56 {
-                result.castle_white_queen = false;
-            }
-            if mv.from == 4 {
-                result.castle_black_king = false;
-                result.castle_black_queen = false;
-            }
-            if mv.from == 7 || mv.to == 7 {
-                result.castle_black_king = false;
-            }
-            if mv.from == 0 || mv.to == 0 {
-                result.castle_black_queen = false;
+        if moved_piece.kind == PieceKind::King {
+            result.castling.clear(self.turn);
+        }
+        for color in Color::ALL {
+            for side in CastleSide::ALL {
+                if self.castling_rook(color, side).is_some_and(|rook| rook == from || rook == to) {
+                    result.castling.set(color, side, None);
+                }
             }
         }
 
-        if moved_piece.is_pawn() && (mv.from / 8).abs_diff(mv.to / 8) > 1 {
-            result.en_passant = Some((mv.from + mv.to) / 2);
+        if mv.is_double_push() {
+            result.en_passant = Some(Square::new(from.file(), (from.rank() + to.rank()) / 2));
         } else {
             result.en_passant = None;
         }
-        if moved_piece.is_pawn() && self.en_passant == Some(mv.to) {
-            if mv.to < 32 {
-                result.squares[mv.to + 8] = NONE;
-            } else {
-                result.squares[mv.to - 8] = NONE;
-            }
+        if mv.is_en_passant() {
+            result.set_square(Square::new(to.file(), from.rank()), None);
         }
 
-        result.squares[mv.to] = match mv.promote_to {
-            Some(piece) => pieces::new(piece, self.active_color()),
-            None => result.squares[mv.from],
-        };
-        result.squares[mv.from] = 0;
-        if mv.castle {
-            match mv.to {
-                62 => {
-                    result.squares[61] = result.squares[63];
-                    result.squares[63] = NONE;
-                }
-                58 => {
-                    result.squares[59] = result.squares[56];
-                    result.squares[56] = NONE;
-                }
-                6 => {
-                    result.squares[5] = result.squares[7];
-                    result.squares[7] = NONE;
-                }
-                2 => {
-                    result.squares[3] = result.squares[0];
-                    result.squares[0] = NONE;
-                }
-                _ => (),
-            }
+        if mv.is_castle() {
+            // King and rook may swap or stay in place in Chess960, so clear both squares first
+            let side = if to.file() > from.file() { CastleSide::King } else { CastleSide::Queen };
+            let rook = self.piece_at(to);
+            result.set_square(from, None);
+            result.set_square(to, None);
+            result.set_square(Square::new(side.king_file(), from.rank()), Some(moved_piece));
+            result.set_square(Square::new(side.rook_file(), from.rank()), rook);
+        } else {
+            let placed = match mv.promote_to() {
+                Some(kind) => Piece::new(kind, self.turn),
+                None => moved_piece,
+            };
+            result.set_square(to, Some(placed));
+            result.set_square(from, None);
         }
 
-        result.white_is_active = !result.white_is_active;
+        result.turn = self.turn.flip();
+        result.hash ^= result.castle_hash() ^ result.en_passant_hash() ^ zobrist::KEYS.black_to_move;
         result
     }
 }
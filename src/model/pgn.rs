@@ -0,0 +1,176 @@
+use crate::model::util::errors;
+use crate::model::Game;
+use std::fmt::{Display, Formatter};
+
+// A game read from PGN with its tag pairs, the moves are replayed into `game`
+#[derive(Clone, Debug)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub game: Game,
+    // NAGs and comments written after the move of the same ply, none when reading
+    pub annotations: Vec<String>,
+}
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+// Movetext lines are wrapped before this length
+const LINE_LENGTH: usize = 80;
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+
+    // Replaces the value of the tag or adds it at the end
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = String::from(value),
+            None => self.tags.push((String::from(name), String::from(value))),
+        }
+    }
+}
+
+impl From<Game> for PgnGame {
+    // The seven tag roster with unknown values, and the start position unless it is the standard one
+    fn from(game: Game) -> Self {
+        let mut pgn = PgnGame {
+            tags: Vec::new(),
+            game,
+            annotations: Vec::new(),
+        };
+        for name in ["Event", "Site", "Date", "Round", "White", "Black"] {
+            pgn.set_tag(name, "?");
+        }
+        pgn.set_tag("Result", pgn.game.result().score());
+        if pgn.game.start_fen() != Game::new().start_fen() {
+            pgn.set_tag("SetUp", "1");
+            pgn.set_tag("FEN", &pgn.game.start_fen());
+        }
+        pgn
+    }
+}
+
+// Tag pairs, movetext with the annotations wrapped into lines and the result from the Result tag
+impl Display for PgnGame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (name, value) in &self.tags {
+            writeln!(f, "[{name} \"{}\"]", value.replace('\\', "\\\\").replace('"', "\\\""))?;
+        }
+        writeln!(f)?;
+        let result = self.tag("Result").unwrap_or("*");
+        let mut tokens = Vec::new();
+        let (movetext, mut ply) = (self.game.movetext(), 0);
+        for token in movetext.split(' ').filter(|token| !token.is_empty()) {
+            tokens.push(token);
+            // move numbers end with '.', moves never do
+            if !token.ends_with('.') {
+                tokens.extend(self.annotations.get(ply).into_iter().flat_map(|annotation| annotation.split_whitespace()));
+                ply += 1;
+            }
+        }
+        let mut line = String::new();
+        for token in tokens.into_iter().chain([result]) {
+            if !line.is_empty() && line.len() + 1 + token.len() > LINE_LENGTH {
+                writeln!(f, "{line}")?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(token);
+        }
+        writeln!(f, "{line}")
+    }
+}
+
+// Reads all games of a PGN collection. Comments, variations and NAGs are skipped,
+// games starting from a position need a FEN tag
+pub fn parse_games(pgn: &str) -> std::io::Result<Vec<PgnGame>> {
+    let mut games = Vec::new();
+    let mut tags: Vec<(String, String)> = Vec::new();
+    let mut moves: Vec<String> = Vec::new();
+    let mut chars = pgn.chars().peekable();
+    while let Some(&symbol) = chars.peek() {
+        match symbol {
+            '[' => {
+                if !moves.is_empty() {
+                    games.push(replay(std::mem::take(&mut tags), std::mem::take(&mut moves))?);
+                }
+                chars.next();
+                let name: String = chars.by_ref().take_while(|&symbol| !symbol.is_whitespace()).collect();
+                if name.is_empty() || chars.by_ref().find(|&symbol| !symbol.is_whitespace()) != Some('"') {
+                    return Err(errors::invalid_input(format!("Unexpected tag: '[{name}'")));
+                }
+                // quotes and backslashes in the value are escaped with a backslash, a ']' in it is part of the value
+                let mut value = String::new();
+                while let Some(symbol) = chars.next() {
+                    match symbol {
+                        '\\' => value.extend(chars.next()),
+                        '"' => break,
+                        symbol => value.push(symbol),
+                    }
+                }
+                chars.by_ref().find(|&symbol| symbol == ']');
+                tags.push((name, value));
+            }
+            '{' => {
+                chars.by_ref().find(|&symbol| symbol == '}');
+            }
+            ';' => {
+                chars.by_ref().find(|&symbol| symbol == '\n');
+            }
+            '(' => {
+                let mut depth = 0;
+                for symbol in chars.by_ref() {
+                    match symbol {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => (),
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            symbol if symbol.is_whitespace() => {
+                chars.next();
+            }
+            _ => {
+                let mut token = String::new();
+                while let Some(&symbol) = chars.peek() {
+                    if symbol.is_whitespace() || "[{;(".contains(symbol) {
+                        break;
+                    }
+                    token.push(symbol);
+                    chars.next();
+                }
+                // move numbers may be glued to the move as in '1.e4'
+                let san = token.trim_start_matches(|symbol: char| symbol.is_ascii_digit() || symbol == '.');
+                if RESULTS.contains(&token.as_str()) {
+                    games.push(replay(std::mem::take(&mut tags), std::mem::take(&mut moves))?);
+                } else if !san.is_empty() && !token.starts_with('$') {
+                    moves.push(String::from(san));
+                }
+            }
+        }
+    }
+    if !moves.is_empty() || !tags.is_empty() {
+        games.push(replay(tags, moves)?);
+    }
+    Ok(games)
+}
+
+fn replay(tags: Vec<(String, String)>, moves: Vec<String>) -> std::io::Result<PgnGame> {
+    let fen = tags.iter().find(|(tag, _)| tag == "FEN");
+    let mut game = match fen {
+        Some((_, fen)) => Game::from_fen(fen)?,
+        None => Game::new(),
+    };
+    for san in &moves {
+        game.make_san_move(san)?;
+    }
+    Ok(PgnGame {
+        tags,
+        game,
+        annotations: Vec::new(),
+    })
+}
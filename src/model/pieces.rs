@@ -1,109 +1,150 @@
-pub const NONE: u8 = 0;
-pub const KING: u8 = 1;
-pub const PAWN: u8 = 2;
-pub const KNIGHT: u8 = 3;
-pub const BISHOP: u8 = 4;
-pub const ROOK: u8 = 5;
-pub const QUEEN: u8 = 6;
+use crate::model::util::errors;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
-pub const WHITE: u8 = 8;
-pub const BLACK: u8 = 16;
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Color {
+    White,
+    Black,
+}
 
-pub trait Piece {
-    fn is_color(&self, color: u8) -> bool;
-    fn is_piece(&self, kind: u8) -> bool;
-    fn notation(&self) -> char;
-    fn without_color(&self) -> u8;
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub enum PieceKind {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
 
-    fn is(&self, kind: u8, color: u8) -> bool {
-        self.is_color(color) && self.is_piece(kind)
-    }
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Piece {
+    pub kind: PieceKind,
+    pub color: Color,
+}
 
-    fn is_king(&self) -> bool {
-        self.is_piece(KING)
-    }
-    fn is_pawn(&self) -> bool {
-        self.is_piece(PAWN)
+impl Color {
+    pub const ALL: [Color; 2] = [Color::White, Color::Black];
+
+    pub fn flip(self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
     }
-    fn is_knight(&self) -> bool {
-        self.is_piece(KNIGHT)
+
+    pub fn index(self) -> usize {
+        self as usize
     }
-    fn is_bishop(&self) -> bool {
-        self.is_piece(BISHOP)
+}
+
+impl PieceKind {
+    pub const ALL: [PieceKind; 6] = [PieceKind::Pawn, PieceKind::Knight, PieceKind::Bishop, PieceKind::Rook, PieceKind::Queen, PieceKind::King];
+    pub const PROMOTIONS: [PieceKind; 4] = [PieceKind::Queen, PieceKind::Rook, PieceKind::Knight, PieceKind::Bishop];
+
+    pub fn index(self) -> usize {
+        self as usize
     }
-    fn is_rook(&self) -> bool {
-        self.is_piece(ROOK)
+
+    // Lowercase letter as used in FEN and UCI promotions
+    pub fn char(self) -> char {
+        match self {
+            PieceKind::Pawn => 'p',
+            PieceKind::Knight => 'n',
+            PieceKind::Bishop => 'b',
+            PieceKind::Rook => 'r',
+            PieceKind::Queen => 'q',
+            PieceKind::King => 'k',
+        }
     }
-    fn is_queen(&self) -> bool {
-        self.is_piece(QUEEN)
+
+    pub fn from_char(symbol: char) -> Option<PieceKind> {
+        PieceKind::ALL.into_iter().find(|kind| kind.char() == symbol.to_ascii_lowercase())
     }
 }
 
-impl Piece for u8 {
-    fn is_color(&self, color: u8) -> bool {
-        (self & color) > 0
+impl Piece {
+    pub fn new(kind: PieceKind, color: Color) -> Piece {
+        Piece { kind, color }
     }
 
-    fn is_piece(&self, piece: u8) -> bool {
-        (self & 0b00000111) == piece
+    // 0..12, white pieces first
+    pub fn index(self) -> usize {
+        self.color.index() * 6 + self.kind.index()
     }
 
-    fn notation(&self) -> char {
-        let mut result = '-';
-        if self.is_king() {
-            result = 'k';
-        }
-        if self.is_queen() {
-            result = 'q';
-        }
-        if self.is_knight() {
-            result = 'n';
-        }
-        if self.is_rook() {
-            result = 'r';
-        }
-        if self.is_pawn() {
-            result = 'p';
-        }
-        if self.is_bishop() {
-            result = 'b';
-        }
-        if self.is_color(WHITE) {
-            result.to_ascii_uppercase()
-        } else {
-            result
+    // FEN letter, uppercase for white
+    pub fn char(self) -> char {
+        match self.color {
+            Color::White => self.kind.char().to_ascii_uppercase(),
+            Color::Black => self.kind.char(),
         }
     }
 
-    fn without_color(&self) -> u8 {
-        self & 0b00000111
+    pub fn from_char(symbol: char) -> Option<Piece> {
+        let color = if symbol.is_ascii_uppercase() { Color::White } else { Color::Black };
+        PieceKind::from_char(symbol).map(|kind| Piece::new(kind, color))
     }
-}
 
-pub fn new(kind: u8, color: u8) -> u8 {
-    kind | color
+    pub fn is(self, kind: PieceKind, color: Color) -> bool {
+        self.kind == kind && self.color == color
+    }
 }
 
-pub fn pawn(color: u8) -> u8 {
-    new(PAWN, color)
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Color::White => "w",
+            Color::Black => "b",
+        })
+    }
 }
 
-pub fn rook(color: u8) -> u8 {
-    new(ROOK, color)
+impl Display for PieceKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.char())
+    }
 }
 
-pub fn knight(color: u8) -> u8 {
-    new(KNIGHT, color)
+impl Display for Piece {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.char())
+    }
 }
 
-pub fn bishop(color: u8) -> u8 {
-    new(BISHOP, color)
+impl FromStr for Color {
+    type Err = std::io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "w" => Ok(Color::White),
+            "b" => Ok(Color::Black),
+            other => Err(errors::invalid_input(format!("Unexpected color: '{other}'"))),
+        }
+    }
 }
 
-pub fn quieen(color: u8) -> u8 {
-    new(QUEEN, color)
+impl FromStr for PieceKind {
+    type Err = std::io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next().and_then(PieceKind::from_char), chars.next()) {
+            (Some(kind), None) => Ok(kind),
+            _ => Err(errors::invalid_input(format!("Unexpected piece kind: '{s}'"))),
+        }
+    }
 }
 
-pub fn king(color: u8) -> u8 {
-    new(KING, color)
+impl FromStr for Piece {
+    type Err = std::io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next().and_then(Piece::from_char), chars.next()) {
+            (Some(piece), None) => Ok(piece),
+            _ => Err(errors::invalid_input(format!("Unexpected piece: '{s}'"))),
+        }
+    }
 }
@@ -0,0 +1,94 @@
+use crate::model::util::*;
+use crate::model::{Board, Move, PieceKind, Square};
+
+impl Board {
+    // Standard algebraic notation of a legal move, like 'Nbd2', 'exd6', 'e8=Q+' or 'O-O-O#'
+    pub fn to_san(&self, mv: &Move) -> String {
+        let next = self.make_move(mv);
+        let suffix = match (next.is_check(), next.gen_moves(false).is_empty()) {
+            (true, true) => "#",
+            (true, false) => "+",
+            _ => "",
+        };
+        format!("{}{suffix}", self.san_without_suffix(mv))
+    }
+
+    // SAN of moves played one after the other from this board, like a PV
+    pub fn san_line(&self, moves: &[Move]) -> Vec<String> {
+        let mut board = *self;
+        let mut line = Vec::new();
+        for mv in moves {
+            line.push(board.to_san(mv));
+            board = board.make_move(mv);
+        }
+        line
+    }
+
+    // Finds the legal move for a SAN string. Check marks, annotations and a missing '=' are tolerated
+    pub fn parse_san(&self, san: &str) -> std::io::Result<Move> {
+        let normalize = |san: &str| san.trim().trim_end_matches(['+', '#', '!', '?']).replace('=', "").replace('0', "O");
+        let wanted = normalize(san);
+        self.gen_moves(false)
+            .into_iter()
+            .find(|mv| normalize(&self.san_without_suffix(mv)) == wanted)
+            .or_else(|| self.find_disambiguated(&wanted))
+            .ok_or(errors::invalid_input(format!("Illegal or ambiguous move: '{san}'")))
+    }
+
+    // Piece moves with more disambiguation than needed, like 'Ngf3' or 'Ng1f3'. The hint only has to fit one piece
+    fn find_disambiguated(&self, san: &str) -> Option<Move> {
+        let kind = PieceKind::from_char(san.chars().next().filter(char::is_ascii_uppercase)?)?;
+        let to: Square = san.get(san.len().checked_sub(2)?..)?.parse().ok()?;
+        let hint = san.get(1..san.len() - 2)?;
+        let (hint, capture) = hint.strip_suffix('x').map_or((hint, false), |hint| (hint, true));
+        if kind == PieceKind::Pawn || hint.len() > 2 {
+            return None;
+        }
+        let fits = |from: Square| {
+            hint.bytes().all(|hint| match hint {
+                b'a'..=b'h' => from.file() == hint - b'a',
+                b'1'..=b'8' => from.rank() == hint - b'1',
+                _ => false,
+            })
+        };
+        let mut candidates = self.gen_moves(false).into_iter().filter(|mv| {
+            let piece = self.piece_at(mv.from()).map(|piece| piece.kind);
+            mv.to() == to && !mv.is_castle() && piece == Some(kind) && mv.is_capture() == capture && fits(mv.from())
+        });
+        let mv = candidates.next()?;
+        candidates.next().is_none().then_some(mv)
+    }
+
+    fn san_without_suffix(&self, mv: &Move) -> String {
+        let (from_square, to) = (mv.from(), mv.to());
+        let piece = self.piece_at(from_square).expect("No piece to move");
+        if mv.is_castle() {
+            return String::from(if to > from_square { "O-O" } else { "O-O-O" });
+        }
+        let from = from_square.to_string();
+        let takes = if mv.is_capture() { "x" } else { "" };
+        if piece.kind == PieceKind::Pawn {
+            let file = if mv.is_capture() { &from[..1] } else { "" };
+            let promotion = mv.promote_to().map_or(String::new(), |kind| format!("={}", kind.char().to_ascii_uppercase()));
+            return format!("{file}{takes}{to}{promotion}");
+        }
+
+        // pieces of the same kind that can also go to the target square
+        let others: Vec<_> = self
+            .gen_moves(false)
+            .into_iter()
+            .filter(|other| other.to() == to && other.from() != from_square && self.piece_at(other.from()) == Some(piece))
+            .map(|other| other.from())
+            .collect();
+        let disambiguation = if others.is_empty() {
+            ""
+        } else if others.iter().all(|other| other.file() != from_square.file()) {
+            &from[..1]
+        } else if others.iter().all(|other| other.rank() != from_square.rank()) {
+            &from[1..]
+        } else {
+            &from
+        };
+        format!("{}{disambiguation}{takes}{to}", piece.kind.char().to_ascii_uppercase())
+    }
+}
@@ -0,0 +1,80 @@
+use crate::model::util::errors;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// Board square, index 0 is a8 and 63 is h1 like the board array
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Square(u8);
+
+impl Square {
+    pub const A1: Square = Square(56);
+    pub const B1: Square = Square(57);
+    pub const C1: Square = Square(58);
+    pub const D1: Square = Square(59);
+    pub const E1: Square = Square(60);
+    pub const F1: Square = Square(61);
+    pub const G1: Square = Square(62);
+    pub const H1: Square = Square(63);
+    pub const A8: Square = Square(0);
+    pub const B8: Square = Square(1);
+    pub const C8: Square = Square(2);
+    pub const D8: Square = Square(3);
+    pub const E8: Square = Square(4);
+    pub const F8: Square = Square(5);
+    pub const G8: Square = Square(6);
+    pub const H8: Square = Square(7);
+
+    // file 0 is 'a', rank 0 is '1'
+    pub fn new(file: u8, rank: u8) -> Square {
+        assert!(file < 8 && rank < 8, "Square out of the board: {file}, {rank}");
+        Square((7 - rank) * 8 + file)
+    }
+
+    pub fn from_index(index: usize) -> Square {
+        assert!(index < 64, "Square index must be i < 64, but got {index}");
+        Square(index as u8)
+    }
+
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..64).map(Square)
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn file(self) -> u8 {
+        self.0 % 8
+    }
+
+    pub fn rank(self) -> u8 {
+        7 - self.0 / 8
+    }
+
+    // Mirrors the square vertically, a1 becomes a8
+    pub fn flip(self) -> Square {
+        Square(self.0 ^ 56)
+    }
+
+    // Square `diff` indexes away, callers make sure it is still on the board
+    pub fn offset(self, diff: i8) -> Square {
+        Square::from_index((self.0 as i8 + diff) as usize)
+    }
+}
+
+impl Display for Square {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", (b'a' + self.file()) as char, self.rank() + 1)
+    }
+}
+
+impl FromStr for Square {
+    type Err = std::io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            [file @ b'a'..=b'h', rank @ b'1'..=b'8'] => Ok(Square::new(file - b'a', rank - b'1')),
+            _ => Err(errors::invalid_input(format!("Unexpected square: '{s}'"))),
+        }
+    }
+}
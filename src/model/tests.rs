@@ -4,119 +4,120 @@ use crate::{engine::Engine, model::*, uci};
 #[test]
 fn construct_start_board() {
     let start_board = Board::new();
-    assert_eq!(pieces::new(ROOK, BLACK), start_board.squares[0]);
-    assert_eq!(pieces::new(KNIGHT, BLACK), start_board.squares[1]);
-    assert_eq!(pieces::new(BISHOP, BLACK), start_board.squares[2]);
-    assert_eq!(pieces::new(QUEEN, BLACK), start_board.squares[3]);
-    assert_eq!(pieces::new(KING, BLACK), start_board.squares[4]);
-    assert_eq!(pieces::new(BISHOP, BLACK), start_board.squares[5]);
-    assert_eq!(pieces::new(KNIGHT, BLACK), start_board.squares[6]);
-    assert_eq!(pieces::new(ROOK, BLACK), start_board.squares[7]);
+    assert_eq!(Some(Piece::new(PieceKind::Rook, Color::Black)), start_board.squares[0]);
+    assert_eq!(Some(Piece::new(PieceKind::Knight, Color::Black)), start_board.squares[1]);
+    assert_eq!(Some(Piece::new(PieceKind::Bishop, Color::Black)), start_board.squares[2]);
+    assert_eq!(Some(Piece::new(PieceKind::Queen, Color::Black)), start_board.squares[3]);
+    assert_eq!(Some(Piece::new(PieceKind::King, Color::Black)), start_board.squares[4]);
+    assert_eq!(Some(Piece::new(PieceKind::Bishop, Color::Black)), start_board.squares[5]);
+    assert_eq!(Some(Piece::new(PieceKind::Knight, Color::Black)), start_board.squares[6]);
+    assert_eq!(Some(Piece::new(PieceKind::Rook, Color::Black)), start_board.squares[7]);
     for i in 8..15 {
-        assert_eq!(pieces::new(PAWN, BLACK), start_board.squares[i]);
+        assert_eq!(Some(Piece::new(PieceKind::Pawn, Color::Black)), start_board.squares[i]);
     }
     for i in 16..47 {
-        assert_eq!(pieces::NONE, start_board.squares[i]);
+        assert_eq!(None, start_board.squares[i]);
     }
     for i in 48..55 {
-        assert_eq!(pieces::new(PAWN, WHITE), start_board.squares[i]);
+        assert_eq!(Some(Piece::new(PieceKind::Pawn, Color::White)), start_board.squares[i]);
     }
-    assert_eq!(pieces::new(ROOK, WHITE), start_board.squares[56]);
-    assert_eq!(pieces::new(KNIGHT, WHITE), start_board.squares[57]);
-    assert_eq!(pieces::new(BISHOP, WHITE), start_board.squares[58]);
-    assert_eq!(pieces::new(QUEEN, WHITE), start_board.squares[59]);
-    assert_eq!(pieces::new(KING, WHITE), start_board.squares[60]);
-    assert_eq!(pieces::new(BISHOP, WHITE), start_board.squares[61]);
-    assert_eq!(pieces::new(KNIGHT, WHITE), start_board.squares[62]);
-    assert_eq!(pieces::new(ROOK, WHITE), start_board.squares[63]);
+    assert_eq!(Some(Piece::new(PieceKind::Rook, Color::White)), start_board.squares[56]);
+    assert_eq!(Some(Piece::new(PieceKind::Knight, Color::White)), start_board.squares[57]);
+    assert_eq!(Some(Piece::new(PieceKind::Bishop, Color::White)), start_board.squares[58]);
+    assert_eq!(Some(Piece::new(PieceKind::Queen, Color::White)), start_board.squares[59]);
+    assert_eq!(Some(Piece::new(PieceKind::King, Color::White)), start_board.squares[60]);
+    assert_eq!(Some(Piece::new(PieceKind::Bishop, Color::White)), start_board.squares[61]);
+    assert_eq!(Some(Piece::new(PieceKind::Knight, Color::White)), start_board.squares[62]);
+    assert_eq!(Some(Piece::new(PieceKind::Rook, Color::White)), start_board.squares[63]);
 
-    assert!(start_board.white_is_active);
-    assert!(start_board.castle_white_king);
-    assert!(start_board.castle_white_queen);
-    assert!(start_board.castle_black_king);
-    assert!(start_board.castle_black_queen);
+    assert_eq!(Color::White, start_board.turn);
+    assert_eq!(Some(Square::H1), start_board.castling_rook(Color::White, CastleSide::King));
+    assert_eq!(Some(Square::A1), start_board.castling_rook(Color::White, CastleSide::Queen));
+    assert_eq!(Some(Square::H8), start_board.castling_rook(Color::Black, CastleSide::King));
+    assert_eq!(Some(Square::A8), start_board.castling_rook(Color::Black, CastleSide::Queen));
+    assert!(!start_board.chess960);
 }
 
 #[test]
 fn notation_to_index() {
-    assert_eq!(0, util::square_notation_to_index("a8").unwrap());
-    assert_eq!(1, util::square_notation_to_index("b8").unwrap());
-    assert_eq!(2, util::square_notation_to_index("c8").unwrap());
-    assert_eq!(3, util::square_notation_to_index("d8").unwrap());
-    assert_eq!(4, util::square_notation_to_index("e8").unwrap());
-    assert_eq!(5, util::square_notation_to_index("f8").unwrap());
-    assert_eq!(6, util::square_notation_to_index("g8").unwrap());
-    assert_eq!(7, util::square_notation_to_index("h8").unwrap());
-
-    assert_eq!(8, util::square_notation_to_index("a7").unwrap());
-    assert_eq!(9, util::square_notation_to_index("b7").unwrap());
-    assert_eq!(10, util::square_notation_to_index("c7").unwrap());
-    assert_eq!(11, util::square_notation_to_index("d7").unwrap());
-    assert_eq!(12, util::square_notation_to_index("e7").unwrap());
-    assert_eq!(13, util::square_notation_to_index("f7").unwrap());
-    assert_eq!(14, util::square_notation_to_index("g7").unwrap());
-    assert_eq!(15, util::square_notation_to_index("h7").unwrap());
-
-    assert_eq!(16, util::square_notation_to_index("a6").unwrap());
-    assert_eq!(17, util::square_notation_to_index("b6").unwrap());
-    assert_eq!(18, util::square_notation_to_index("c6").unwrap());
-    assert_eq!(19, util::square_notation_to_index("d6").unwrap());
-    assert_eq!(20, util::square_notation_to_index("e6").unwrap());
-    assert_eq!(21, util::square_notation_to_index("f6").unwrap());
-    assert_eq!(22, util::square_notation_to_index("g6").unwrap());
-    assert_eq!(23, util::square_notation_to_index("h6").unwrap());
-
-    assert_eq!(24, util::square_notation_to_index("a5").unwrap());
-    assert_eq!(25, util::square_notation_to_index("b5").unwrap());
-    assert_eq!(26, util::square_notation_to_index("c5").unwrap());
-    assert_eq!(27, util::square_notation_to_index("d5").unwrap());
-    assert_eq!(28, util::square_notation_to_index("e5").unwrap());
-    assert_eq!(29, util::square_notation_to_index("f5").unwrap());
-    assert_eq!(30, util::square_notation_to_index("g5").unwrap());
-    assert_eq!(31, util::square_notation_to_index("h5").unwrap());
-
-    assert_eq!(32, util::square_notation_to_index("a4").unwrap());
-    assert_eq!(33, util::square_notation_to_index("b4").unwrap());
-    assert_eq!(34, util::square_notation_to_index("c4").unwrap());
-    assert_eq!(35, util::square_notation_to_index("d4").unwrap());
-    assert_eq!(36, util::square_notation_to_index("e4").unwrap());
-    assert_eq!(37, util::square_notation_to_index("f4").unwrap());
-    assert_eq!(38, util::square_notation_to_index("g4").unwrap());
-    assert_eq!(39, util::square_notation_to_index("h4").unwrap());
-
-    assert_eq!(40, util::square_notation_to_index("a3").unwrap());
-    assert_eq!(41, util::square_notation_to_index("b3").unwrap());
-    assert_eq!(42, util::square_notation_to_index("c3").unwrap());
-    assert_eq!(43, util::square_notation_to_index("d3").unwrap());
-    assert_eq!(44, util::square_notation_to_index("e3").unwrap());
-    assert_eq!(45, util::square_notation_to_index("f3").unwrap());
-    assert_eq!(46, util::square_notation_to_index("g3").unwrap());
-    assert_eq!(47, util::square_notation_to_index("h3").unwrap());
-
-    assert_eq!(48, util::square_notation_to_index("a2").unwrap());
-    assert_eq!(49, util::square_notation_to_index("b2").unwrap());
-    assert_eq!(50, util::square_notation_to_index("c2").unwrap());
-    assert_eq!(51, util::square_notation_to_index("d2").unwrap());
-    assert_eq!(52, util::square_notation_to_index("e2").unwrap());
-    assert_eq!(53, util::square_notation_to_index("f2").unwrap());
-    assert_eq!(54, util::square_notation_to_index("g2").unwrap());
-    assert_eq!(55, util::square_notation_to_index("h2").unwrap());
-
-    assert_eq!(56, util::square_notation_to_index("a1").unwrap());
-    assert_eq!(57, util::square_notation_to_index("b1").unwrap());
-    assert_eq!(58, util::square_notation_to_index("c1").unwrap());
-    assert_eq!(59, util::square_notation_to_index("d1").unwrap());
-    assert_eq!(60, util::square_notation_to_index("e1").unwrap());
-    assert_eq!(61, util::square_notation_to_index("f1").unwrap());
-    assert_eq!(62, util::square_notation_to_index("g1").unwrap());
-    assert_eq!(63, util::square_notation_to_index("h1").unwrap());
+    assert_eq!(0, "a8".parse::<Square>().unwrap().index());
+    assert_eq!(1, "b8".parse::<Square>().unwrap().index());
+    assert_eq!(2, "c8".parse::<Square>().unwrap().index());
+    assert_eq!(3, "d8".parse::<Square>().unwrap().index());
+    assert_eq!(4, "e8".parse::<Square>().unwrap().index());
+    assert_eq!(5, "f8".parse::<Square>().unwrap().index());
+    assert_eq!(6, "g8".parse::<Square>().unwrap().index());
+    assert_eq!(7, "h8".parse::<Square>().unwrap().index());
+
+    assert_eq!(8, "a7".parse::<Square>().unwrap().index());
+    assert_eq!(9, "b7".parse::<Square>().unwrap().index());
+    assert_eq!(10, "c7".parse::<Square>().unwrap().index());
+    assert_eq!(11, "d7".parse::<Square>().unwrap().index());
+    assert_eq!(12, "e7".parse::<Square>().unwrap().index());
+    assert_eq!(13, "f7".parse::<Square>().unwrap().index());
+    assert_eq!(14, "g7".parse::<Square>().unwrap().index());
+    assert_eq!(15, "h7".parse::<Square>().unwrap().index());
+
+    assert_eq!(16, "a6".parse::<Square>().unwrap().index());
+    assert_eq!(17, "b6".parse::<Square>().unwrap().index());
+    assert_eq!(18, "c6".parse::<Square>().unwrap().index());
+    assert_eq!(19, "d6".parse::<Square>().unwrap().index());
+    assert_eq!(20, "e6".parse::<Square>().unwrap().index());
+    assert_eq!(21, "f6".parse::<Square>().unwrap().index());
+    assert_eq!(22, "g6".parse::<Square>().unwrap().index());
+    assert_eq!(23, "h6".parse::<Square>().unwrap().index());
+
+    assert_eq!(24, "a5".parse::<Square>().unwrap().index());
+    assert_eq!(25, "b5".parse::<Square>().unwrap().index());
+    assert_eq!(26, "c5".parse::<Square>().unwrap().index());
+    assert_eq!(27, "d5".parse::<Square>().unwrap().index());
+    assert_eq!(28, "e5".parse::<Square>().unwrap().index());
+    assert_eq!(29, "f5".parse::<Square>().unwrap().index());
+    assert_eq!(30, "g5".parse::<Square>().unwrap().index());
+    assert_eq!(31, "h5".parse::<Square>().unwrap().index());
+
+    assert_eq!(32, "a4".parse::<Square>().unwrap().index());
+    assert_eq!(33, "b4".parse::<Square>().unwrap().index());
+    assert_eq!(34, "c4".parse::<Square>().unwrap().index());
+    assert_eq!(35, "d4".parse::<Square>().unwrap().index());
+    assert_eq!(36, "e4".parse::<Square>().unwrap().index());
+    assert_eq!(37, "f4".parse::<Square>().unwrap().index());
+    assert_eq!(38, "g4".parse::<Square>().unwrap().index());
+    assert_eq!(39, "h4".parse::<Square>().unwrap().index());
+
+    assert_eq!(40, "a3".parse::<Square>().unwrap().index());
+    assert_eq!(41, "b3".parse::<Square>().unwrap().index());
+    assert_eq!(42, "c3".parse::<Square>().unwrap().index());
+    assert_eq!(43, "d3".parse::<Square>().unwrap().index());
+    assert_eq!(44, "e3".parse::<Square>().unwrap().index());
+    assert_eq!(45, "f3".parse::<Square>().unwrap().index());
+    assert_eq!(46, "g3".parse::<Square>().unwrap().index());
+    assert_eq!(47, "h3".parse::<Square>().unwrap().index());
+
+    assert_eq!(48, "a2".parse::<Square>().unwrap().index());
+    assert_eq!(49, "b2".parse::<Square>().unwrap().index());
+    assert_eq!(50, "c2".parse::<Square>().unwrap().index());
+    assert_eq!(51, "d2".parse::<Square>().unwrap().index());
+    assert_eq!(52, "e2".parse::<Square>().unwrap().index());
+    assert_eq!(53, "f2".parse::<Square>().unwrap().index());
+    assert_eq!(54, "g2".parse::<Square>().unwrap().index());
+    assert_eq!(55, "h2".parse::<Square>().unwrap().index());
+
+    assert_eq!(56, "a1".parse::<Square>().unwrap().index());
+    assert_eq!(57, "b1".parse::<Square>().unwrap().index());
+    assert_eq!(58, "c1".parse::<Square>().unwrap().index());
+    assert_eq!(59, "d1".parse::<Square>().unwrap().index());
+    assert_eq!(60, "e1".parse::<Square>().unwrap().index());
+    assert_eq!(61, "f1".parse::<Square>().unwrap().index());
+    assert_eq!(62, "g1".parse::<Square>().unwrap().index());
+    assert_eq!(63, "h1".parse::<Square>().unwrap().index());
 }
 
 #[test]
 fn index_to_notation() {
     for i in 0..63 {
-        let notation = util::index_to_square_notation(i).unwrap();
-        let reversed = util::square_notation_to_index(&notation[..]).unwrap();
+        let notation = Square::from_index(i).to_string();
+        let reversed = notation.parse::<Square>().unwrap().index();
         assert_eq!(i, reversed);
     }
 }
@@ -126,8 +127,289 @@ fn disable_castle() {
     let mut engine = Engine::new();
     let command = uci::Command::parse("position startpos moves g1h3 g7g5 e2e3 f7f5 f1d3 g5g4 e1g1 d7d6 d3a6 c7c6 g2g3 e8f7 b1c3 b8a6 g1g2 g8f6 d1g4 c8e6 g4e2 e6b3 a2a3 f6e8 h3g5 f7f6 g5f7 d8b6 f7g5").unwrap();
     engine.execute_uci(command).unwrap();
-    assert!(!engine.board.castle_white_king);
-    assert!(!engine.board.castle_white_queen);
-    assert!(!engine.board.castle_black_king);
-    assert!(!engine.board.castle_black_queen);
+    assert!(engine.board().castling.is_empty());
+}
+
+#[test]
+fn incremental_hash() {
+    let mut engine = Engine::new();
+    let command = uci::Command::parse("position startpos moves e2e4 d7d5 e4d5 c7c5 d5c6 b8c6 g1f3 g8f6 f1c4 e7e5 e1g1 e8e7 a2a4 d8d2").unwrap();
+    engine.execute_uci(command).unwrap();
+    assert_eq!(engine.board().compute_hash(), engine.board().hash);
+
+    let mut transposed = Engine::new();
+    let command = uci::Command::parse("position startpos moves g1f3 g8f6 b1c3 b8c6 c3b1 c6b8 f3g1 f6g8").unwrap();
+    transposed.execute_uci(command).unwrap();
+    assert_eq!(Board::new().hash, transposed.board().hash);
+}
+
+#[test]
+fn san_notation() {
+    let board = Board::from_fen(String::from("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")).unwrap();
+    let san = |from: &str, to: &str| {
+        let mv = board.gen_moves(false).into_iter().find(|mv| {
+            mv.from() == from.parse().unwrap() && mv.uci_to() == to.parse().unwrap()
+        });
+        board.to_san(&mv.unwrap())
+    };
+    assert_eq!("O-O", san("e1", "g1"));
+    assert_eq!("O-O-O", san("e1", "c1"));
+    assert_eq!("dxe6", san("d5", "e6"));
+    assert_eq!("Nxf7", san("e5", "f7"));
+    assert_eq!("Qxf6", san("f3", "f6"));
+    assert_eq!("Bxa6", san("e2", "a6"));
+    assert_eq!("gxh3", san("g2", "h3"));
+
+    let mate = Board::from_fen(String::from("k7/6R1/8/8/8/8/8/6KR w - - 0 1")).unwrap();
+    assert_eq!("Rh8#", mate.to_san(&Move::regular(Square::H1, Square::H8)));
+    assert_eq!("Rg8+", mate.to_san(&Move::regular("g7".parse().unwrap(), Square::G8)));
+}
+
+#[test]
+fn san_disambiguation_and_promotion() {
+    let board = Board::from_fen(String::from("8/1P6/6k1/8/8/8/4K3/R6R w - - 0 1")).unwrap();
+    assert_eq!("Rad1", board.to_san(&board.parse_san("Rad1").unwrap()));
+    assert_eq!("Rhd1", board.to_san(&board.parse_san("Rhd1").unwrap()));
+    assert_eq!("b8=Q", board.to_san(&board.parse_san("b8Q").unwrap()));
+    assert_eq!(Some(PieceKind::Knight), board.parse_san("b8=N").unwrap().promote_to());
+    assert!(board.parse_san("Rd1").is_err());
+    assert!(board.parse_san("Qd1").is_err());
+    // more disambiguation than needed is accepted as long as it fits the moving piece
+    assert_eq!("Ra2", board.to_san(&board.parse_san("Raa2").unwrap()));
+    assert_eq!("Rad1", board.to_san(&board.parse_san("Ra1d1").unwrap()));
+    assert_eq!("Kd3", board.to_san(&board.parse_san("Ke2d3").unwrap()));
+    assert!(board.parse_san("Rbd1").is_err());
+    assert!(board.parse_san("R1d1").is_err());
+    let knights = Board::new();
+    assert_eq!("Nf3", knights.to_san(&knights.parse_san("Ngf3").unwrap()));
+    assert!(knights.parse_san("Nbf3").is_err());
+    assert!(knights.parse_san("Ngxf3").is_err());
+
+    let castling = Board::from_fen(String::from("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")).unwrap();
+    assert_eq!(Move::castle(Square::E1, Square::H1), castling.parse_san("0-0").unwrap());
+    assert_eq!(Move::castle(Square::E1, Square::A1), castling.parse_san("O-O-O").unwrap());
+}
+
+#[test]
+fn typed_squares_and_pieces() {
+    let e4: Square = "e4".parse().unwrap();
+    assert_eq!((4, 3), (e4.file(), e4.rank()));
+    assert_eq!("e5", e4.flip().to_string());
+    assert_eq!(Square::new(0, 0), Square::A1);
+    assert!("i1".parse::<Square>().is_err());
+    assert!("a0".parse::<Square>().is_err());
+
+    assert_eq!(Color::Black, Color::White.flip());
+    assert_eq!("b", Color::Black.to_string());
+    assert_eq!(Piece::new(PieceKind::Knight, Color::White), "N".parse().unwrap());
+    assert_eq!("q", Piece::new(PieceKind::Queen, Color::Black).to_string());
+    assert_eq!(PieceKind::Knight, "n".parse().unwrap());
+    assert!("x".parse::<Piece>().is_err());
+}
+
+#[test]
+fn knight_promotion_notation() {
+    let mv = Move::promotion("b7".parse().unwrap(), Square::B8, PieceKind::Knight);
+    assert_eq!("b7b8n", mv.to_notation());
+    assert_eq!(mv, "b7b8n".parse().unwrap());
+}
+
+#[test]
+fn move_encoding() {
+    let promotion = Move::promotion("b7".parse().unwrap(), "a8".parse().unwrap(), PieceKind::Rook).with_capture();
+    assert_eq!(2, std::mem::size_of::<Move>());
+    assert_eq!(promotion, Move::from_raw(promotion.raw()));
+    assert_eq!(Some(PieceKind::Rook), promotion.promote_to());
+    assert!(promotion.is_capture() && !promotion.is_castle() && !promotion.is_en_passant());
+    assert_eq!("b7a8r", promotion.to_notation());
+
+    let board = Board::from_fen(String::from("4k3/8/8/3pP3/8/8/8/R3K3 w Q d6 0 1")).unwrap();
+    let en_passant = board.find_move(&"e5d6".parse().unwrap()).unwrap();
+    assert!(en_passant.is_en_passant() && en_passant.is_capture());
+    assert!(board.find_move(&"e1c1".parse().unwrap()).unwrap().is_castle());
+    assert_eq!(vec![en_passant], board.gen_moves(true).to_vec());
+}
+
+#[test]
+fn chess960_castling_fen() {
+    let board = Board::from_fen(String::from("bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w GEge - 0 1")).unwrap();
+    assert!(board.chess960);
+    assert_eq!(Some(Square::G1), board.castling_rook(Color::White, CastleSide::King));
+    assert_eq!(Some(Square::E1), board.castling_rook(Color::White, CastleSide::Queen));
+    assert_eq!("bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w KQkq - 0 0", board.to_fen());
+    assert_eq!("bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w GEge - 0 0", board.to_shredder_fen());
+
+    // X-FEN names the inner rook by its file
+    let inner = Board::from_fen(String::from("4k3/8/8/8/8/8/8/1R2K1RR w G - 0 1")).unwrap();
+    assert_eq!(Some(6), inner.castling.rook_file(Color::White, CastleSide::King));
+    assert_eq!("4k3/8/8/8/8/8/8/1R2K1RR w G - 0 0", inner.to_fen());
+    assert!(Board::from_fen(String::from("4k3/8/8/8/8/8/8/4K3 w K - 0 1")).is_err());
+    assert_eq!("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 0", Board::new().to_shredder_fen());
+}
+
+#[test]
+fn chess960_start_positions() {
+    assert_eq!(Board::new().squares, Board::chess960(chess960::STANDARD_POSITION).unwrap().squares);
+    assert_eq!("bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 0", Board::chess960(0).unwrap().to_fen());
+    assert_eq!("rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 0", Board::chess960(959).unwrap().to_fen());
+    assert!(Board::chess960(960).is_err());
+    let distinct: std::collections::HashSet<_> = (0..960).map(|index| chess960::back_rank(index).unwrap()).collect();
+    assert_eq!(960, distinct.len());
+}
+
+#[test]
+fn chess960_castle_notation() {
+    // the king on b1 castles queen side by taking the rook on a1 and ends on c1
+    let mut board = Board::from_fen(String::from("4k3/8/8/8/8/8/8/RK6 w A - 0 1")).unwrap();
+    let castle = board.find_move(&"b1a1".parse().unwrap()).unwrap();
+    assert!(castle.is_castle());
+    assert_eq!("b1a1", castle.to_notation());
+    assert_eq!("O-O-O", board.to_san(&castle));
+    let next = board.make_move(&castle);
+    assert_eq!(Some(Piece::new(PieceKind::King, Color::White)), next.piece_at(Square::C1));
+    assert_eq!(Some(Piece::new(PieceKind::Rook, Color::White)), next.piece_at(Square::D1));
+    assert!(next.castling.is_empty());
+    assert_eq!(next.compute_hash(), next.hash);
+
+    // without UCI_Chess960 the same castle is written with the king destination
+    board.chess960 = false;
+    assert_eq!("b1c1", board.find_move(&"b1c1".parse().unwrap()).unwrap().to_notation());
+
+    let attacked = Board::from_fen(String::from("2r1k3/8/8/8/8/8/8/RK6 w A - 0 1")).unwrap();
+    assert!(attacked.find_move(&"b1a1".parse().unwrap()).is_none());
+}
+
+#[test]
+fn game_history_and_results() {
+    let mut game = Game::new();
+    for san in ["f3", "e5", "g4"] {
+        game.make_san_move(san).unwrap();
+    }
+    assert_eq!(GameResult::Ongoing, game.result());
+    game.make_move(&"d8h4".parse().unwrap()).unwrap();
+    assert_eq!(GameResult::Checkmate { winner: Color::Black }, game.result());
+    assert_eq!("0-1", game.result().score());
+    assert_eq!("1. f3 e5 2. g4 Qh4#", game.movetext());
+    assert_eq!("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3", game.to_fen());
+
+    assert_eq!(Some("d8h4".parse().unwrap()), game.takeback());
+    assert_eq!(3, game.moves().len());
+    assert!(game.make_move(&"e1e2".parse().unwrap()).is_err());
+    assert_eq!(4, game.hashes().count());
+
+    let stalemate = Game::from_fen("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1").unwrap();
+    assert_eq!(GameResult::Stalemate, stalemate.result());
+    let bishops = Game::from_fen("k7/8/1K6/8/8/3b4/2B5/8 w - - 0 1").unwrap();
+    assert_eq!(GameResult::InsufficientMaterial, bishops.result());
+    let opposite_bishops = Game::from_fen("k7/8/1K6/8/8/3b4/3B4/8 w - - 0 1").unwrap();
+    assert_eq!(GameResult::Ongoing, opposite_bishops.result());
+}
+
+#[test]
+fn game_draw_rules() {
+    let mut game = Game::new();
+    for _ in 0..2 {
+        for mv in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            game.make_move(&mv.parse().unwrap()).unwrap();
+        }
+    }
+    assert_eq!(3, game.repetitions());
+    assert_eq!(GameResult::Repetition, game.result());
+    assert_eq!(8, game.halfmove_clock());
+
+    let mut fifty = Game::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w Q - 99 80").unwrap();
+    fifty.make_move(&"a1a2".parse().unwrap()).unwrap();
+    assert_eq!(GameResult::FiftyMoves, fifty.result());
+    assert_eq!("4k3/8/8/8/8/8/R3P3/4K3 b - - 100 80", fifty.to_fen());
+    fifty.takeback();
+    fifty.make_move(&"e2e4".parse().unwrap()).unwrap();
+    assert_eq!(0, fifty.halfmove_clock());
+
+    let black_first = {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 12").unwrap();
+        game.make_san_move("Kd7").unwrap();
+        game.make_san_move("e4").unwrap();
+        game
+    };
+    assert_eq!("12... Kd7 13. e4", black_first.movetext());
+    assert_eq!(13, black_first.fullmove_number());
+}
+
+#[test]
+fn epd_operations() {
+    let line = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\"; c0 \"mate in 3; Qg6 fxg6\";";
+    let position: epd::EpdPosition = line.parse().unwrap();
+    assert_eq!(Some("WAC.001"), position.id());
+    assert_eq!(vec![position.board.parse_san("Qg6").unwrap()], position.san_moves("bm").unwrap());
+    assert!(position.san_moves("am").unwrap().is_empty());
+    assert_eq!(Some(&[String::from("mate in 3; Qg6 fxg6")][..]), position.operation("c0"));
+    assert_eq!(line, position.to_string());
+
+    let sts = "1kr5/3n4/q3p2p/p2n2p1/PppB1P2/5BP1/1P2Q2P/3R2K1 w - - bm f5; id \"STS(v1.0) Undermine.001\"; \
+        c0 \"f5=10, Be5+=2, Bf2=3, Bg4=2\"; c8 \"10 2 3 2\"; c9 \"f4f5 d4e5 d4f2 f3g4\";";
+    let position: epd::EpdPosition = sts.parse().unwrap();
+    let points = position.points().unwrap();
+    assert_eq!(4, points.len());
+    assert_eq!(("f4f5".parse().unwrap(), 10), points[0]);
+    assert_eq!(("d4e5".parse().unwrap(), 2), points[1]);
+    assert_eq!(None, position.direct_mate().unwrap());
+
+    let suite = epd::parse_epd("# mates\n\n8/8/8/8/8/5k2/8/5K1R w - - dm 2; hmvc 0;\n").unwrap();
+    assert_eq!(Some(2), suite[0].direct_mate().unwrap());
+    assert_eq!(Some(&[String::from("0")][..]), suite[0].operation("hmvc"));
+    assert!("8/8/8/8/8/5k2/8/5K1R w - - dm 2".parse::<epd::EpdPosition>().is_err());
+    assert!("8/8/8/8/8/5k2/8/5K1R w - - bm Qh8;".parse::<epd::EpdPosition>().unwrap().san_moves("bm").is_err());
+}
+
+#[test]
+fn pgn_writer() {
+    let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 3 12").unwrap();
+    assert_eq!("4k3/8/8/8/8/8/4P3/4K3 b - - 3 12", game.start_fen());
+    for san in ["Kd7", "e4", "Ke6"] {
+        game.make_san_move(san).unwrap();
+    }
+    assert_eq!("4k3/8/8/8/8/8/4P3/4K3 b - - 3 12", game.start_fen());
+    let mut written = pgn::PgnGame::from(game);
+    written.set_tag("White", "a \"quoted\" name");
+    let text = written.to_string();
+    assert!(text.starts_with("[Event \"?\"]\n"));
+    assert!(text.contains("[White \"a \\\"quoted\\\" name\"]\n"));
+    assert!(text.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 3 12\"]\n\n12... Kd7 13. e4 Ke6 *\n"));
+    // escaped tag values read back the same
+    written.set_tag("Event", "back\\slash [club]");
+    let read = pgn::parse_games(&written.to_string()).unwrap();
+    assert_eq!(Some("a \"quoted\" name"), read[0].tag("White"));
+    assert_eq!(Some("back\\slash [club]"), read[0].tag("Event"));
+    assert_eq!(3, read[0].game.moves().len());
+    assert!(pgn::parse_games("[Event]\n1. e4 *").is_err());
+
+    let mut long = Game::new();
+    for _ in 0..20 {
+        for mv in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            long.make_move(&mv.parse().unwrap()).unwrap();
+        }
+    }
+    let text = pgn::PgnGame::from(long).to_string();
+    assert!(!text.contains("FEN"));
+    assert!(text.lines().all(|line| line.len() <= 80));
+    assert!(text.ends_with(" 1/2-1/2\n"));
+    let read = pgn::parse_games(&text).unwrap();
+    assert_eq!(80, read[0].game.moves().len());
+    assert_eq!(Some("1/2-1/2"), read[0].tag("Result"));
+}
+
+#[test]
+fn board_diagram_and_checkers() {
+    let board = Board::from_fen(String::from("4k3/8/8/8/1b6/8/8/4K2R w K - 0 1")).unwrap();
+    let diagram = board.diagram(false, false);
+    let lines: Vec<&str> = diagram.lines().collect();
+    assert_eq!("8 |   |   |   |   | k |   |   |   |", lines[1]);
+    assert_eq!("1 |   |   |   |   | K |   |   | R |", lines[15]);
+    assert_eq!("    a   b   c   d   e   f   g   h", lines[17]);
+    let flipped = board.diagram(true, true);
+    let lines: Vec<&str> = flipped.lines().collect();
+    assert_eq!("1 | ♖ |   |   | ♔ |   |   |   |   |", lines[1]);
+    assert_eq!("    h   g   f   e   d   c   b   a", lines[17]);
+    assert_eq!(vec!["b4".parse::<Square>().unwrap()], board.checkers());
+    assert!(Board::new().checkers().is_empty());
 }
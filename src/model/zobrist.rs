@@ -0,0 +1,45 @@
+use lazy_static::*;
+
+pub struct ZobristKeys {
+    // indexed by Piece::index and square
+    pub pieces: [[u64; 64]; 12],
+    // white king, white queen, black king, black queen
+    pub castle: [u64; 4],
+    pub en_passant: [u64; 8],
+    pub black_to_move: u64,
+}
+
+// splitmix64, so that the keys are the same on every machine and every build
+fn next_key(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+lazy_static! {
+    pub static ref KEYS: ZobristKeys = {
+        let mut state = 0x63_7275_7374u64;
+        let mut pieces = [[0u64; 64]; 12];
+        for piece in pieces.iter_mut() {
+            for key in piece.iter_mut() {
+                *key = next_key(&mut state);
+            }
+        }
+        let mut castle = [0u64; 4];
+        for key in castle.iter_mut() {
+            *key = next_key(&mut state);
+        }
+        let mut en_passant = [0u64; 8];
+        for key in en_passant.iter_mut() {
+            *key = next_key(&mut state);
+        }
+        ZobristKeys {
+            pieces,
+            castle,
+            en_passant,
+            black_to_move: next_key(&mut state),
+        }
+    };
+}
@@ -0,0 +1,139 @@
+use crate::model::util::errors;
+use crate::model::Game;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// A UCI engine run as a child process. Its output is read on a thread, so that waiting for it can time out
+pub struct UciClient {
+    // from 'id name', the command when the engine doesn't send one
+    pub name: String,
+    command: String,
+    process: Child,
+    input: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl UciClient {
+    // The command may have arguments separated by spaces
+    pub fn start(command: &str) -> io::Result<UciClient> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or(errors::invalid_input(String::from("Expected an engine command")))?;
+        let mut process = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let input = process.stdin.take().unwrap();
+        let output = BufReader::new(process.stdout.take().unwrap());
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in output.lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut client = UciClient {
+            name: String::from(command),
+            command: String::from(command),
+            process,
+            input,
+            lines,
+        };
+        client.send("uci")?;
+        for line in client.read_until("uciok", None)? {
+            if let Some(name) = line.strip_prefix("id name ") {
+                client.name = String::from(name.trim());
+            }
+        }
+        Ok(client)
+    }
+
+    // Replaces a hanging engine with a new process
+    pub fn restart(&mut self) -> io::Result<()> {
+        *self = UciClient::start(&self.command)?;
+        Ok(())
+    }
+
+    pub fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.input, "{line}")?;
+        self.input.flush()
+    }
+
+    // The lines up to the one starting with `prefix`, which is the last one. Fails with TimedOut once `timeout` has passed
+    pub fn read_until(&mut self, prefix: &str, timeout: Option<Duration>) -> io::Result<Vec<String>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut lines = Vec::new();
+        loop {
+            let line = match deadline {
+                None => self.lines.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                Some(deadline) => self.lines.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            };
+            let line = match line {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        ErrorKind::TimedOut,
+                        format!("{} didn't send '{prefix}' in time", self.name),
+                    ))
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(errors::invalid_input(format!("{} quit before '{prefix}'", self.name))),
+            };
+            let done = line.trim().starts_with(prefix);
+            lines.push(String::from(line.trim()));
+            if done {
+                return Ok(lines);
+            }
+        }
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> io::Result<()> {
+        self.send(&format!("setoption name {name} value {value}"))
+    }
+
+    pub fn is_ready(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        self.read_until("readyok", None).map(|_| ())
+    }
+
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.is_ready()
+    }
+
+    // Sends the game as 'position fen ... moves ...' and 'go' with the arguments, the move is in UCI notation
+    pub fn best_move(&mut self, game: &Game, go: &str, timeout: Option<Duration>) -> io::Result<String> {
+        let mut position = format!("position fen {}", game.start_fen());
+        if !game.moves().is_empty() {
+            let moves: Vec<String> = game.moves().iter().map(|mv| mv.to_notation()).collect();
+            position.push_str(&format!(" moves {}", moves.join(" ")));
+        }
+        self.send(&position)?;
+        self.send(&format!("go {go}"))?;
+        let lines = self.read_until("bestmove", timeout)?;
+        let best = lines.last().unwrap().split_whitespace().nth(1);
+        best.map(String::from)
+            .ok_or(errors::invalid_input(format!("{} sent bestmove without a move", self.name)))
+    }
+}
+
+impl Drop for UciClient {
+    // engines that don't quit in time are killed
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.process.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
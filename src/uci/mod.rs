@@ -1,24 +1,40 @@
-use crate::model;
-use model::pieces;
-use model::util::*;
+use crate::engine::bench::BENCH_DEPTH;
+use crate::model::util::*;
+use crate::model::Move;
 use std::io::Result;
 
+pub mod client;
+pub mod options;
+mod tests;
+
 pub enum Position {
     Start,
     Fen(String),
 }
 
-#[allow(dead_code)]
+// Times are in milliseconds
+#[derive(Default)]
 pub struct GoCommand {
-    white_time: u64,
-    black_time: u64,
+    pub white_time: Option<u64>,
+    pub black_time: Option<u64>,
+    pub white_increment: Option<u64>,
+    pub black_increment: Option<u64>,
+    pub moves_to_go: Option<u32>,
+    pub move_time: Option<u64>,
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub infinite: bool,
+    pub ponder: bool,
+    pub search_moves: Vec<Move>,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub struct Move {
-    pub from: usize,
-    pub to: usize,
-    pub promote_to: Option<u8>,
+// 'go perft depth [divide plies] [hash mb] [threads count] [detailed]', divide is 1 by default
+pub struct PerftCommand {
+    pub depth: u8,
+    pub divide: u8,
+    pub hash_mb: usize,
+    pub threads: Option<usize>,
+    pub detailed: bool,
 }
 
 pub enum Command {
@@ -26,54 +42,56 @@ pub enum Command {
     IsReady,
     NewGame,
     SetPosition { position: Position, moves: Vec<Move> },
+    SetOption { name: String, value: Option<String> },
     Go(GoCommand),
-    Perft(u8),
+    Stop,
+    PonderHit,
+    Perft(PerftCommand),
+    // searches the built-in positions to the depth, BENCH_DEPTH by default
+    Bench { depth: u8 },
     Display,
 }
 
-impl Move {
-    pub fn from_notation(mv: &str) -> std::io::Result<Move> {
-        let from = square_notation_to_index(&mv[0..2])?;
-        let to = square_notation_to_index(&mv[2..4])?;
-        let promote_to = match mv.chars().nth(4) {
-            Some('q') | Some('Q') => Some(pieces::QUEEN),
-            Some('r') | Some('R') => Some(pieces::ROOK),
-            Some('k') | Some('K') => Some(pieces::KNIGHT),
-            Some('b') | Some('B') => Some(pieces::BISHOP),
-            Some(other) => return Result::Err(errors::invalid_input(format!("Unexpected promotion: '{}'", other))),
-            None => None,
-        };
-        Ok(Move { from, to, promote_to })
-    }
-}
-
 impl Command {
     pub fn parse(raw: &str) -> Result<Command> {
         let mut split: std::str::SplitWhitespace<'_> = raw.split_whitespace();
-        match split.next().ok_or(errors::invalid_input(format!("Unexpected empty uci input")))? {
+        match split.next().ok_or(errors::invalid_input(String::from("Unexpected empty uci input")))? {
             "uci" => Result::Ok(Command::Uci),
             "isready" => Result::Ok(Command::IsReady),
             "ucinewgame" => Result::Ok(Command::NewGame),
             "position" => parse_position_command(split),
+            "setoption" => parse_setoption_command(split),
             "go" => parse_go_command(split),
+            "stop" => Result::Ok(Command::Stop),
+            "ponderhit" => Result::Ok(Command::PonderHit),
             "d" => Result::Ok(Command::Display),
+            "bench" => Result::Ok(Command::Bench {
+                depth: match split.next() {
+                    None => BENCH_DEPTH,
+                    depth => parse_number("bench", depth)?,
+                },
+            }),
             other => Result::Err(errors::invalid_input(format!("Unexpected uci input: '{}'", other))),
         }
     }
 }
 
-fn parse_position_command(mut split: std::str::SplitWhitespace<'_>) -> Result<Command> {
+fn parse_position_command(split: std::str::SplitWhitespace<'_>) -> Result<Command> {
+    let mut split = split.peekable();
     let position: Position = match split
         .next()
-        .ok_or(errors::invalid_input(format!("Unexpected empty input after 'position'")))?
+        .ok_or(errors::invalid_input(String::from("Unexpected empty input after 'position'")))?
     {
         "fen" => {
-            let pieces = split.next().expect("Expected fen pieces");
-            let active_color = split.next().expect("Expected fen active_color");
-            let castling = split.next().expect("Expected fen castling");
-            let en_passant = split.next().expect("Expected fen en_passant");
-            let half_moves = split.next().expect("Expected fen half_moves");
-            let moves = split.next().expect("Expected fen moves");
+            let mut field = |name: &str| split.next().ok_or(errors::invalid_input(format!("Expected fen {name}")));
+            let (pieces, active_color) = (field("pieces")?, field("active_color")?);
+            let (castling, en_passant) = (field("castling")?, field("en_passant")?);
+            // the clocks may be left out
+            let mut clock = |default: &'static str| match split.peek() {
+                None | Some(&"moves") => default,
+                Some(_) => split.next().unwrap(),
+            };
+            let (half_moves, moves) = (clock("0"), clock("1"));
             Position::Fen(format!("{pieces} {active_color} {castling} {en_passant} {half_moves} {moves}"))
         }
         "startpos" => Position::Start,
@@ -83,8 +101,7 @@ fn parse_position_command(mut split: std::str::SplitWhitespace<'_>) -> Result<Co
     match split.next() {
         Some("moves") => {
             for move_notation in split {
-                let mv = Move::from_notation(&move_notation)?;
-                moves.push(mv);
+                moves.push(move_notation.parse()?);
             }
         }
         Some(other) => {
@@ -96,38 +113,79 @@ fn parse_position_command(mut split: std::str::SplitWhitespace<'_>) -> Result<Co
     Result::Ok(Command::SetPosition { position, moves })
 }
 
-fn parse_go_command(mut split: std::str::SplitWhitespace<'_>) -> Result<Command> {
-    let mut result: Command = Command::Go(GoCommand{
-        white_time: u64::MAX,
-        black_time: u64::MAX
-    });
+// setoption name <id> [value <x>], both id and value may contain spaces
+fn parse_setoption_command(mut split: std::str::SplitWhitespace<'_>) -> Result<Command> {
+    if split.next() != Some("name") {
+        return Result::Err(errors::invalid_input(String::from("Expected 'name' after 'setoption'")));
+    }
+    let mut name: Vec<&str> = Vec::new();
+    let mut value: Option<Vec<&str>> = None;
+    for token in split {
+        match value.as_mut() {
+            Some(value) => value.push(token),
+            None if token == "value" => value = Some(Vec::new()),
+            None => name.push(token),
+        }
+    }
+    if name.is_empty() {
+        return Result::Err(errors::invalid_input(String::from("Expected option name after 'setoption name'")));
+    }
+    Result::Ok(Command::SetOption {
+        name: name.join(" "),
+        value: value.map(|value| value.join(" ")),
+    })
+}
+
+fn parse_go_command(split: std::str::SplitWhitespace<'_>) -> Result<Command> {
+    let mut go = GoCommand::default();
+    let mut split = split.peekable();
     while let Some(arg) = split.next() {
         match arg {
-            "wtime" => {
-                result = Command::Go(GoCommand {
-                    white_time: parse_time(&mut split)?,
-                    black_time: 0, //                    black_time: parse_time(&mut split)?,
-                });
-            }
-            "perft" => {
-                let depth_string: &str = split.next().ok_or(errors::invalid_input(format!("expected depth for perft")))?;
-                let depth: u8 = depth_string
-                    .parse()
-                    .or(Result::Err(errors::invalid_input(format!("Invalid depth format"))))?;
-                result = Command::Perft(depth);
+            "wtime" => go.white_time = Some(parse_number(arg, split.next())?),
+            "btime" => go.black_time = Some(parse_number(arg, split.next())?),
+            "winc" => go.white_increment = Some(parse_number(arg, split.next())?),
+            "binc" => go.black_increment = Some(parse_number(arg, split.next())?),
+            "movestogo" => go.moves_to_go = Some(parse_number(arg, split.next())?),
+            "movetime" => go.move_time = Some(parse_number(arg, split.next())?),
+            "depth" => go.depth = Some(parse_number(arg, split.next())?),
+            "nodes" => go.nodes = Some(parse_number(arg, split.next())?),
+            "infinite" => go.infinite = true,
+            "ponder" => go.ponder = true,
+            "searchmoves" => {
+                while let Some(mv) = split.next_if(|token| token.parse::<Move>().is_ok()) {
+                    go.search_moves.push(mv.parse()?);
+                }
             }
+            "perft" => return parse_perft_command(parse_number(arg, split.next())?, split),
             _ => (),
         }
     }
-    Result::Ok(result)
+    Result::Ok(Command::Go(go))
 }
 
-fn parse_time(source: &mut std::str::SplitWhitespace<'_>) -> Result<u64> {
-    let time_string = source
-        .next()
-        .ok_or(errors::invalid_input(format!("Unexpected time input after 'wtime'")))?;
-    let time_u64: u64 = time_string.parse().or(Result::Err(errors::invalid_input(format!(
-        "wtime was not a number but '{time_string}'"
-    ))))?;
-    Ok(time_u64)
+fn parse_perft_command<'a>(depth: u8, mut split: impl Iterator<Item = &'a str>) -> Result<Command> {
+    let mut perft = PerftCommand {
+        depth,
+        divide: 1,
+        hash_mb: 0,
+        threads: None,
+        detailed: false,
+    };
+    while let Some(arg) = split.next() {
+        match arg {
+            "divide" => perft.divide = parse_number(arg, split.next())?,
+            "hash" => perft.hash_mb = parse_number(arg, split.next())?,
+            "threads" => perft.threads = Some(parse_number(arg, split.next())?),
+            "detailed" => perft.detailed = true,
+            other => return Result::Err(errors::invalid_input(format!("Unexpected perft argument: '{other}'"))),
+        }
+    }
+    Result::Ok(Command::Perft(perft))
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: Option<&str>) -> Result<T> {
+    let value = value.ok_or(errors::invalid_input(format!("Expected a value after '{name}'")))?;
+    value
+        .parse()
+        .or(Result::Err(errors::invalid_input(format!("{name} was not a number but '{value}'"))))
 }
@@ -0,0 +1,120 @@
+use crate::model::util::errors;
+use std::fmt::{Display, Formatter};
+use std::io::Result;
+
+pub const HASH: &str = "Hash";
+pub const CLEAR_HASH: &str = "Clear Hash";
+pub const THREADS: &str = "Threads";
+pub const MULTI_PV: &str = "MultiPV";
+pub const PONDER: &str = "Ponder";
+pub const MOVE_OVERHEAD: &str = "Move Overhead";
+pub const PAWN_VALUE: &str = "PawnValue";
+pub const KNIGHT_VALUE: &str = "KnightValue";
+pub const BISHOP_VALUE: &str = "BishopValue";
+pub const ROOK_VALUE: &str = "RookValue";
+pub const QUEEN_VALUE: &str = "QueenValue";
+pub const UCI_OPPONENT: &str = "UCI_Opponent";
+pub const UCI_CHESS960: &str = "UCI_Chess960";
+pub const OWN_BOOK: &str = "OwnBook";
+pub const BOOK_FILE: &str = "BookFile";
+pub const BEST_BOOK_MOVE: &str = "BestBookMove";
+pub const SYZYGY_PATH: &str = "SyzygyPath";
+pub const TABLEBASE_PATH: &str = "TablebasePath";
+pub const SKILL_LEVEL: &str = "Skill Level";
+pub const UCI_LIMIT_STRENGTH: &str = "UCI_LimitStrength";
+pub const UCI_ELO: &str = "UCI_Elo";
+
+#[derive(Debug)]
+pub enum OptionType {
+    Spin { default: i64, min: i64, max: i64 },
+    Check { default: bool },
+    Combo { default: &'static str, vars: &'static [&'static str] },
+    String { default: &'static str },
+    Button,
+}
+
+#[derive(Debug)]
+pub struct UciOption {
+    pub name: &'static str,
+    pub kind: OptionType,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum OptionValue {
+    Spin(i64),
+    Check(bool),
+    Combo(String),
+    String(String),
+    Button,
+}
+
+pub static OPTIONS: &[UciOption] = &[
+    UciOption { name: HASH, kind: OptionType::Spin { default: 16, min: 1, max: 4096 } },
+    UciOption { name: CLEAR_HASH, kind: OptionType::Button },
+    UciOption { name: THREADS, kind: OptionType::Spin { default: 1, min: 1, max: 256 } },
+    UciOption { name: MULTI_PV, kind: OptionType::Spin { default: 1, min: 1, max: 256 } },
+    UciOption { name: PONDER, kind: OptionType::Check { default: false } },
+    UciOption { name: MOVE_OVERHEAD, kind: OptionType::Spin { default: 10, min: 0, max: 5000 } },
+    UciOption { name: PAWN_VALUE, kind: OptionType::Spin { default: 100, min: 0, max: 2000 } },
+    UciOption { name: KNIGHT_VALUE, kind: OptionType::Spin { default: 300, min: 0, max: 2000 } },
+    UciOption { name: BISHOP_VALUE, kind: OptionType::Spin { default: 310, min: 0, max: 2000 } },
+    UciOption { name: ROOK_VALUE, kind: OptionType::Spin { default: 600, min: 0, max: 3000 } },
+    UciOption { name: QUEEN_VALUE, kind: OptionType::Spin { default: 900, min: 0, max: 5000 } },
+    UciOption { name: UCI_OPPONENT, kind: OptionType::String { default: "" } },
+    UciOption { name: UCI_CHESS960, kind: OptionType::Check { default: false } },
+    UciOption { name: OWN_BOOK, kind: OptionType::Check { default: false } },
+    UciOption { name: BOOK_FILE, kind: OptionType::String { default: "" } },
+    UciOption { name: BEST_BOOK_MOVE, kind: OptionType::Check { default: false } },
+    UciOption { name: SYZYGY_PATH, kind: OptionType::String { default: "" } },
+    UciOption { name: TABLEBASE_PATH, kind: OptionType::String { default: "" } },
+    UciOption { name: SKILL_LEVEL, kind: OptionType::Spin { default: 20, min: 0, max: 20 } },
+    UciOption { name: UCI_LIMIT_STRENGTH, kind: OptionType::Check { default: false } },
+    UciOption { name: UCI_ELO, kind: OptionType::Spin { default: 1500, min: 600, max: 2600 } },
+];
+
+// Option names are case insensitive in UCI
+pub fn find(name: &str) -> Option<&'static UciOption> {
+    OPTIONS.iter().find(|option| option.name.eq_ignore_ascii_case(name))
+}
+
+impl UciOption {
+    pub fn parse(&self, value: Option<&str>) -> Result<OptionValue> {
+        let name = self.name;
+        match (&self.kind, value) {
+            (OptionType::Button, _) => Ok(OptionValue::Button),
+            (OptionType::String { .. }, None | Some("<empty>")) => Ok(OptionValue::String(String::new())),
+            (OptionType::String { .. }, Some(value)) => Ok(OptionValue::String(String::from(value))),
+            (_, None) => Err(errors::invalid_input(format!("Expected a value for option {name}"))),
+            (OptionType::Spin { min, max, .. }, Some(value)) => match value.parse::<i64>() {
+                Ok(number) if (*min..=*max).contains(&number) => Ok(OptionValue::Spin(number)),
+                _ => Err(errors::invalid_input(format!("Option {name} expects a number in {min}..{max} but got '{value}'"))),
+            },
+            (OptionType::Check { .. }, Some(value)) => match value.to_lowercase().as_str() {
+                "true" => Ok(OptionValue::Check(true)),
+                "false" => Ok(OptionValue::Check(false)),
+                _ => Err(errors::invalid_input(format!("Option {name} expects true or false but got '{value}'"))),
+            },
+            (OptionType::Combo { vars, .. }, Some(value)) => match vars.iter().find(|var| var.eq_ignore_ascii_case(value)) {
+                Some(var) => Ok(OptionValue::Combo(String::from(*var))),
+                None => Err(errors::invalid_input(format!("Option {name} expects one of {vars:?} but got '{value}'"))),
+            },
+        }
+    }
+}
+
+impl Display for UciOption {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "option name {} type ", self.name)?;
+        match &self.kind {
+            OptionType::Spin { default, min, max } => write!(f, "spin default {default} min {min} max {max}"),
+            OptionType::Check { default } => write!(f, "check default {default}"),
+            OptionType::Combo { default, vars } => {
+                write!(f, "combo default {default}")?;
+                vars.iter().try_for_each(|var| write!(f, " var {var}"))
+            }
+            OptionType::String { default: "" } => write!(f, "string default <empty>"),
+            OptionType::String { default } => write!(f, "string default {default}"),
+            OptionType::Button => write!(f, "button"),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+#[cfg(test)]
+use crate::uci::*;
+#[cfg(test)]
+use crate::model::PieceKind;
+
+#[test]
+fn parse_go_limits() {
+    let Ok(Command::Go(go)) = Command::parse("go wtime 1000 btime 2000 winc 10 binc 20 movestogo 5 depth 8 nodes 100000") else {
+        panic!("Expected go command")
+    };
+    assert_eq!(Some(1000), go.white_time);
+    assert_eq!(Some(2000), go.black_time);
+    assert_eq!(Some(10), go.white_increment);
+    assert_eq!(Some(20), go.black_increment);
+    assert_eq!(Some(5), go.moves_to_go);
+    assert_eq!(Some(8), go.depth);
+    assert_eq!(Some(100000), go.nodes);
+    assert!(!go.infinite);
+}
+
+#[test]
+fn parse_go_search_moves() {
+    let Ok(Command::Go(go)) = Command::parse("go searchmoves e2e4 d2d4 infinite") else {
+        panic!("Expected go command")
+    };
+    assert!(go.infinite);
+    assert_eq!(2, go.search_moves.len());
+    assert_eq!("d2d4".parse::<Move>().unwrap(), go.search_moves[1]);
+}
+
+#[test]
+fn parse_invalid_go() {
+    assert!(Command::parse("go depth").is_err());
+    assert!(Command::parse("go nodes many").is_err());
+    assert!("e2".parse::<Move>().is_err());
+    assert!("e9e4".parse::<Move>().is_err());
+    assert!("e7e8k".parse::<Move>().is_err());
+    assert_eq!(Some(PieceKind::Knight), "e7e8n".parse::<Move>().unwrap().promote_to());
+}
+
+#[test]
+fn parse_perft() {
+    let Ok(Command::Perft(perft)) = Command::parse("go perft 5 divide 2 hash 16 threads 4 detailed") else {
+        panic!("Expected perft command")
+    };
+    assert_eq!((5, 2, 16, Some(4), true), (perft.depth, perft.divide, perft.hash_mb, perft.threads, perft.detailed));
+    let Ok(Command::Perft(perft)) = Command::parse("go perft 3") else {
+        panic!("Expected perft command")
+    };
+    assert_eq!((3, 1, 0, None, false), (perft.depth, perft.divide, perft.hash_mb, perft.threads, perft.detailed));
+    assert!(Command::parse("go perft 3 divide").is_err());
+    assert!(Command::parse("go perft 3 fast").is_err());
+}
+
+#[test]
+fn parse_bench() {
+    let Ok(Command::Bench { depth }) = Command::parse("bench") else {
+        panic!("Expected bench command")
+    };
+    assert_eq!(crate::engine::bench::BENCH_DEPTH, depth);
+    assert!(matches!(Command::parse("bench 6"), Ok(Command::Bench { depth: 6 })));
+    assert!(Command::parse("bench deep").is_err());
+}
+
+#[test]
+fn parse_position() {
+    let fen = |input: &str| match Command::parse(input) {
+        Ok(Command::SetPosition { position: Position::Fen(fen), moves }) => (fen, moves.len()),
+        _ => panic!("Expected position command"),
+    };
+    // the clocks default to '0 1'
+    let bare = "4k3/8/8/8/8/8/8/4K3 w - - 0 1";
+    assert_eq!((String::from(bare), 0), fen("position fen 4k3/8/8/8/8/8/8/4K3 w - -"));
+    assert_eq!((String::from(bare), 1), fen("position fen 4k3/8/8/8/8/8/8/4K3 w - - moves e1e2"));
+    assert_eq!((String::from("4k3/8/8/8/8/8/8/4K3 b - - 12 40"), 0), fen("position fen 4k3/8/8/8/8/8/8/4K3 b - - 12 40"));
+    assert!(Command::parse("position fen 4k3/8/8/8/8/8/8/4K3 w").is_err());
+    assert!(Command::parse("position fen").is_err());
+}
+
+#[test]
+fn option_lines() {
+    let lines: Vec<String> = options::OPTIONS.iter().map(|option| option.to_string()).collect();
+    assert!(lines.contains(&String::from("option name Hash type spin default 16 min 1 max 4096")));
+    assert!(lines.contains(&String::from("option name Clear Hash type button")));
+    assert!(lines.contains(&String::from("option name Ponder type check default false")));
+    assert!(lines.contains(&String::from("option name UCI_Opponent type string default <empty>")));
+}
+
+#[test]
+fn option_values() {
+    let threads = options::find("threads").unwrap();
+    assert_eq!(options::OptionValue::Spin(4), threads.parse(Some("4")).unwrap());
+    assert!(threads.parse(Some("0")).is_err());
+    assert!(threads.parse(Some("four")).is_err());
+    assert!(threads.parse(None).is_err());
+
+    let ponder = options::find("Ponder").unwrap();
+    assert_eq!(options::OptionValue::Check(true), ponder.parse(Some("true")).unwrap());
+    assert!(ponder.parse(Some("yes")).is_err());
+
+    let combo = options::UciOption {
+        name: "Style",
+        kind: options::OptionType::Combo { default: "Solid", vars: &["Solid", "Risky"] },
+    };
+    assert_eq!("option name Style type combo default Solid var Solid var Risky", combo.to_string());
+    assert_eq!(options::OptionValue::Combo(String::from("Risky")), combo.parse(Some("risky")).unwrap());
+    assert!(combo.parse(Some("Random")).is_err());
+    assert!(options::find("Unknown").is_none());
+}
+
+#[test]
+fn parse_set_option() {
+    let Ok(Command::SetOption { name, value }) = Command::parse("setoption name Clear Hash") else {
+        panic!("Expected setoption command")
+    };
+    assert_eq!("Clear Hash", name);
+    assert!(value.is_none());
+
+    let Ok(Command::SetOption { name, value }) = Command::parse("setoption name UCI_Opponent value GM 2800 human Some Name") else {
+        panic!("Expected setoption command")
+    };
+    assert_eq!("UCI_Opponent", name);
+    assert_eq!(Some(String::from("GM 2800 human Some Name")), value);
+}
+
+#[test]
+fn set_engine_options() {
+    let mut engine = crate::engine::Engine::new();
+    for input in [
+        "setoption name Hash value 2",
+        "setoption name Threads value 3",
+        "setoption name MultiPV value 2",
+        "setoption name Move Overhead value 50",
+        "setoption name QueenValue value 950",
+        "setoption name Clear Hash",
+    ] {
+        engine.execute_uci(Command::parse(input).unwrap()).unwrap();
+    }
+    assert_eq!(2, engine.settings.hash_mb);
+    assert_eq!(3, engine.settings.threads);
+    assert_eq!(2, engine.settings.multi_pv);
+    assert_eq!(std::time::Duration::from_millis(50), engine.settings.move_overhead);
+    assert_eq!(950, engine.settings.eval.piece_value(PieceKind::Queen));
+    assert!(engine.execute_uci(Command::parse("setoption name Threads value 1000").unwrap()).is_err());
+    assert!(engine.execute_uci(Command::parse("setoption name Foo value 1").unwrap()).is_err());
+}
+
+#[test]
+fn chess960_option_and_positions() {
+    let mut engine = crate::engine::Engine::new();
+    // a king on b1 castles only in Chess960 notation, switching the option off keeps it
+    for input in ["setoption name UCI_Chess960 value true", "position fen 4k3/8/8/8/8/8/PP6/RK6 w A - 0 1"] {
+        engine.execute_uci(Command::parse(input).unwrap()).unwrap();
+    }
+    engine.execute_uci(Command::parse("setoption name UCI_Chess960 value false").unwrap()).unwrap();
+    assert!(engine.board().chess960);
+    let moves = engine.board().gen_moves(false);
+    let castle = moves.iter().find(|mv| mv.is_castle()).unwrap();
+    assert_eq!("b1a1", castle.to_notation());
+    assert_eq!(1, moves.iter().filter(|mv| mv.to_notation() == "b1c1").count());
+
+    // the standard position goes back to standard notation
+    for input in ["setoption name UCI_Chess960 value true", "position startpos", "setoption name UCI_Chess960 value false"] {
+        engine.execute_uci(Command::parse(input).unwrap()).unwrap();
+    }
+    assert!(!engine.board().chess960);
+}
@@ -0,0 +1,32 @@
+use crust::{Board, Engine};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Counts every allocation of this test binary, so keep a single test in here
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn perft_does_not_allocate() {
+    let mut engine = Engine::new();
+    engine.game = Board::from_fen(String::from("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")).unwrap().into();
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    assert_eq!(97_862, engine.performance_test(3));
+    assert_eq!(0, ALLOCATIONS.load(Ordering::Relaxed) - before);
+}
@@ -0,0 +1,282 @@
+use crust::engine::book::BookBuilder;
+use crust::engine::perft::suite::{Reference, SuiteRunner};
+use crust::engine::tablebase::{Dtm, Tables};
+use crust::engine::tournament::{self, MatchScore, MatchSettings};
+use crust::uci::client::UciClient;
+use crust::uci::Command;
+use crust::{Board, CollectingOutput, Engine, EvalParams, Info, Move, Response, Score, SearchLimits, Square};
+use std::io::{BufRead, BufReader, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+// the board doesn't keep move clocks, so only the position part of a FEN is compared
+fn position(fen: &str) -> String {
+    fen.split(' ').take(4).collect::<Vec<_>>().join(" ")
+}
+
+fn perft(board: &Board, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    board.gen_moves(false).iter().map(|mv| perft(&board.make_move(mv), depth - 1)).sum()
+}
+
+#[test]
+fn fen_round_trip() {
+    let board = Board::from_fen(String::from(KIWIPETE)).unwrap();
+    assert_eq!(position(KIWIPETE), position(&board.to_fen()));
+    assert_eq!(Board::new().hash, Board::default().hash);
+    assert!(Board::from_fen(String::from("not a fen")).is_err());
+}
+
+#[test]
+fn move_generation() {
+    let board = Board::from_fen(String::from(KIWIPETE)).unwrap();
+    assert_eq!(48, perft(&board, 1));
+    assert_eq!(2_039, perft(&board, 2));
+    assert_eq!(8_902, perft(&Board::new(), 3));
+}
+
+#[test]
+fn san_moves() {
+    let mut board = Board::new();
+    let moves = ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Bxc6", "dxc6", "O-O"];
+    for san in moves {
+        let mv = board.parse_san(san).unwrap();
+        assert_eq!(san, board.to_san(&mv));
+        board = board.make_move(&mv);
+    }
+    assert_eq!("r1bqkbnr/1pp2ppp/p1p5/4p3/4P3/5N2/PPPP1PPP/RNBQ1RK1 b kq -", position(&board.to_fen()));
+}
+
+#[test]
+fn evaluation() {
+    let board = Board::from_fen(String::from("4k3/4p3/8/8/8/8/4P3/3QK3 w - - 0 1")).unwrap();
+    assert_eq!(900, board.evaluate(&EvalParams::default(), 0));
+}
+
+#[test]
+fn stalemate_evaluation() {
+    let params = EvalParams::default();
+    let stalemate = Board::from_fen(String::from("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1")).unwrap();
+    assert_eq!(0, stalemate.evaluate(&params, 0));
+    let mate = Board::from_fen(String::from("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1")).unwrap();
+    assert!(mate.evaluate(&params, 0) < -(i32::MAX - 100));
+}
+
+#[test]
+fn search_with_limits() {
+    let mut engine = Engine::with_output(Arc::new(CollectingOutput::default()));
+    engine.game = Board::from_fen(String::from("k7/6R1/8/8/8/8/8/6KR w - - 0 1")).unwrap().into();
+    let mv = engine.search(&SearchLimits {
+        depth: Some(2),
+        ..Default::default()
+    });
+    assert_eq!(Some(Move::regular(Square::H1, Square::H8)), mv);
+}
+
+#[test]
+fn search_callbacks() {
+    let lines = Arc::new(AtomicUsize::new(0));
+    let counter = lines.clone();
+    let mut engine = Engine::with_output(Arc::new(move |response: Response| {
+        if let Response::Info(Info::Line { score, .. }) = response {
+            assert_eq!(Score::Mate(1), score);
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }));
+    engine.game = Board::from_fen(String::from("k7/6R1/8/8/8/8/8/6KR w - - 0 1")).unwrap().into();
+    let lines_found = engine.search_lines(&SearchLimits {
+        depth: Some(3),
+        ..Default::default()
+    });
+    assert_eq!(Move::regular(Square::H1, Square::H8), lines_found[0].mv);
+    assert_eq!(3, lines.load(Ordering::Relaxed));
+}
+
+#[test]
+fn uci_session() {
+    let output = Arc::new(CollectingOutput::default());
+    let mut engine = Engine::with_output(output.clone());
+    for line in ["uci", "position startpos moves e2e4 e7e5", "go depth 2"] {
+        assert!(engine.execute_line(line));
+    }
+    engine.execute_uci(Command::Stop).unwrap();
+    let lines = output.lines();
+    assert!(lines.contains(&String::from("uciok")));
+    assert!(lines.last().unwrap().starts_with("bestmove "));
+}
+
+#[test]
+fn chess960_session() {
+    let output = Arc::new(CollectingOutput::default());
+    let mut engine = Engine::with_output(output.clone());
+    // king takes rook castles, e1f1 ends with the king on g1 and the rook on f1
+    for line in ["setoption name UCI_Chess960 value true", "position fen 4k3/8/8/8/8/8/8/4KR2 w F - 0 1 moves e1f1"] {
+        assert!(engine.execute_line(line));
+    }
+    assert!(output.lines().is_empty());
+    assert_eq!("4k3/8/8/8/8/8/8/5RK1 b - -", position(&engine.board().to_fen()));
+}
+
+#[test]
+fn own_book_session() {
+    let mut builder = BookBuilder::new(4, 1);
+    builder.add_pgn("1. d4 d5 2. c4 *").unwrap();
+    let path = std::env::temp_dir().join(format!("crust-book-{}.bin", std::process::id()));
+    builder.build().save(&path).unwrap();
+
+    let output = Arc::new(CollectingOutput::default());
+    let mut engine = Engine::with_output(output.clone());
+    let book_file = format!("setoption name BookFile value {}", path.display());
+    for line in [book_file.as_str(), "setoption name OwnBook value true", "position startpos moves d2d4", "go depth 5"] {
+        assert!(engine.execute_line(line));
+    }
+    engine.execute_uci(Command::Stop).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(vec![String::from("bestmove d7d5")], output.lines());
+    assert!(engine.execute_line("setoption name BookFile value /nonexistent/book.bin"));
+    assert_eq!(2, output.lines().len());
+}
+
+#[test]
+fn syzygy_path_option() {
+    let directory = std::env::temp_dir().join(format!("crust-tables-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let output = Arc::new(CollectingOutput::default());
+    let mut engine = Engine::with_output(output.clone());
+    assert!(engine.execute_line(&format!("setoption name SyzygyPath value {}", directory.display())));
+    std::fs::remove_dir(&directory).unwrap();
+    assert_eq!(vec![String::from("info string found 0 tablebases with up to 0 pieces")], output.lines());
+    for line in ["setoption name SyzygyPath value /nonexistent/syzygy", "setoption name SyzygyPath value <empty>"] {
+        assert!(engine.execute_line(line));
+    }
+    assert_eq!(2, output.lines().len());
+}
+
+#[test]
+fn generated_tables_in_search() {
+    let mut tables = Tables::new();
+    tables.generate(&"KRK".parse().unwrap());
+    let directory = std::env::temp_dir().join(format!("crust-ctb-{}", std::process::id()));
+    tables.save(&directory).unwrap();
+
+    let output = Arc::new(CollectingOutput::default());
+    let mut engine = Engine::with_output(output.clone());
+    assert!(engine.execute_line(&format!("setoption name TablebasePath value {}", directory.display())));
+    std::fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(vec![String::from("info string found 1 endgame tables with up to 3 pieces")], output.lines());
+
+    // a long mate is found right away, with the distance from the table
+    let fen = "8/8/8/4k3/8/8/8/R3K3 w - - 0 1";
+    let Some(Dtm::Win(plies)) = tables.probe(&Board::from_fen(String::from(fen)).unwrap()) else {
+        panic!("KRK is won");
+    };
+    for line in [format!("position fen {fen}"), String::from("go depth 2")] {
+        assert!(engine.execute_line(&line));
+    }
+    engine.wait_for_search();
+    let mate = format!("score mate {}", plies.div_ceil(2));
+    assert!(output.lines().iter().any(|line| line.contains(&mate) && !line.contains("tbhits 0 ")));
+    assert!(engine.execute_line("setoption name TablebasePath value /nonexistent/tables"));
+}
+
+#[test]
+fn perft_suite_reference() {
+    let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    let mut reference = UciClient::start(env!("CARGO_BIN_EXE_crust")).unwrap();
+    let divide = reference.divide(fen, &[String::from("e2a6")], 2).unwrap();
+    assert_eq!(36, divide.len());
+    assert!(divide.contains(&(String::from("b4c3"), 52)));
+    let runner = SuiteRunner::new(1, 1);
+    assert_eq!(None, runner.bisect(fen, 3, &mut reference).unwrap());
+}
+
+#[test]
+fn match_between_engines() {
+    let engine = String::from(env!("CARGO_BIN_EXE_crust"));
+    let settings = MatchSettings {
+        engines: [engine.clone(), format!("{engine} ")],
+        time_control: "2+0.1".parse().unwrap(),
+        games: 2,
+        concurrency: 2,
+        sprt: None,
+    };
+    // a mate in two for white, each engine wins it once
+    let openings = tournament::load_openings("7k/8/5K2/8/8/8/8/6Q1 w - -", false).unwrap();
+    let mut reported = Vec::new();
+    let score = tournament::run_match(&settings, &openings, |played, _, names| {
+        assert_eq!(settings.engines, *names);
+        reported.push((played.number, played.white, played.result(), played.termination.clone()));
+    })
+    .unwrap();
+    reported.sort();
+    assert_eq!(
+        vec![(0, 0, "1-0", String::from("white mates")), (1, 1, "1-0", String::from("white mates"))],
+        reported
+    );
+    assert_eq!(
+        MatchScore {
+            wins: 1,
+            draws: 0,
+            losses: 1
+        },
+        score
+    );
+}
+
+#[test]
+fn protocol_from_first_command() {
+    let start = || {
+        std::process::Command::new(env!("CARGO_BIN_EXE_crust"))
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap()
+    };
+    let mut xboard = start();
+    let mut input = xboard.stdin.take().unwrap();
+    input.write_all(b"\nxboard\nprotover 2\nping 1\nsetboard k7/6R1/8/8/8/8/8/6KR w - - 0 1\nsd 2\ngo\n").unwrap();
+    let output: Vec<String> = BufReader::new(xboard.stdout.take().unwrap())
+        .lines()
+        .map(Result::unwrap)
+        .take_while(|line| !line.starts_with("1-0"))
+        .collect();
+    input.write_all(b"quit\n").unwrap();
+    assert!(xboard.wait().unwrap().success());
+    assert!(output.ends_with(&[String::from("feature done=1"), String::from("pong 1"), String::from("move h1h8")]), "{output:?}");
+
+    let mut uci = start();
+    uci.stdin.take().unwrap().write_all(b"uci\nquit\n").unwrap();
+    let output = String::from_utf8(uci.wait_with_output().unwrap().stdout).unwrap();
+    assert!(output.contains("uciok\n"));
+    assert!(!output.contains("feature"));
+}
+
+#[test]
+fn analysis_report() {
+    let analyze = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_crust"))
+            .arg("analyze")
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let report = analyze(&["r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4", "--depth", "2"]);
+    assert_eq!("Best move Qxf7#, score #1 at depth 2, pv Qxf7#\n", report);
+
+    let games = std::env::temp_dir().join(format!("crust-analysis-{}.pgn", std::process::id()));
+    let annotated = std::env::temp_dir().join(format!("crust-annotated-{}.pgn", std::process::id()));
+    std::fs::write(&games, "[White \"A\"]\n[Black \"B\"]\n\n1. e4 e5 2. Qh5 Nc6 3. Qxf7+ Kxf7 *\n").unwrap();
+    let report = analyze(&[games.to_str().unwrap(), "--depth", "2", "--output", annotated.to_str().unwrap()]);
+    assert!(report.starts_with("Game 1: A - B\n"));
+    assert!(report.contains("White: inaccuracies 0, mistakes 0, blunders 1"));
+    let text = std::fs::read_to_string(&annotated).unwrap().replace('\n', " ");
+    assert!(text.contains("3. Qxf7+ $4 {-8.00 Blunder. Best was"));
+    std::fs::remove_file(&games).unwrap();
+    std::fs::remove_file(&annotated).unwrap();
+}
@@ -0,0 +1,43 @@
+use crust::{Board, Engine};
+
+// Positions of the Chess960 perft suite with the expected node counts per depth
+fn suite() -> Vec<(String, Vec<(u8, u64)>)> {
+    let mut positions: Vec<(String, Vec<(u8, u64)>)> = Vec::new();
+    for line in include_str!("data/chess960.perft").lines() {
+        let mut parts = line.splitn(2, ' ');
+        match (parts.next(), parts.next()) {
+            (Some("epd"), Some(fen)) => positions.push((String::from(fen), Vec::new())),
+            (Some("perft"), Some(expected)) => {
+                let (depth, nodes) = expected.split_once(' ').expect("Expected depth and node count");
+                let position = positions.last_mut().expect("perft before epd");
+                position.1.push((depth.parse().unwrap(), nodes.parse().unwrap()));
+            }
+            _ => (),
+        }
+    }
+    positions
+}
+
+// Runs every depth with at most `max_nodes` nodes
+fn run_suite(max_nodes: u64) {
+    let positions = suite();
+    assert_eq!(960, positions.len());
+    let mut engine = Engine::new();
+    for (fen, counts) in positions {
+        engine.game = Board::from_fen(fen.clone()).unwrap().into();
+        for (depth, nodes) in counts.into_iter().filter(|&(_, nodes)| nodes <= max_nodes) {
+            assert_eq!(nodes, engine.performance_test(depth), "{fen} at depth {depth}");
+        }
+    }
+}
+
+#[test]
+fn chess960_perft_suite() {
+    run_suite(2_000);
+}
+
+#[test]
+#[ignore = "takes minutes, run with --release --ignored"]
+fn chess960_perft_suite_deep() {
+    run_suite(5_000_000);
+}